@@ -0,0 +1,63 @@
+//! Instruction dispatch throughput: `cargo bench -p chip8-core`. Compare
+//! against a baseline saved before a change with `-- --save-baseline NAME`
+//! and `-- --baseline NAME`, on a quiet machine.
+
+use chip8_core::chip8::{Chip8, Error};
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion, Throughput};
+use std::hint::black_box;
+
+const STEPS: u64 = 100_000;
+
+/// A tight loop of arithmetic, skips and jumps, the kind of code that
+/// dominates at high instructions per second.
+const ALU_LOOP: [u8; 14] = [
+    0x60, 0x01, // LD V0, 1
+    0x71, 0x01, // ADD V1, 1
+    0x80, 0x14, // ADD V0, V1
+    0x82, 0x03, // XOR V2, V0
+    0x32, 0x00, // SE V2, 0
+    0xA3, 0x00, // LD I, 0x300
+    0x12, 0x02, // JP 0x202
+];
+
+/// Steps a clone of `fresh` `STEPS` times with `step`, generic so that
+/// each way of stepping is inlined into its own loop.
+fn bench_steps<F>(group: &mut BenchmarkGroup<WallTime>, how: &str, fresh: &Chip8, step: F)
+where
+    F: Fn(&mut Chip8) -> Result<(), Error>,
+{
+    group.bench_function(how, |b| {
+        b.iter(|| {
+            let mut cpu = fresh.clone();
+            for _ in 0..STEPS {
+                black_box(step(&mut cpu)).unwrap();
+            }
+            cpu
+        })
+    });
+}
+
+/// Each ROM runs through `step`, which dispatches on the `DISPATCH` table,
+/// and through `step_decoded`, the `decode_opcode` and `Opcode` match it
+/// replaced, as the baseline.
+fn bench_rom(c: &mut Criterion, name: &str, rom: &[u8]) {
+    let mut fresh = Chip8::new(7);
+    fresh.load_rom_bytes(rom).unwrap();
+
+    let mut group = c.benchmark_group(format!("dispatch/{}", name));
+    group.throughput(Throughput::Elements(STEPS));
+    bench_steps(&mut group, "table", &fresh, Chip8::step);
+    bench_steps(&mut group, "decoded", &fresh, Chip8::step_decoded);
+    group.finish();
+}
+
+fn dispatch(c: &mut Criterion) {
+    bench_rom(c, "alu_loop", &ALU_LOOP);
+
+    let tetris = concat!(env!("CARGO_MANIFEST_DIR"), "/../../roms/TETRIS");
+    bench_rom(c, "tetris", &std::fs::read(tetris).unwrap());
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
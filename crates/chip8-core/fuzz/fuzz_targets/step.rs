@@ -0,0 +1,57 @@
+//! Loads arbitrary bytes as a ROM under an arbitrary configuration and runs
+//! it, from `crates/chip8-core`:
+//!
+//!     cargo +nightly fuzz run step
+//!
+//! Guest faults are expected; any panic is a bug in the core.
+
+#![no_main]
+
+use chip8_core::chip8::{Chip8, Extensions, Quirks, MAX_STACK_LIMIT, VIP_DRAW_BUDGET};
+use libfuzzer_sys::fuzz_target;
+
+/// Instructions per input, enough for loops to get going.
+const STEPS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    // The first four bytes pick the configuration, the rest is the ROM
+    let Some((config, rom)) = data.split_first_chunk::<4>() else {
+        return;
+    };
+    let bits = u32::from_le_bytes(*config);
+    let bit = |n: u32| bits >> n & 1 != 0;
+
+    let mut cpu = Chip8::new(bits as u64);
+    cpu.set_extensions(Extensions {
+        rtc: bit(0),
+        banking: bit(1),
+        light_pen: bit(2),
+        frame_counter: bit(3),
+    });
+    cpu.set_quirks(Quirks {
+        wrap_x: bit(4),
+        wrap_y: bit(5),
+        shift_vy: bit(6),
+        load_store_increment: bit(7),
+        jump_vx: bit(8),
+        vf_reset: bit(9),
+        display_wait: bit(10),
+    });
+    cpu.set_trace_memory(bit(11));
+    cpu.set_draw_budget(bit(12).then_some(VIP_DRAW_BUDGET));
+    cpu.set_stack_limit(1 + (bits >> 16) as usize % MAX_STACK_LIMIT);
+
+    if cpu.load_rom_bytes(rom).is_err() {
+        return;
+    }
+
+    for n in 0..STEPS {
+        if n % 20 == 0 {
+            cpu.tick_timers();
+            cpu.set_keypad(n / 20 % 16, bit(13 + (n / 20 % 3) as u32));
+        }
+        if cpu.step().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,2235 @@
+use crate::isa::{decode_opcode, Opcode};
+use crate::memory::Memory;
+#[cfg(feature = "alloc")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::fmt;
+use core::ops::{Index, IndexMut};
+#[cfg(feature = "std")]
+use std::io;
+
+pub const VIDEO_WIDTH: usize = 64;
+pub const VIDEO_HEIGHT: usize = 32;
+// The framebuffer is always SUPER-CHIP sized; in low resolution every pixel
+// is stored as a 2x2 block. Each pixel holds one bit per XO-CHIP plane.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+/// Bytes in the framebuffer rendered by `Chip8::render_rgba`.
+pub const RGBA_SIZE: usize = HIRES_WIDTH * HIRES_HEIGHT * 4;
+
+// XO-CHIP's 16-bit address space; plain CHIP-8 programs only use 4K of it
+pub const MEMORY_SIZE: usize = 0x10000;
+pub const MEMORY_START: usize = 0x200;
+const NUM_KEYS: usize = 16;
+const NUM_REGS: usize = 16;
+
+// With the banking extension, the top of the original 4K is a window onto
+// the part of the ROM file that doesn't fit below it.
+const BANK_WINDOW_START: usize = 0xE00;
+#[cfg(feature = "alloc")]
+const BANK_SIZE: usize = 0x200;
+
+pub const DEFAULT_STACK_LIMIT: usize = 16;
+/// Sprite rows a COSMAC VIP draws in a frame: its interpreter waits for
+/// the vertical blank before each DRW, so one sprite of up to 15 rows.
+pub const VIP_DRAW_BUDGET: u16 = 15;
+pub const MAX_STACK_LIMIT: usize = 64;
+
+const FONTSET_START_ADDRESS: usize = 0x50;
+const FONTSET_SIZE: usize = 5 * 16;
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// SUPER-CHIP 8x10 digits for Fx30, right after the small font
+const BIG_FONTSET_START_ADDRESS: usize = FONTSET_START_ADDRESS + FONTSET_SIZE;
+const BIG_FONTSET: [u8; 10 * 10] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+];
+
+/// Flag registers for Fx75/Fx85: SUPER-CHIP has 8, XO-CHIP one per
+/// register, and a SUPER-CHIP program never uses more than its 8.
+const NUM_FLAGS: usize = 16;
+
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+// A 500 Hz square wave at the default pitch, for ROMs that never load a
+// pattern of their own
+pub const DEFAULT_AUDIO_PATTERN: [u8; AUDIO_PATTERN_SIZE] = [0xF0; AUDIO_PATTERN_SIZE];
+pub const DEFAULT_PITCH: u8 = 64;
+
+// The last two bytes of the interpreter area, clear of both fonts
+const FRAME_COUNTER_ADDRESS: usize = MEMORY_START - 2;
+
+/// Non-standard opcodes, all off by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Extensions {
+    /// FxE0 - LD [I], RTC: stores hours, minutes and seconds at I..I+2, as
+    /// given by the `set_clock` function. The command-line frontends give
+    /// the time in UTC.
+    pub rtc: bool,
+    /// FxF8 - BANK Vx: maps 512-byte bank Vx of an oversized ROM to 0xE00
+    pub banking: bool,
+    /// FxF9 - LD [I], PEN: stores the mouse x, y and buttons at I..I+2
+    pub light_pen: bool,
+    /// A read-only big-endian count of frames at 0x1FE..0x1FF
+    pub frame_counter: bool,
+}
+
+/// What went wrong in the guest program. `pc` is the address of the
+/// instruction that caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    InvalidInstruction { pc: u16, op: u16 },
+    StackOverflow { pc: u16 },
+    StackUnderflow { pc: u16 },
+    MemoryOutOfBounds { pc: u16, addr: usize },
+    InvalidKey { pc: u16, key: u8 },
+    InvalidBank { pc: u16, bank: usize },
+}
+
+impl Fault {
+    pub fn pc(&self) -> u16 {
+        match *self {
+            Fault::InvalidInstruction { pc, .. }
+            | Fault::StackOverflow { pc }
+            | Fault::StackUnderflow { pc }
+            | Fault::MemoryOutOfBounds { pc, .. }
+            | Fault::InvalidKey { pc, .. }
+            | Fault::InvalidBank { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidInstruction { pc, op } => {
+                write!(f, "invalid instruction {:#06X} at {:#05X}", op, pc)
+            }
+            Fault::StackOverflow { pc } => write!(f, "stack overflow at {:#05X}", pc),
+            Fault::StackUnderflow { pc } => write!(f, "stack underflow at {:#05X}", pc),
+            Fault::MemoryOutOfBounds { pc, addr } => {
+                write!(f, "memory access {:#X} out of bounds at {:#05X}", addr, pc)
+            }
+            Fault::InvalidKey { pc, key } => write!(f, "invalid key {:#X} at {:#05X}", key, pc),
+            Fault::InvalidBank { pc, bank } => write!(f, "invalid bank {} at {:#05X}", bank, pc),
+        }
+    }
+}
+
+/// Instructions shown either side of a fault.
+#[cfg(feature = "alloc")]
+const FAULT_CONTEXT: u16 = 3;
+
+/// A guest fault with the state around it, so that a report of one says
+/// what the program was doing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub fault: Fault,
+    /// `(address, word)` of the instructions around the faulting one,
+    /// assuming word-aligned code and leaving out any outside memory.
+    #[cfg(feature = "alloc")]
+    pub code: Vec<(u16, u16)>,
+    pub registers: [u8; NUM_REGS],
+    pub index: u16,
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.fault)?;
+
+        for &(addr, op) in &self.code {
+            let marker = if addr == self.fault.pc() { '>' } else { ' ' };
+            let text = decode_opcode(op).map_or("??".to_string(), |opcode| opcode.mnemonic());
+            writeln!(f, "  {} {:#05X}  {:04X}  {}", marker, addr, op, text)?;
+        }
+
+        for (row, half) in self.registers.chunks(NUM_REGS / 2).enumerate() {
+            let regs: Vec<String> = half
+                .iter()
+                .enumerate()
+                .map(|(n, value)| format!("V{:X} {:02X}", row * NUM_REGS / 2 + n, value))
+                .collect();
+            writeln!(f, "  {}", regs.join("  "))?;
+        }
+        write!(f, "  I {:#05X}", self.index)
+    }
+}
+
+/// Why a ROM couldn't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// Without banking, a ROM must fit between 0x200 and the end of memory.
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => e.fmt(f),
+            LoadError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, but at most {} fit", size, max)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+/*
+    Save state format, version 2. Multi-byte numbers are big-endian. With
+    d the stack depth and s = 65562 + 2d:
+
+    offset      size    field
+    0           4       magic "C8ST"
+    4           1       version, 2
+    5           65536   memory 0x0000-0xFFFF
+    65541       16      V0-VF
+    65557       2       I
+    65559       2       PC
+    65561       1       stack depth d, at most 64
+    65562       2d      return addresses, outermost call first
+    s           8192    display, 128x64 bytes row by row; bit 0 is plane 1,
+                        bit 1 plane 2, and in low resolution each pixel is
+                        stored as a 2x2 block
+    s+8192      1       high resolution, 0 or 1
+    s+8193      1       selected planes, 0-3
+    s+8194      16      flag registers; SUPER-CHIP only uses the first 8
+    s+8210      16      XO-CHIP audio pattern
+    s+8226      1       XO-CHIP pitch
+    s+8227      16      keypad, keys 0-F, 1 if held
+    s+8243      1       delay timer
+    s+8244      1       sound timer
+    s+8245      3       light pen x, y, buttons
+    s+8248      2       frame counter
+    s+8250              end; a state with no stack is 73812 bytes
+
+    Readers check the magic, then accept only versions they know. Any
+    change to the layout gets a new version, so within a version fields are
+    never moved, resized or added. Quirks, extensions, the stack limit and
+    ROM banks are configuration, not state, and aren't stored. Version 1
+    was the same but with 8 flag registers, so it was 8 bytes shorter.
+
+    A state is corrupt if it is truncated, has bytes past the end, a stack
+    deeper than 64, planes above 3 or display bytes above 3.
+*/
+
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+const STATE_VERSION: u8 = 2;
+
+/// Why a save state couldn't be loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion(u8),
+    /// Truncated, too long, or holding out-of-range values.
+    Corrupt,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(v) => {
+                write!(f, "unsupported save state version {}", v)
+            }
+            StateError::Corrupt => write!(f, "corrupt save state"),
+        }
+    }
+}
+
+/// Reads a save state field by field.
+struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        if n > self.data.len() {
+            return Err(StateError::Corrupt);
+        }
+
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        self.take(N)?.try_into().map_err(|_| StateError::Corrupt)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        let [byte] = self.bytes()?;
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_be_bytes(self.bytes()?))
+    }
+}
+
+/// RGBA colours indexed by a pixel's plane bits: unlit, plane 1, plane 2
+/// and both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [[u8; 4]; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            colors: [
+                [0, 0, 0, 255],
+                [255, 255, 255, 255],
+                [170, 170, 170, 255],
+                [85, 85, 85, 255],
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// A palette of two colours, as plain CHIP-8 needs. The XO-CHIP planes
+    /// get shades between them, the way the default palette does between
+    /// black and white.
+    pub fn new(background: [u8; 4], foreground: [u8; 4]) -> Palette {
+        let mix = |thirds: u16| {
+            let mut color = [0; 4];
+            for (c, (&bg, &fg)) in color.iter_mut().zip(background.iter().zip(&foreground)) {
+                *c = ((bg as u16 * (3 - thirds) + fg as u16 * thirds) / 3) as u8;
+            }
+            color
+        };
+        Palette {
+            colors: [background, foreground, mix(2), mix(1)],
+        }
+    }
+
+    /// Renders `video`, pixels of plane bits as from `Chip8::get_video`,
+    /// into `out` as packed RGBA. See `Chip8::render_rgba`.
+    pub fn render(self, video: &[u8], out: &mut [u8]) {
+        assert_eq!(out.len(), video.len() * 4, "RGBA buffer is the wrong size");
+        for (rgba, &pixel) in out.chunks_exact_mut(4).zip(video) {
+            if let Some(color) = self.colors.get(pixel as usize & 3) {
+                rgba.copy_from_slice(color);
+            }
+        }
+    }
+
+    /// Amber phosphor.
+    pub fn amber() -> Palette {
+        Palette {
+            colors: [
+                [26, 16, 0, 255],
+                [255, 176, 0, 255],
+                [178, 112, 0, 255],
+                [102, 64, 0, 255],
+            ],
+        }
+    }
+
+    /// Green phosphor.
+    pub fn green() -> Palette {
+        Palette {
+            colors: [
+                [0, 20, 0, 255],
+                [51, 255, 51, 255],
+                [32, 170, 32, 255],
+                [16, 96, 16, 255],
+            ],
+        }
+    }
+
+    /// The four greens of the original Game Boy screen.
+    pub fn gameboy() -> Palette {
+        Palette {
+            colors: [
+                [155, 188, 15, 255],
+                [15, 56, 15, 255],
+                [48, 98, 48, 255],
+                [139, 172, 15, 255],
+            ],
+        }
+    }
+
+    /// The colours Octo, the XO-CHIP IDE, starts with.
+    pub fn octo() -> Palette {
+        Palette {
+            colors: [
+                [153, 102, 0, 255],
+                [255, 204, 0, 255],
+                [255, 102, 0, 255],
+                [102, 34, 0, 255],
+            ],
+        }
+    }
+}
+
+/// Behaviours that differ between CHIP-8 platforms. The defaults match what
+/// this interpreter has always done.
+#[derive(Debug, Clone, Copy)]
+pub struct Quirks {
+    /// Sprites running off the right edge wrap to the left; otherwise clip
+    pub wrap_x: bool,
+    /// Sprites running off the bottom edge wrap to the top; otherwise clip
+    pub wrap_y: bool,
+    /// 8xy6/8xyE shift Vy into Vx; otherwise they shift Vx in place
+    pub shift_vy: bool,
+    /// Fx55/Fx65 leave I pointing past the last register stored or loaded
+    pub load_store_increment: bool,
+    /// Bxnn jumps to xnn + Vx; otherwise to xnn + V0
+    pub jump_vx: bool,
+    /// 8xy1/8xy2/8xy3 clear VF
+    pub vf_reset: bool,
+    /// Dxyn waits for the vertical blank, ending the frame
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            wrap_x: true,
+            wrap_y: true,
+            shift_vy: false,
+            load_store_increment: false,
+            jump_vx: false,
+            vf_reset: false,
+            display_wait: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn chip8() -> Quirks {
+        Quirks {
+            wrap_x: false,
+            wrap_y: false,
+            shift_vy: true,
+            load_store_increment: true,
+            jump_vx: false,
+            vf_reset: true,
+            display_wait: true,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP-48.
+    pub fn schip() -> Quirks {
+        Quirks {
+            wrap_x: false,
+            wrap_y: false,
+            shift_vy: false,
+            load_store_increment: false,
+            jump_vx: true,
+            vf_reset: false,
+            display_wait: false,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo.
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            wrap_x: true,
+            wrap_y: true,
+            shift_vy: true,
+            load_store_increment: true,
+            jump_vx: false,
+            vf_reset: false,
+            display_wait: false,
+        }
+    }
+}
+
+/// Something a step did that observers outside the core may care about.
+/// Each step reports `InstructionExecuted` first, followed by any others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuestEvent {
+    /// The instruction word at `pc`; `decode_opcode` turns it into an
+    /// `Opcode`.
+    InstructionExecuted { pc: u16, op: u16 },
+    /// DRW, with the wrapped start position and whether a pixel was erased.
+    SpriteDrawn {
+        x: u8,
+        y: u8,
+        height: u8,
+        collision: bool,
+    },
+    /// CLS, or 00FE/00FF switching resolution, cleared the display.
+    DisplayCleared { pc: u16 },
+    /// One of the SUPER-CHIP or XO-CHIP scrolls moved the display.
+    DisplayScrolled { pc: u16 },
+    /// Fx18 started the sound timer from silence.
+    SoundStarted { ticks: u8 },
+    /// Fx18 cut off a running sound timer by setting it to zero. The timer
+    /// running out by itself shows in `is_sound_on` after `tick_timers`.
+    SoundStopped { pc: u16 },
+    /// DRW with the display wait quirk: the sprite is drawn, and the
+    /// rest of the frame is skipped as the VIP waited for the vertical
+    /// blank.
+    VblankWaited { pc: u16 },
+    /// Fx0A found no key pressed and will run again.
+    KeyWaited { pc: u16 },
+    /// Fx07 read a delay timer that hasn't expired, as busy-wait loops do.
+    TimerPolled { pc: u16 },
+    /// CALL, with the stack depth after the push.
+    StackPushed { pc: u16, depth: usize },
+    /// SCHIP's EXIT asked to quit. PC stays on the EXIT, so every later
+    /// step reports this again.
+    Exited { pc: u16 },
+    /// DRW took the sprite rows drawn since the last `tick_timers` past
+    /// the budget set with `set_draw_budget`, to `rows`. Reported once a
+    /// frame.
+    DrawBudgetExceeded { pc: u16, rows: u16 },
+    /// BRK asked an attached debugger to pause after it.
+    BreakRequested { pc: u16 },
+    /// A data read, with memory tracing on.
+    MemoryRead { pc: u16, addr: usize },
+    /// A data write, with memory tracing on. Reported even if the write is
+    /// dropped, as with the frame counter.
+    MemoryWritten { pc: u16, addr: usize, value: u8 },
+}
+
+/// What one `cycle` did that a frontend may want to act on, so it needn't
+/// poll or diff the machine's state to find out. A set of flags, read
+/// from the step's events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CycleStatus(u32);
+
+impl CycleStatus {
+    /// A sprite was drawn, or the display cleared or scrolled; redraw.
+    pub const DREW_TO_SCREEN: CycleStatus = CycleStatus(1);
+    /// Fx0A found no key held and will run again.
+    pub const WAITING_FOR_KEY: CycleStatus = CycleStatus(2);
+    /// A draw is waiting for the vertical blank, with the display wait
+    /// quirk; the frame should end here.
+    pub const WAITING_FOR_VBLANK: CycleStatus = CycleStatus(4);
+    /// The sound timer started from silence; start the beep.
+    pub const SOUND_STARTED: CycleStatus = CycleStatus(8);
+    /// The sound timer was set to zero while running; stop the beep.
+    pub const SOUND_STOPPED: CycleStatus = CycleStatus(16);
+    /// SCHIP's EXIT ran.
+    pub const EXITED: CycleStatus = CycleStatus(32);
+
+    /// The flags the events of one step add up to.
+    pub fn from_events(events: &[GuestEvent]) -> CycleStatus {
+        let bits = events
+            .iter()
+            .map(|event| match event {
+                GuestEvent::SpriteDrawn { .. }
+                | GuestEvent::DisplayCleared { .. }
+                | GuestEvent::DisplayScrolled { .. } => CycleStatus::DREW_TO_SCREEN,
+                GuestEvent::KeyWaited { .. } => CycleStatus::WAITING_FOR_KEY,
+                GuestEvent::VblankWaited { .. } => CycleStatus::WAITING_FOR_VBLANK,
+                GuestEvent::SoundStarted { .. } => CycleStatus::SOUND_STARTED,
+                GuestEvent::SoundStopped { .. } => CycleStatus::SOUND_STOPPED,
+                GuestEvent::Exited { .. } => CycleStatus::EXITED,
+                _ => CycleStatus::default(),
+            })
+            .fold(0, |bits, status| bits | status.0);
+        CycleStatus(bits)
+    }
+
+    /// Whether all of `flags` are set.
+    pub fn contains(self, flags: CycleStatus) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    /// The flags as a mask, for bindings.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// Most events one step can report: with memory tracing on, a 16x16 sprite
+/// drawn on both XO-CHIP planes reads 64 bytes, besides the instruction,
+/// the draw itself, the vertical blank wait and going over the draw budget.
+const MAX_EVENTS: usize = 68;
+
+/// The events of one step, kept in place so stepping never allocates.
+#[derive(Debug, Clone)]
+struct Events {
+    buf: [GuestEvent; MAX_EVENTS],
+    len: usize,
+}
+
+impl Events {
+    fn new() -> Events {
+        Events {
+            buf: [GuestEvent::Exited { pc: 0 }; MAX_EVENTS],
+            len: 0,
+        }
+    }
+
+    /// Drops anything past `MAX_EVENTS`, which no instruction reaches.
+    fn push(&mut self, event: GuestEvent) {
+        if let Some(slot) = self.buf.get_mut(self.len) {
+            *slot = event;
+            self.len += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn as_slice(&self) -> &[GuestEvent] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+}
+
+/// Runs the instruction word `op`, fetched from `pc`.
+type Handler = fn(&mut Chip8, u16, u16) -> Result<(), Fault>;
+
+/// Instruction handlers by top nibble. Execution decodes straight from the
+/// instruction word, taking only the fields each handler needs; `Opcode`
+/// is for tooling. Between them the handlers accept exactly the encodings
+/// `decode_opcode` does, which the tests check word by word. Against
+/// `step_decoded`, which gets to the same handlers through `decode_opcode`
+/// and a match on the `Opcode`, `benches/dispatch.rs` measured the table
+/// taking 11-22% less time per instruction on its ALU loop and 31-39% less
+/// on TETRIS, over three runs.
+const DISPATCH: [Handler; 16] = [
+    Chip8::exec_0,
+    Chip8::exec_jp,
+    Chip8::exec_call,
+    Chip8::exec_se_byte,
+    Chip8::exec_sne_byte,
+    Chip8::exec_5,
+    Chip8::exec_ld_byte,
+    Chip8::exec_add_byte,
+    Chip8::exec_alu,
+    Chip8::exec_sne_reg,
+    Chip8::exec_ld_i,
+    Chip8::exec_jp_v0,
+    Chip8::exec_rnd,
+    Chip8::exec_drw,
+    Chip8::exec_keys,
+    Chip8::exec_f,
+];
+
+// The operand fields of an instruction word
+fn x(op: u16) -> usize {
+    (op >> 8) as usize & 0xF
+}
+
+fn y(op: u16) -> usize {
+    (op >> 4) as usize & 0xF
+}
+
+fn n(op: u16) -> u8 {
+    (op & 0xF) as u8
+}
+
+fn byte(op: u16) -> u8 {
+    (op & 0xFF) as u8
+}
+
+fn addr(op: u16) -> u16 {
+    op & 0xFFF
+}
+
+/// V0 to VF. Indexing uses the low nibble, like the instruction fields the
+/// index comes from, so it never goes out of range.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Registers([u8; NUM_REGS]);
+
+impl Index<usize> for Registers {
+    type Output = u8;
+
+    #[allow(clippy::indexing_slicing)] // n & 0xF < NUM_REGS
+    fn index(&self, n: usize) -> &u8 {
+        &self.0[n & 0xF]
+    }
+}
+
+impl IndexMut<usize> for Registers {
+    #[allow(clippy::indexing_slicing)] // n & 0xF < NUM_REGS
+    fn index_mut(&mut self, n: usize) -> &mut u8 {
+        &mut self.0[n & 0xF]
+    }
+}
+
+/// CXNN's random numbers, SplitMix64. Each instance has its own, so
+/// instances started from the same seed draw the same sequence however the
+/// others run, and a clone carries on from where it was taken.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_byte(&mut self) -> u8 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        ((z ^ (z >> 31)) >> 56) as u8
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Chip8 {
+    mem: Memory,
+    reg: Registers,
+
+    i: u16,
+    pc: u16,
+    stack: [u16; MAX_STACK_LIMIT],
+    /// How many entries of `stack` are in use.
+    sp: usize,
+    stack_limit: usize,
+    video: [u8; HIRES_HEIGHT * HIRES_WIDTH],
+    /// Whether `video` may have changed since `take_video_dirty`.
+    video_dirty: bool,
+    hires: bool,
+    planes: u8,
+    flags: [u8; NUM_FLAGS],
+    audio: [u8; AUDIO_PATTERN_SIZE],
+    pitch: u8,
+    keypad: [bool; NUM_KEYS],
+
+    dt: u8,
+    st: u8,
+
+    rng: Rng,
+    clock: fn() -> (u8, u8, u8),
+    ext: Extensions,
+    quirks: Quirks,
+    events: Events,
+    /// ROM past the fixed area, with the banking extension. Banks need a
+    /// heap, so without `alloc` such ROMs don't load.
+    #[cfg(feature = "alloc")]
+    banks: Arc<[u8]>,
+    pen: (u8, u8, u8),
+    frames: u16,
+    trace_memory: bool,
+    /// Sprite rows a frame may draw before `DrawBudgetExceeded`.
+    draw_budget: Option<u16>,
+    /// Sprite rows drawn since the last `tick_timers`.
+    rows_drawn: u16,
+}
+
+impl fmt::Display for Chip8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, reg) in self.reg.0.iter().enumerate() {
+            writeln!(f, "[v{:X}]: {:#02X}", i, reg)?;
+        }
+
+        let pc = self.pc as usize;
+        let byte = |addr: usize| self.mem.get(addr).unwrap_or(0) as u16;
+        let op = (byte(pc) << 8) | byte(pc + 1);
+
+        writeln!(f, "[pc]: {:#02X}", self.pc)?;
+        writeln!(f, "[i]: {:#02X}", self.i)?;
+        writeln!(f, "[opcode]: {:#04X}", op)
+    }
+}
+
+/*
+    Boot sequence. Every machine starts in exactly this state, so traces
+    from different tools line up from the first instruction:
+
+    1.  All 64K of memory is zeroed.
+    2.  The 5-byte digits 0-F go at 0x050-0x09F and the 10-byte SUPER-CHIP
+        digits at 0x0A0-0x103.
+    3.  V0-VF, I, both timers, the stack pointer, the flag registers, the
+        keypad and the frame counter are zero.
+    4.  The display is clear and low resolution with plane 1 selected; the
+        audio pattern and pitch are XO-CHIP's defaults.
+    5.  PC is 0x200. Loading a ROM copies it there and, with banking, maps
+        bank 0; nothing else changes.
+    6.  Cycle 0 is the first `step`, fetching the word at 0x200. Nothing
+        runs before it: timers first tick after the first frame.
+*/
+
+impl Chip8 {
+    /// A machine in the boot state described above, before any ROM, whose
+    /// CXNN draws come from `seed`.
+    pub fn new(seed: u64) -> Chip8 {
+        let mut new_emu = Chip8 {
+            mem: Memory::new(),
+            reg: Registers::default(),
+
+            i: 0,
+            pc: MEMORY_START as u16,
+            stack: [0; MAX_STACK_LIMIT],
+            sp: 0,
+            stack_limit: DEFAULT_STACK_LIMIT,
+            video: [0; HIRES_HEIGHT * HIRES_WIDTH],
+            video_dirty: true,
+            hires: false,
+            planes: 1,
+            flags: [0; NUM_FLAGS],
+            audio: DEFAULT_AUDIO_PATTERN,
+            pitch: DEFAULT_PITCH,
+            keypad: [false; NUM_KEYS],
+
+            dt: 0,
+            st: 0,
+
+            rng: Rng(seed),
+            clock: || (0, 0, 0),
+            ext: Extensions::default(),
+            quirks: Quirks::default(),
+            events: Events::new(),
+            #[cfg(feature = "alloc")]
+            banks: Arc::from([]),
+            pen: (0, 0, 0),
+            frames: 0,
+            trace_memory: false,
+            draw_budget: None,
+            rows_drawn: 0,
+        };
+
+        new_emu.write_fonts();
+        new_emu
+    }
+
+    fn write_fonts(&mut self) {
+        // Both fonts sit well inside memory, so these always fit
+        let _ = self.mem.write_slice(FONTSET_START_ADDRESS, &FONTSET);
+        let _ = self
+            .mem
+            .write_slice(BIG_FONTSET_START_ADDRESS, &BIG_FONTSET);
+    }
+
+    /// Like a reset button: the boot sequence except that memory is kept, so
+    /// the loaded ROM starts over from 0x200. The fonts are written again in
+    /// case the program overwrote them, and with banking bank 0 is mapped
+    /// back in. Configuration, the flag registers, which persist like the
+    /// HP-48's, and the keypad and light pen, which the host holds, are kept.
+    pub fn reset(&mut self) {
+        let mem = core::mem::replace(&mut self.mem, Memory::new());
+        #[cfg(feature = "alloc")]
+        let banks = Arc::clone(&self.banks);
+
+        self.reset_hard();
+        self.mem = mem;
+        self.write_fonts();
+        #[cfg(feature = "alloc")]
+        if !banks.is_empty() {
+            self.banks = banks;
+            let _ = self.map_bank(MEMORY_START as u16, 0);
+        }
+    }
+
+    /// Powers the machine off and on: `reset` but with memory cleared and
+    /// ROM banks dropped as well, so a ROM has to be loaded again.
+    pub fn reset_hard(&mut self) {
+        let mut fresh = Chip8::new(0);
+        fresh.keep_config(self);
+        fresh.rng = self.rng.clone();
+        fresh.flags = self.flags;
+        fresh.keypad = self.keypad;
+        fresh.pen = self.pen;
+        *self = fresh;
+    }
+
+    /// Goes back to `snapshot`, a clone of this machine taken earlier. As
+    /// with `reset`, the configuration and the keypad and light pen, which
+    /// the host holds, are kept. Clones share memory pages until one of
+    /// them writes, so keeping recent clones is cheaper than saving states.
+    pub fn restore(&mut self, snapshot: &Chip8) {
+        let mut next = snapshot.clone();
+        next.keep_config(self);
+        next.keypad = self.keypad;
+        next.pen = self.pen;
+        next.video_dirty = true;
+        *self = next;
+    }
+
+    /// Copies what the host sets up rather than the guest: everything
+    /// `save_state` leaves out except the ROM banks and the random number
+    /// generator.
+    fn keep_config(&mut self, from: &Chip8) {
+        self.clock = from.clock;
+        self.ext = from.ext;
+        self.quirks = from.quirks;
+        self.stack_limit = from.stack_limit;
+        self.trace_memory = from.trace_memory;
+        self.draw_budget = from.draw_budget;
+    }
+
+    /// Serializes the machine: memory, registers, stack, timers, display,
+    /// keypad and the SUPER-CHIP/XO-CHIP state. Configuration (quirks,
+    /// extensions, stack limit) and ROM banks are not included, so load a
+    /// state into an instance set up the same way.
+    #[cfg(feature = "alloc")]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.mem.len() + self.video.len() + 256);
+        out.extend(STATE_MAGIC);
+        out.push(STATE_VERSION);
+        out.extend(self.mem.to_vec());
+        out.extend(self.reg.0);
+        out.extend(self.i.to_be_bytes());
+        out.extend(self.pc.to_be_bytes());
+        out.push(self.sp as u8);
+        for addr in self.get_stack() {
+            out.extend(addr.to_be_bytes());
+        }
+        out.extend(self.video);
+        out.push(self.hires as u8);
+        out.push(self.planes);
+        out.extend(self.flags);
+        out.extend(self.audio);
+        out.push(self.pitch);
+        out.extend(self.keypad.map(u8::from));
+        out.push(self.dt);
+        out.push(self.st);
+        out.extend([self.pen.0, self.pen.1, self.pen.2]);
+        out.extend(self.frames.to_be_bytes());
+        out
+    }
+
+    /// Restores a state from `save_state`. On error nothing is changed.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader { data };
+        if reader.take(STATE_MAGIC.len()) != Ok(STATE_MAGIC) {
+            return Err(StateError::NotAState);
+        }
+        let version = reader.u8()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let mut next = self.clone();
+        next.mem
+            .write_slice(0, reader.take(self.mem.len())?)
+            .ok_or(StateError::Corrupt)?;
+        next.reg = Registers(reader.bytes()?);
+        next.i = reader.u16()?;
+        next.pc = reader.u16()?;
+        let depth = reader.u8()? as usize;
+        if depth > MAX_STACK_LIMIT {
+            return Err(StateError::Corrupt);
+        }
+        for addr in next.stack.iter_mut().take(depth) {
+            *addr = reader.u16()?;
+        }
+        next.sp = depth;
+        next.video = reader.bytes()?;
+        next.hires = reader.u8()? != 0;
+        next.planes = reader.u8()?;
+        next.flags = reader.bytes()?;
+        next.audio = reader.bytes()?;
+        next.pitch = reader.u8()?;
+        next.keypad = reader.bytes::<NUM_KEYS>()?.map(|key| key != 0);
+        next.dt = reader.u8()?;
+        next.st = reader.u8()?;
+        let [x, y, buttons] = reader.bytes()?;
+        next.pen = (x, y, buttons);
+        next.frames = reader.u16()?;
+
+        if !reader.data.is_empty() || next.planes > 3 || next.video.iter().any(|&p| p > 3) {
+            return Err(StateError::Corrupt);
+        }
+
+        next.events.clear();
+        next.video_dirty = true;
+        *self = next;
+        Ok(())
+    }
+
+    /// Loads a ROM at 0x200. With the banking extension, anything past
+    /// 0xE00 is kept as banks of `BANK_SIZE` bytes and bank 0 is mapped in.
+    /// Set extensions before calling this.
+    pub fn load_rom_bytes(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let flat_end = match self.ext.banking {
+            true => BANK_WINDOW_START,
+            false => MEMORY_SIZE,
+        };
+
+        if data.len() <= flat_end - MEMORY_START {
+            let _ = self.mem.write_slice(MEMORY_START, data);
+            return Ok(());
+        }
+
+        #[cfg(feature = "alloc")]
+        if self.ext.banking {
+            let (fixed, banked) = data.split_at(BANK_WINDOW_START - MEMORY_START);
+            let _ = self.mem.write_slice(MEMORY_START, fixed);
+            self.banks = Arc::from(banked);
+            // The ROM overflowed the fixed area, so bank 0 is never empty
+            let _ = self.map_bank(MEMORY_START as u16, 0);
+            return Ok(());
+        }
+
+        Err(LoadError::RomTooLarge {
+            size: data.len(),
+            max: flat_end - MEMORY_START,
+        })
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn map_bank(&mut self, pc: u16, bank: usize) -> Result<(), Fault> {
+        Err(Fault::InvalidBank { pc, bank })
+    }
+
+    #[cfg(feature = "alloc")]
+    fn map_bank(&mut self, pc: u16, bank: usize) -> Result<(), Fault> {
+        let start = bank * BANK_SIZE;
+        if start >= self.banks.len() {
+            return Err(Fault::InvalidBank { pc, bank });
+        }
+
+        let banks = Arc::clone(&self.banks);
+        let page = banks
+            .get(start..banks.len().min(start + BANK_SIZE))
+            .ok_or(Fault::InvalidBank { pc, bank })?;
+        self.mem
+            .fill(BANK_WINDOW_START..BANK_WINDOW_START + BANK_SIZE, 0);
+        self.mem
+            .write_slice(BANK_WINDOW_START, page)
+            .ok_or(Fault::InvalidBank { pc, bank })
+    }
+
+    fn read(&mut self, pc: u16, addr: usize) -> Result<u8, Fault> {
+        if self.trace_memory {
+            self.events.push(GuestEvent::MemoryRead { pc, addr });
+        }
+
+        self.mem
+            .get(addr)
+            .ok_or(Fault::MemoryOutOfBounds { pc, addr })
+    }
+
+    fn write(&mut self, pc: u16, addr: usize, value: u8) -> Result<(), Fault> {
+        if self.trace_memory {
+            self.events
+                .push(GuestEvent::MemoryWritten { pc, addr, value });
+        }
+
+        // Stores to the frame counter are dropped, like writes to ROM
+        if self.ext.frame_counter && (FRAME_COUNTER_ADDRESS..MEMORY_START).contains(&addr) {
+            return Ok(());
+        }
+
+        let cell = self
+            .mem
+            .get_mut(addr)
+            .ok_or(Fault::MemoryOutOfBounds { pc, addr })?;
+        *cell = value;
+        Ok(())
+    }
+
+    fn key(&self, pc: u16, key: u8) -> Result<bool, Fault> {
+        self.keypad
+            .get(key as usize)
+            .copied()
+            .ok_or(Fault::InvalidKey { pc, key })
+    }
+
+    pub fn set_extensions(&mut self, ext: Extensions) {
+        self.ext = ext;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Reports every data read and write as an event. Off by default, since
+    /// a draw alone can read 32 bytes.
+    pub fn set_trace_memory(&mut self, trace: bool) {
+        self.trace_memory = trace;
+    }
+
+    /// Reports `DrawBudgetExceeded` when a frame draws more than `rows`
+    /// sprite rows, e.g. `VIP_DRAW_BUDGET` for ROMs meant for real
+    /// hardware. `None`, the default, turns the check off.
+    pub fn set_draw_budget(&mut self, rows: Option<u16>) {
+        self.draw_budget = rows;
+    }
+
+    /// Sets how many nested CALLs are allowed, from 1 to `MAX_STACK_LIMIT`.
+    /// `None`, leaving the limit as it was, for anything outside that.
+    pub fn set_stack_limit(&mut self, limit: usize) -> Option<()> {
+        if !(1..=MAX_STACK_LIMIT).contains(&limit) {
+            return None;
+        }
+        self.stack_limit = limit;
+        Some(())
+    }
+
+    /// Sets where FxE0 reads the time of day from. Without one it reads a
+    /// fixed 00:00:00.
+    pub fn set_clock(&mut self, clock: fn() -> (u8, u8, u8)) {
+        self.clock = clock;
+    }
+
+    /// The framebuffer, `HIRES_WIDTH` by `HIRES_HEIGHT` whatever the
+    /// resolution the guest has selected. Bit 0 of a pixel is plane 1 and
+    /// bit 1 is plane 2; plain CHIP-8 only ever uses plane 1.
+    pub fn get_video(&self) -> &[u8] {
+        &self.video
+    }
+
+    /// Whether the framebuffer may have changed since the last call, for
+    /// frontends to skip redrawing an unchanged one. A new machine, and one
+    /// that's just loaded a state, starts out dirty.
+    pub fn take_video_dirty(&mut self) -> bool {
+        core::mem::replace(&mut self.video_dirty, false)
+    }
+
+    /// Whether the guest has switched to the 128x64 SUPER-CHIP display.
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Renders the framebuffer into `out` as packed RGBA, row by row,
+    /// `HIRES_WIDTH * 4` bytes per row, for frontends to hand straight to a
+    /// texture or canvas. `out` must be `RGBA_SIZE` bytes.
+    pub fn render_rgba(&self, out: &mut [u8], palette: Palette) {
+        palette.render(&self.video, out);
+    }
+
+    /// Whether the sound timer is running.
+    pub fn is_sound_on(&self) -> bool {
+        self.st > 0
+    }
+
+    /// The 128-bit XO-CHIP audio pattern, played most significant bit
+    /// first while the sound timer runs.
+    pub fn get_audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio
+    }
+
+    /// The pitch register set by Fx3A. The pattern plays at
+    /// `4000 * 2^((pitch - 64) / 48)` bits a second.
+    pub fn get_pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Copies out the whole of guest memory.
+    #[cfg(feature = "alloc")]
+    pub fn get_memory(&self) -> Vec<u8> {
+        self.mem.to_vec()
+    }
+
+    pub fn read_memory(&self, addr: usize) -> Option<u8> {
+        self.mem.get(addr)
+    }
+
+    /// Changes a byte from outside the guest, e.g. from a debugger, and
+    /// returns the old value. `None` past the end of memory.
+    pub fn poke_memory(&mut self, addr: usize, value: u8) -> Option<u8> {
+        let cell = self.mem.get_mut(addr)?;
+        Some(core::mem::replace(cell, value))
+    }
+
+    pub fn set_register(&mut self, n: usize, value: u8) {
+        self.reg[n] = value;
+    }
+
+    pub fn set_index(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn get_registers(&self) -> &[u8] {
+        &self.reg.0
+    }
+
+    pub fn get_index(&self) -> u16 {
+        self.i
+    }
+
+    pub fn get_pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Return addresses, outermost call first.
+    pub fn get_stack(&self) -> &[u16] {
+        self.stack.get(..self.sp).unwrap_or_default()
+    }
+
+    /// The delay and sound timers.
+    pub fn get_timers(&self) -> (u8, u8) {
+        (self.dt, self.st)
+    }
+
+    pub fn get_keypad(&self) -> &[bool] {
+        &self.keypad
+    }
+
+    /// The events of the last step, in the order they happened.
+    pub fn events(&self) -> &[GuestEvent] {
+        self.events.as_slice()
+    }
+
+    /// Whether the last step was an EXIT, after which the frontend should
+    /// close or leave the program.
+    pub fn exited(&self) -> bool {
+        self.events()
+            .iter()
+            .any(|e| matches!(e, GuestEvent::Exited { .. }))
+    }
+
+    /// Whether the last step was a DRW waiting for the vertical blank, after
+    /// which the frame should end early.
+    pub fn waiting_for_vblank(&self) -> bool {
+        self.events()
+            .iter()
+            .any(|e| matches!(e, GuestEvent::VblankWaited { .. }))
+    }
+
+    /// Sets the light pen position in display pixels and its buttons as a
+    /// bitmask (bit 0 left, bit 1 right).
+    pub fn set_pen(&mut self, x: u8, y: u8, buttons: u8) {
+        self.pen = (x, y, buttons);
+    }
+
+    /// Presses or releases `key`, 0 to F. `None` for any other key.
+    pub fn set_keypad(&mut self, key: usize, value: bool) -> Option<()> {
+        *self.keypad.get_mut(key)? = value;
+        Some(())
+    }
+
+    /// The display size in the guest's current resolution.
+    fn resolution(&self) -> (usize, usize) {
+        match self.hires {
+            true => (HIRES_WIDTH, HIRES_HEIGHT),
+            false => (VIDEO_WIDTH, VIDEO_HEIGHT),
+        }
+    }
+
+    /// Flips a pixel of `plane` in the guest's current resolution and
+    /// returns whether it was lit.
+    fn flip(&mut self, x: usize, y: usize, plane: u8) -> bool {
+        let size = HIRES_WIDTH / self.resolution().0;
+        let mut was_lit = false;
+
+        for py in y * size..(y + 1) * size {
+            for px in x * size..(x + 1) * size {
+                if let Some(pixel) = self.video.get_mut(py * HIRES_WIDTH + px) {
+                    was_lit |= *pixel & plane != 0;
+                    *pixel ^= plane;
+                }
+            }
+        }
+
+        was_lit
+    }
+
+    /// Moves the selected planes by whole pixels of the current resolution,
+    /// filling in with unlit pixels.
+    fn scroll(&mut self, pc: u16, dx: isize, dy: isize) {
+        self.video_dirty = true;
+        self.events.push(GuestEvent::DisplayScrolled { pc });
+        let size = (HIRES_WIDTH / self.resolution().0) as isize;
+        let (dx, dy) = (dx * size, dy * size);
+        let old = self.video;
+
+        for y in 0..HIRES_HEIGHT {
+            for x in 0..HIRES_WIDTH {
+                let sx = x as isize - dx;
+                let sy = y as isize - dy;
+                let inside = (0..HIRES_WIDTH as isize).contains(&sx)
+                    && (0..HIRES_HEIGHT as isize).contains(&sy);
+                let moved = match inside {
+                    true => old
+                        .get(sy as usize * HIRES_WIDTH + sx as usize)
+                        .copied()
+                        .unwrap_or(0),
+                    false => 0,
+                };
+
+                if let Some(pixel) = self.video.get_mut(y * HIRES_WIDTH + x) {
+                    *pixel = (*pixel & !self.planes) | (moved & self.planes);
+                }
+            }
+        }
+    }
+
+    /// Skips the next instruction, which is two words long if it is
+    /// XO-CHIP's `F000 nnnn`.
+    fn skip(&mut self) {
+        let byte = |addr: u16| self.mem.get(addr as usize).unwrap_or(0);
+        let long = byte(self.pc) == 0xF0 && byte(self.pc.wrapping_add(1)) == 0x00;
+
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+    }
+
+    /// Runs up to `max` instructions, stopping early after one for which
+    /// `stop` returns true. Returns how many ran when stopped early, or
+    /// `None` when the batch ran to its end: all `max` instructions, or up
+    /// to a draw waiting for the vertical blank.
+    pub fn run_batch(
+        &mut self,
+        max: u32,
+        mut stop: impl FnMut(&Chip8) -> bool,
+    ) -> Result<Option<u32>, Error> {
+        for n in 1..=max {
+            self.step()?;
+            if stop(self) {
+                return Ok(Some(n));
+            }
+            if self.waiting_for_vblank() {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Executes one instruction. Guest faults are returned rather than
+    /// panicking, so the core can't be brought down by a bad ROM.
+    pub fn step(&mut self) -> Result<(), Error> {
+        self.execute().map_err(|fault| self.fault_context(fault))
+    }
+
+    /// Like `step`, but sums up what the instruction did as a
+    /// `CycleStatus`.
+    pub fn cycle(&mut self) -> Result<CycleStatus, Error> {
+        self.step()?;
+        Ok(CycleStatus::from_events(self.events()))
+    }
+
+    fn fault_context(&self, fault: Fault) -> Error {
+        #[cfg(feature = "alloc")]
+        let code = {
+            let start = fault.pc().saturating_sub(FAULT_CONTEXT * 2);
+            (0..=FAULT_CONTEXT * 2)
+                .map(|n| start.wrapping_add(n * 2))
+                .filter_map(|addr| {
+                    let hi = self.mem.get(addr as usize)?;
+                    let lo = self.mem.get(addr as usize + 1)?;
+                    Some((addr, u16::from_be_bytes([hi, lo])))
+                })
+                .collect()
+        };
+
+        Error {
+            fault,
+            #[cfg(feature = "alloc")]
+            code,
+            registers: self.reg.0,
+            index: self.i,
+        }
+    }
+
+    fn execute(&mut self) -> Result<(), Fault> {
+        let pc = self.pc;
+        // The fetch goes straight to memory, since it isn't a data read
+        let fetch = |addr: usize| {
+            self.mem
+                .get(addr)
+                .ok_or(Fault::MemoryOutOfBounds { pc, addr })
+        };
+        let op = u16::from_be_bytes([fetch(pc as usize)?, fetch(pc as usize + 1)?]);
+
+        self.pc = self.pc.wrapping_add(2);
+        self.events.clear();
+        self.events.push(GuestEvent::InstructionExecuted { pc, op });
+
+        let exec = DISPATCH
+            .get((op >> 12) as usize)
+            .ok_or(Fault::InvalidInstruction { pc, op })?;
+        exec(self, pc, op)
+    }
+
+    /// `step` the way it ran before `DISPATCH`: all of `decode_opcode`,
+    /// then a second match on the `Opcode`. It's only here as the baseline
+    /// in `benches/dispatch.rs`, and calls the same handlers, so the two
+    /// differ only in how they get to them.
+    #[doc(hidden)]
+    pub fn step_decoded(&mut self) -> Result<(), Error> {
+        self.execute_decoded()
+            .map_err(|fault| self.fault_context(fault))
+    }
+
+    fn execute_decoded(&mut self) -> Result<(), Fault> {
+        let pc = self.pc;
+        let fetch = |addr: usize| {
+            self.mem
+                .get(addr)
+                .ok_or(Fault::MemoryOutOfBounds { pc, addr })
+        };
+        let op = u16::from_be_bytes([fetch(pc as usize)?, fetch(pc as usize + 1)?]);
+
+        self.pc = self.pc.wrapping_add(2);
+        self.events.clear();
+        self.events.push(GuestEvent::InstructionExecuted { pc, op });
+
+        let opcode = decode_opcode(op).ok_or(Fault::InvalidInstruction { pc, op })?;
+        match opcode {
+            Opcode::Sys(_)
+            | Opcode::Cls
+            | Opcode::Ret
+            | Opcode::ScrollDown(_)
+            | Opcode::ScrollUp(_)
+            | Opcode::ScrollRight
+            | Opcode::ScrollLeft
+            | Opcode::Exit
+            | Opcode::Lores
+            | Opcode::Hires => self.exec_0(pc, op),
+            Opcode::Jp(_) => self.exec_jp(pc, op),
+            Opcode::Call(_) => self.exec_call(pc, op),
+            Opcode::SeByte(..) => self.exec_se_byte(pc, op),
+            Opcode::SneByte(..) => self.exec_sne_byte(pc, op),
+            Opcode::SeReg(..) | Opcode::SaveRange(..) | Opcode::LoadRange(..) => {
+                self.exec_5(pc, op)
+            }
+            Opcode::LdByte(..) => self.exec_ld_byte(pc, op),
+            Opcode::AddByte(..) => self.exec_add_byte(pc, op),
+            Opcode::LdReg(..)
+            | Opcode::Or(..)
+            | Opcode::And(..)
+            | Opcode::Xor(..)
+            | Opcode::AddReg(..)
+            | Opcode::Sub(..)
+            | Opcode::Shr(..)
+            | Opcode::Subn(..)
+            | Opcode::Shl(..) => self.exec_alu(pc, op),
+            Opcode::SneReg(..) => self.exec_sne_reg(pc, op),
+            Opcode::LdI(_) => self.exec_ld_i(pc, op),
+            Opcode::JpV0(_) => self.exec_jp_v0(pc, op),
+            Opcode::Rnd(..) => self.exec_rnd(pc, op),
+            Opcode::Drw(..) => self.exec_drw(pc, op),
+            Opcode::Skp(_) | Opcode::Sknp(_) => self.exec_keys(pc, op),
+            Opcode::LdVxDt(_)
+            | Opcode::LdVxK(_)
+            | Opcode::LdDtVx(_)
+            | Opcode::LdStVx(_)
+            | Opcode::AddI(_)
+            | Opcode::LdF(_)
+            | Opcode::LdB(_)
+            | Opcode::LdIVx(_)
+            | Opcode::LdVxI(_)
+            | Opcode::LdHf(_)
+            | Opcode::SaveFlags(_)
+            | Opcode::LoadFlags(_)
+            | Opcode::LdILong
+            | Opcode::Plane(_)
+            | Opcode::Audio
+            | Opcode::Pitch(_)
+            | Opcode::Rtc(_)
+            | Opcode::Bank(_)
+            | Opcode::Pen(_)
+            | Opcode::Brk => self.exec_f(pc, op),
+        }
+    }
+
+    /// 0nnn: SYS, CLS, RET and the SUPER-CHIP display and EXIT instructions.
+    fn exec_0(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        match addr(op) {
+            0x0E0 => {
+                for pixel in self.video.iter_mut() {
+                    *pixel &= !self.planes;
+                }
+                self.video_dirty = true;
+                self.events.push(GuestEvent::DisplayCleared { pc });
+            }
+
+            0x0EE => {
+                self.sp = self.sp.checked_sub(1).ok_or(Fault::StackUnderflow { pc })?;
+                self.pc = *self
+                    .stack
+                    .get(self.sp)
+                    .ok_or(Fault::StackUnderflow { pc })?;
+            }
+
+            0x0C0..=0x0CF => self.scroll(pc, 0, n(op) as isize),
+            0x0D0..=0x0DF => self.scroll(pc, 0, -(n(op) as isize)),
+            0x0FB => self.scroll(pc, 4, 0),
+            0x0FC => self.scroll(pc, -4, 0),
+
+            // Stays on EXIT, like a self-jump, for frontends that keep
+            // showing the last frame
+            0x0FD => {
+                self.pc = pc;
+                self.events.push(GuestEvent::Exited { pc });
+            }
+
+            // Switching resolution clears the display, as Octo does
+            0x0FE => {
+                self.hires = false;
+                self.video.fill(0);
+                self.video_dirty = true;
+                self.events.push(GuestEvent::DisplayCleared { pc });
+            }
+
+            0x0FF => {
+                self.hires = true;
+                self.video.fill(0);
+                self.video_dirty = true;
+                self.events.push(GuestEvent::DisplayCleared { pc });
+            }
+
+            // SYS
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 1nnn: JP addr.
+    fn exec_jp(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        self.pc = addr(op);
+        Ok(())
+    }
+
+    /// 2nnn: CALL addr.
+    fn exec_call(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        if self.sp >= self.stack_limit {
+            return Err(Fault::StackOverflow { pc });
+        }
+        *self
+            .stack
+            .get_mut(self.sp)
+            .ok_or(Fault::StackOverflow { pc })? = self.pc;
+        self.sp += 1;
+        self.pc = addr(op);
+        self.events
+            .push(GuestEvent::StackPushed { pc, depth: self.sp });
+        Ok(())
+    }
+
+    /// 3xkk: SE Vx, byte.
+    fn exec_se_byte(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        if self.reg[x(op)] == byte(op) {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    /// 4xkk: SNE Vx, byte.
+    fn exec_sne_byte(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        if self.reg[x(op)] != byte(op) {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    /// 5xy0: SE Vx, Vy, and XO-CHIP's 5xy2/5xy3 register range save and load.
+    fn exec_5(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        let (x, y) = (x(op), y(op));
+
+        match n(op) {
+            0x0 => {
+                if self.reg[x] == self.reg[y] {
+                    self.skip();
+                }
+            }
+
+            0x2 | 0x3 => {
+                // Registers go in the order written, so y < x runs backwards
+                let count = x.abs_diff(y) + 1;
+                let regs = (0..count).map(|n| if x <= y { x + n } else { x - n });
+                let save = n(op) == 0x2;
+
+                for (n, v) in regs.enumerate() {
+                    let addr = self.i as usize + n;
+                    match save {
+                        true => self.write(pc, addr, self.reg[v])?,
+                        false => self.reg[v] = self.read(pc, addr)?,
+                    }
+                }
+            }
+
+            _ => return Err(Fault::InvalidInstruction { pc, op }),
+        }
+
+        Ok(())
+    }
+
+    /// 6xkk: LD Vx, byte.
+    fn exec_ld_byte(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        self.reg[x(op)] = byte(op);
+        Ok(())
+    }
+
+    /// 7xkk: ADD Vx, byte.
+    fn exec_add_byte(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        let x = x(op);
+        self.reg[x] = self.reg[x].wrapping_add(byte(op));
+        Ok(())
+    }
+
+    /// 8xyn: register to register arithmetic.
+    fn exec_alu(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        let (x, y) = (x(op), y(op));
+
+        match n(op) {
+            // LD Vx, Vy
+            0x0 => self.reg[x] = self.reg[y],
+
+            // OR, AND, XOR
+            0x1..=0x3 => {
+                match n(op) {
+                    0x1 => self.reg[x] |= self.reg[y],
+                    0x2 => self.reg[x] &= self.reg[y],
+                    _ => self.reg[x] ^= self.reg[y],
+                }
+                if self.quirks.vf_reset {
+                    self.reg[0xF] = 0;
+                }
+            }
+
+            // ADD Vx, Vy
+            0x4 => {
+                let (res, carry) = self.reg[x].overflowing_add(self.reg[y]);
+                self.reg[x] = res;
+                self.reg[0xF] = carry as u8;
+            }
+
+            // SUB Vx, Vy
+            0x5 => {
+                let (res, borrow) = self.reg[x].overflowing_sub(self.reg[y]);
+                self.reg[x] = res;
+                self.reg[0xF] = !borrow as u8;
+            }
+
+            // SHR Vx {, Vy}
+            0x6 => {
+                if self.quirks.shift_vy {
+                    self.reg[x] = self.reg[y];
+                }
+                self.reg[0xF] = self.reg[x] & 1;
+                self.reg[x] >>= 1;
+            }
+
+            // SUBN Vx, Vy
+            0x7 => {
+                let (res, borrow) = self.reg[y].overflowing_sub(self.reg[x]);
+                self.reg[x] = res;
+                self.reg[0xF] = !borrow as u8;
+            }
+
+            // SHL Vx {, Vy}
+            0xE => {
+                if self.quirks.shift_vy {
+                    self.reg[x] = self.reg[y];
+                }
+                self.reg[0xF] = (self.reg[x] >> 7) & 1;
+                self.reg[x] <<= 1;
+            }
+
+            _ => return Err(Fault::InvalidInstruction { pc, op }),
+        }
+
+        Ok(())
+    }
+
+    /// 9xy0: SNE Vx, Vy.
+    fn exec_sne_reg(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        if n(op) != 0 {
+            return Err(Fault::InvalidInstruction { pc, op });
+        }
+        if self.reg[x(op)] != self.reg[y(op)] {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    /// Annn: LD I, addr.
+    fn exec_ld_i(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        self.i = addr(op);
+        Ok(())
+    }
+
+    /// Bnnn: JP V0, addr.
+    fn exec_jp_v0(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        let v = match self.quirks.jump_vx {
+            true => x(op),
+            false => 0x0,
+        };
+        self.pc = (self.reg[v] as u16) + addr(op);
+        Ok(())
+    }
+
+    /// Cxkk: RND Vx, byte.
+    fn exec_rnd(&mut self, _pc: u16, op: u16) -> Result<(), Fault> {
+        self.reg[x(op)] = self.rng.next_byte() & byte(op);
+        Ok(())
+    }
+
+    /// Dxyn: DRW Vx, Vy, nibble.
+    fn exec_drw(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        // The starting position always wraps; the quirks decide what
+        // happens to the pixels that run past an edge.
+        let (width, height) = self.resolution();
+        let x = self.reg[x(op)] as usize % width;
+        let y = self.reg[y(op)] as usize % height;
+        let n = n(op);
+        let mut i = self.i as usize;
+
+        // Dxy0 draws 16x16 from 32 bytes, two per row
+        let (cols, rows) = if n == 0 { (16, 16) } else { (8, n as usize) };
+        let stride = cols / 8;
+
+        self.reg[0xF] = 0;
+        self.video_dirty = true;
+
+        // Each selected plane takes the next sprite's worth of data
+        for plane in [1, 2] {
+            if self.planes & plane == 0 {
+                continue;
+            }
+
+            for dy in 0..rows {
+                let row = i + dy * stride;
+                let sprite = match stride {
+                    2 => u16::from_be_bytes([self.read(pc, row)?, self.read(pc, row + 1)?]),
+                    _ => (self.read(pc, row)? as u16) << 8,
+                };
+
+                let y = y + dy;
+                if y >= height && !self.quirks.wrap_y {
+                    break;
+                }
+                let y = y % height;
+
+                for dx in 0..cols {
+                    let x = x + dx;
+                    if x >= width && !self.quirks.wrap_x {
+                        break;
+                    }
+                    let x = x % width;
+
+                    if sprite & (0x8000 >> dx) != 0 && self.flip(x, y, plane) {
+                        self.reg[0xF] = 1;
+                    }
+                }
+            }
+
+            i += rows * stride;
+        }
+
+        self.events.push(GuestEvent::SpriteDrawn {
+            x: x as u8,
+            y: y as u8,
+            height: rows as u8,
+            collision: self.reg[0xF] != 0,
+        });
+        if self.quirks.display_wait {
+            self.events.push(GuestEvent::VblankWaited { pc });
+        }
+
+        let before = self.rows_drawn;
+        self.rows_drawn = before.saturating_add(rows as u16);
+        if let Some(budget) = self.draw_budget {
+            if before <= budget && self.rows_drawn > budget {
+                let rows = self.rows_drawn;
+                self.events
+                    .push(GuestEvent::DrawBudgetExceeded { pc, rows });
+            }
+        }
+        Ok(())
+    }
+
+    /// Ex9E/ExA1: SKP and SKNP Vx.
+    fn exec_keys(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        let held = match byte(op) {
+            0x9E => true,
+            0xA1 => false,
+            _ => return Err(Fault::InvalidInstruction { pc, op }),
+        };
+        if self.key(pc, self.reg[x(op)])? == held {
+            self.skip();
+        }
+        Ok(())
+    }
+
+    /// Fxkk: timers, keys, I, memory and the extensions.
+    fn exec_f(&mut self, pc: u16, op: u16) -> Result<(), Fault> {
+        let x = x(op);
+
+        match byte(op) {
+            // XO-CHIP's F000 nnnn: LD I, long addr
+            0x00 if op == 0xF000 => {
+                let addr = self.pc as usize;
+                self.i = u16::from_be_bytes([self.read(pc, addr)?, self.read(pc, addr + 1)?]);
+                self.pc = self.pc.wrapping_add(2);
+            }
+
+            // XO-CHIP's F002: AUDIO
+            0x02 if op == 0xF002 => {
+                let mut pattern = [0; AUDIO_PATTERN_SIZE];
+                for (n, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read(pc, self.i as usize + n)?;
+                }
+                self.audio = pattern;
+            }
+
+            // BRK: a NOP unless a debugger acts on the event
+            0xFF if op == 0xFFFF => {
+                self.events.push(GuestEvent::BreakRequested { pc });
+            }
+
+            // XO-CHIP's Fn01: PLANE n
+            0x01 if x > 3 => return Err(Fault::InvalidInstruction { pc, op }),
+            0x01 => self.planes = x as u8,
+
+            0x07 => {
+                self.reg[x] = self.dt;
+                if self.dt > 0 {
+                    self.events.push(GuestEvent::TimerPolled { pc });
+                }
+            }
+
+            0x0A => {
+                if let Some(key) = self.keypad.iter().position(|&held| held) {
+                    self.reg[x] = key as u8;
+                    return Ok(());
+                }
+
+                self.pc = pc;
+                self.events.push(GuestEvent::KeyWaited { pc });
+            }
+
+            0x15 => self.dt = self.reg[x],
+
+            0x18 => {
+                let ticks = self.reg[x];
+                if self.st == 0 && ticks > 0 {
+                    self.events.push(GuestEvent::SoundStarted { ticks });
+                }
+                if self.st > 0 && ticks == 0 {
+                    self.events.push(GuestEvent::SoundStopped { pc });
+                }
+                self.st = ticks;
+            }
+
+            0x1E => self.i = self.i.wrapping_add(self.reg[x] as u16),
+
+            0x29 => self.i = FONTSET_START_ADDRESS as u16 + self.reg[x] as u16 * 5,
+
+            0x30 => self.i = BIG_FONTSET_START_ADDRESS as u16 + self.reg[x] as u16 * 10,
+
+            0x33 => {
+                let mut value = self.reg[x];
+                let i = self.i as usize;
+
+                self.write(pc, i + 2, value % 10)?;
+                value /= 10;
+                self.write(pc, i + 1, value % 10)?;
+                value /= 10;
+                self.write(pc, i, value % 10)?;
+            }
+
+            0x3A => self.pitch = self.reg[x],
+
+            0x55 => {
+                for v in 0..=x {
+                    self.write(pc, self.i as usize + v, self.reg[v])?;
+                }
+                if self.quirks.load_store_increment {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+
+            0x65 => {
+                for v in 0..=x {
+                    self.reg[v] = self.read(pc, self.i as usize + v)?;
+                }
+                if self.quirks.load_store_increment {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+
+            0x75 => {
+                for (flag, v) in self.flags.iter_mut().zip(0..=x) {
+                    *flag = self.reg[v];
+                }
+            }
+
+            0x85 => {
+                for (v, &flag) in (0..=x).zip(&self.flags) {
+                    self.reg[v] = flag;
+                }
+            }
+
+            0xE0 if self.ext.rtc => {
+                let (h, m, s) = (self.clock)();
+                let i = self.i as usize;
+
+                for (n, value) in [h, m, s].into_iter().enumerate() {
+                    self.write(pc, i + n, value)?;
+                }
+            }
+
+            0xF8 if self.ext.banking => self.map_bank(pc, self.reg[x] as usize)?,
+
+            0xF9 if self.ext.light_pen => {
+                let (px, py, buttons) = self.pen;
+                let i = self.i as usize;
+
+                for (n, value) in [px, py, buttons].into_iter().enumerate() {
+                    self.write(pc, i + n, value)?;
+                }
+            }
+
+            // Including the extension opcodes that aren't enabled
+            _ => return Err(Fault::InvalidInstruction { pc, op }),
+        }
+
+        Ok(())
+    }
+
+    /// Counts the delay and sound timers down, and advances the frame
+    /// counter extension. Call this at 60 Hz, once per frame, however many
+    /// instructions the frame ran.
+    pub fn tick_timers(&mut self) {
+        self.rows_drawn = 0;
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+
+        if self.ext.frame_counter {
+            self.frames = self.frames.wrapping_add(1);
+            let _ = self
+                .mem
+                .write_slice(FRAME_COUNTER_ADDRESS, &self.frames.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift32, so every run tries the same programs.
+    fn next(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    // Run with --features strict-core too: the point is that nothing the
+    // guest does reaches a panic, only a fault.
+    #[test]
+    fn random_programs_fault_instead_of_panicking() {
+        let mut state = 0x2545_F491;
+        // Miri runs a few hundred times slower
+        let programs = if cfg!(miri) { 3 } else { 300 };
+
+        for _ in 0..programs {
+            let bits = next(&mut state);
+            let bit = |n: u32| bits >> n & 1 != 0;
+            let mut cpu = Chip8::new(0xA5);
+            cpu.set_extensions(Extensions {
+                rtc: bit(0),
+                banking: bit(1),
+                light_pen: bit(2),
+                frame_counter: bit(3),
+            });
+            cpu.set_quirks(Quirks {
+                wrap_x: bit(4),
+                wrap_y: bit(5),
+                shift_vy: bit(6),
+                load_store_increment: bit(7),
+                jump_vx: bit(8),
+                vf_reset: bit(9),
+                display_wait: bit(10),
+            });
+            cpu.set_trace_memory(bit(11));
+            cpu.set_draw_budget(bit(12).then_some(VIP_DRAW_BUDGET));
+            cpu.set_stack_limit(1 + bits as usize % MAX_STACK_LIMIT);
+
+            let mut rom = [0; 0x1000];
+            for byte in rom.iter_mut() {
+                *byte = next(&mut state) as u8;
+            }
+            assert!(cpu.load_rom_bytes(&rom).is_ok());
+
+            for n in 0..2000 {
+                if n % 20 == 0 {
+                    cpu.tick_timers();
+                    cpu.set_keypad(next(&mut state) as usize % NUM_KEYS, bit(n % 32));
+                }
+                // Carry on past faults, to reach more of the program
+                if cpu.step().is_err() {
+                    cpu.set_pc(cpu.get_pc().wrapping_add(2));
+                }
+            }
+        }
+    }
+
+    /// Lights the top left pixel, then draws an 8x4 block at (60, 30) in low
+    /// resolution, across the right and bottom edges, and checks what was
+    /// drawn and that VF reports the collision only if the block wrapped
+    /// round onto that pixel.
+    fn draw_across_edges(wrap_x: bool, wrap_y: bool) {
+        let mut cpu = Chip8::new(0);
+        cpu.set_quirks(Quirks {
+            wrap_x,
+            wrap_y,
+            ..Quirks::default()
+        });
+        let rom = [
+            0xA2, 0x10, // LD I, dot
+            0x60, 0x00, // LD V0, 0
+            0xD0, 0x01, // DRW V0, V0, 1
+            0x6A, 0x3C, // LD VA, 60
+            0x6B, 0x1E, // LD VB, 30
+            0xA2, 0x11, // LD I, block
+            0xDA, 0xB4, // DRW VA, VB, 4
+            0x12, 0x0E, // JP 0x20E
+            0x80, // dot
+            0xFF, 0xFF, 0xFF, 0xFF, // block
+        ];
+        assert!(cpu.load_rom_bytes(&rom).is_ok());
+        for _ in 0..7 {
+            assert!(cpu.step().is_ok());
+        }
+
+        let in_block = |x: usize, y: usize| {
+            (60..68).any(|c| (c < VIDEO_WIDTH || wrap_x) && c % VIDEO_WIDTH == x)
+                && (30..34).any(|r| (r < VIDEO_HEIGHT || wrap_y) && r % VIDEO_HEIGHT == y)
+        };
+        // Each low resolution pixel is a 2x2 block of the framebuffer
+        for (n, &pixel) in cpu.get_video().iter().enumerate() {
+            let (x, y) = (n % HIRES_WIDTH / 2, n / HIRES_WIDTH / 2);
+            let lit = in_block(x, y) != ((x, y) == (0, 0));
+            assert_eq!(pixel, lit as u8, "pixel ({}, {})", x, y);
+        }
+        let collided = wrap_x && wrap_y;
+        assert_eq!(cpu.get_registers().get(0xF), Some(&(collided as u8)));
+    }
+
+    #[test]
+    fn sprites_clip_at_both_edges() {
+        draw_across_edges(false, false);
+    }
+
+    #[test]
+    fn sprites_wrap_horizontally_only() {
+        draw_across_edges(true, false);
+    }
+
+    #[test]
+    fn sprites_wrap_vertically_only() {
+        draw_across_edges(false, true);
+    }
+
+    #[test]
+    fn sprites_wrap_both_ways() {
+        draw_across_edges(true, true);
+    }
+
+    #[test]
+    fn all_sixteen_registers_save_to_flags() {
+        let mut cpu = Chip8::new(0);
+        let rom = [
+            0x6F, 0x42, // LD VF, 0x42
+            0xFF, 0x75, // LD R, VF
+            0x6F, 0x00, // LD VF, 0
+            0xFF, 0x85, // LD VF, R
+        ];
+        assert!(cpu.load_rom_bytes(&rom).is_ok());
+        for _ in 0..4 {
+            assert!(cpu.step().is_ok());
+        }
+        assert_eq!(cpu.get_registers().get(0xF), Some(&0x42));
+    }
+
+    #[test]
+    fn setters_reject_out_of_range_values() {
+        let mut cpu = Chip8::new(0);
+
+        assert_eq!(cpu.set_keypad(0xF, true), Some(()));
+        assert_eq!(cpu.set_keypad(NUM_KEYS, true), None);
+        assert_eq!(cpu.set_stack_limit(MAX_STACK_LIMIT), Some(()));
+        assert_eq!(cpu.set_stack_limit(0), None);
+        assert_eq!(cpu.set_stack_limit(MAX_STACK_LIMIT + 1), None);
+
+        let mut mem = Memory::new();
+        assert_eq!(mem.write_slice(MEMORY_SIZE - 2, &[1, 2]), Some(()));
+        assert_eq!(mem.write_slice(MEMORY_SIZE - 1, &[1, 2]), None);
+        assert_eq!(mem.get(MEMORY_SIZE - 1), Some(2));
+    }
+
+    // Every word, with every extension on so none is refused for being
+    // disabled: what faults as invalid is exactly what doesn't decode
+    #[test]
+    fn handlers_accept_what_decode_opcode_does() {
+        let mut cpu = Chip8::new(0);
+        cpu.set_extensions(Extensions {
+            rtc: true,
+            banking: true,
+            light_pen: true,
+            frame_counter: true,
+        });
+
+        // Under Miri, a prime stride still reaches every nibble pattern
+        for op in (0..=u16::MAX).step_by(if cfg!(miri) { 251 } else { 1 }) {
+            let [hi, lo] = op.to_be_bytes();
+            assert!(cpu.poke_memory(0x200, hi).is_some());
+            assert!(cpu.poke_memory(0x201, lo).is_some());
+            cpu.set_pc(0x200);
+
+            let invalid = matches!(
+                cpu.step(),
+                Err(Error {
+                    fault: Fault::InvalidInstruction { .. },
+                    ..
+                })
+            );
+            assert_eq!(invalid, decode_opcode(op).is_none(), "{:04X}", op);
+        }
+    }
+
+    // The benches' baseline only means something if it runs the same machine
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn step_decoded_matches_step() {
+        let mut cpu = Chip8::new(3);
+        cpu.set_extensions(Extensions {
+            rtc: true,
+            banking: true,
+            light_pen: true,
+            frame_counter: true,
+        });
+        assert!(cpu.load_rom_bytes(&[0x6A, 0x12, 0xA3, 0x45]).is_ok());
+        assert!(cpu.step().is_ok());
+        assert!(cpu.step().is_ok());
+
+        for op in (0..=u16::MAX).step_by(if cfg!(miri) { 4099 } else { 97 }) {
+            let [hi, lo] = op.to_be_bytes();
+            assert!(cpu.poke_memory(0x204, hi).is_some());
+            assert!(cpu.poke_memory(0x205, lo).is_some());
+            cpu.set_pc(0x204);
+
+            let mut decoded = cpu.clone();
+            let table = cpu.step().map_err(|e| e.fault);
+            assert_eq!(
+                decoded.step_decoded().map_err(|e| e.fault),
+                table,
+                "{:04X}",
+                op
+            );
+            assert_eq!(decoded.events(), cpu.events(), "{:04X}", op);
+            assert!(decoded.save_state() == cpu.save_state(), "{:04X}", op);
+        }
+    }
+
+    #[test]
+    fn cycle_status_comes_from_the_events() {
+        let mut cpu = Chip8::new(0);
+        let rom = [
+            0x00, 0xE0, // CLS
+            0x60, 0x05, // LD V0, 5
+            0xD0, 0x01, // DRW V0, V0, 1
+            0x00, 0xC1, // SCD 1
+            0x00, 0xFF, // HIGH
+            0xF0, 0x18, // LD ST, V0
+            0x00, 0xFD, // EXIT
+        ];
+        assert!(cpu.load_rom_bytes(&rom).is_ok());
+
+        let drew = CycleStatus::DREW_TO_SCREEN;
+        let expected = [
+            drew,
+            CycleStatus::default(),
+            drew,
+            drew,
+            drew,
+            CycleStatus::SOUND_STARTED,
+            CycleStatus::EXITED,
+        ];
+        for want in expected {
+            assert_eq!(cpu.cycle().ok(), Some(want));
+        }
+        assert!(!CycleStatus::default().contains(drew));
+    }
+
+    #[test]
+    fn instances_draw_their_own_random_numbers() {
+        let rom = [
+            0xC0, 0xFF, // RND V0, 0xFF
+            0x12, 0x00, // JP 0x200
+        ];
+        let draws = |cpu: &mut Chip8, n: usize| -> Vec<u8> {
+            (0..n)
+                .map(|_| {
+                    assert!(cpu.step().is_ok() && cpu.step().is_ok());
+                    cpu.get_registers().first().copied().unwrap_or_default()
+                })
+                .collect()
+        };
+
+        // However much one draws, the other sees the same sequence
+        let mut a = Chip8::new(42);
+        let mut b = Chip8::new(42);
+        assert!(a.load_rom_bytes(&rom).is_ok() && b.load_rom_bytes(&rom).is_ok());
+        let ahead = draws(&mut a, 20);
+        assert_eq!(draws(&mut b, 20), ahead);
+
+        // A clone carries on where it was taken, and another seed differs
+        let mut clone = a.clone();
+        assert_eq!(draws(&mut clone, 5), draws(&mut a, 5));
+        let mut other = Chip8::new(43);
+        assert!(other.load_rom_bytes(&rom).is_ok());
+        assert_ne!(draws(&mut other, 20), ahead);
+    }
+
+    // The boot sequence comment before `Chip8::new`, step by step
+    #[test]
+    fn new_machine_is_in_the_boot_state() {
+        let cpu = Chip8::new(0xA5);
+
+        let mem = cpu.get_memory();
+        assert_eq!(mem.len(), MEMORY_SIZE);
+        let fonts = 0x050..0x104;
+        let digits: Vec<u8> = FONTSET.iter().chain(&BIG_FONTSET).copied().collect();
+        assert_eq!(BIG_FONTSET_START_ADDRESS, 0x0A0);
+        assert_eq!(mem.get(fonts.clone()), Some(&digits[..]));
+        assert!(mem
+            .iter()
+            .enumerate()
+            .all(|(addr, &b)| fonts.contains(&addr) || b == 0));
+
+        assert!(cpu.get_registers().iter().all(|&v| v == 0));
+        assert_eq!(cpu.get_index(), 0);
+        assert_eq!(cpu.get_timers(), (0, 0));
+        assert!(cpu.get_stack().is_empty());
+        assert_eq!(cpu.flags, [0; NUM_FLAGS]);
+        assert!(cpu.get_keypad().iter().all(|&k| !k));
+        assert_eq!(cpu.frames, 0);
+
+        assert!(cpu.get_video().iter().all(|&p| p == 0));
+        assert!(!cpu.is_hires());
+        assert_eq!(cpu.planes, 1);
+        assert_eq!(cpu.get_audio_pattern(), &DEFAULT_AUDIO_PATTERN);
+        assert_eq!(cpu.get_pitch(), DEFAULT_PITCH);
+
+        assert_eq!(cpu.get_pc(), MEMORY_START as u16);
+    }
+}
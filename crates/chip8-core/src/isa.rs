@@ -0,0 +1,409 @@
+//! The CHIP-8 instruction set, independent of any emulator state.
+//!
+//! [`decode_opcode`] turns an instruction word into an [`Opcode`] and
+//! [`Opcode::encode`] turns it back, so assemblers, disassemblers and
+//! linters can share one definition of the encodings.
+//! [`Opcode::mnemonic`] gives the assembly syntax of an opcode.
+//!
+//! # Instruction sets
+//!
+//! - **CHIP-8**: the 35 instructions of the original COSMAC VIP
+//!   interpreter, from `Sys` through `LdVxI`. `0nnn` (SYS) is decoded but
+//!   ignored by modern interpreters, this one included.
+//! - **SUPER-CHIP 1.1**: the HP-48 additions, from `ScrollDown` through
+//!   `LoadFlags`, plus `Dxy0` drawing a 16x16 sprite. They take over some
+//!   `0nnn` words, so a ROM using those as SYS calls decodes differently.
+//! - **Extensions**: non-standard `Fx` opcodes understood by this
+//!   emulator. Other interpreters, including Octo, treat them as invalid,
+//!   so tools targeting those should reject them. All but BRK have to be
+//!   enabled on the command line:
+//!   - `FxE0` ([`Opcode::Rtc`]) stores the wall-clock hours, minutes and
+//!     seconds at I..I+2 (`--ext-rtc`).
+//!   - `FxF8` ([`Opcode::Bank`]) maps 512-byte bank Vx of an oversized ROM
+//!     to 0xE00 (`--ext-banking`).
+//!   - `FxF9` ([`Opcode::Pen`]) stores the mouse x, y and buttons at
+//!     I..I+2 (`--ext-light-pen`).
+//!   - `FFFF` ([`Opcode::Brk`]) pauses the debugger, when one is attached,
+//!     and is a NOP otherwise, so homebrew can carry breakpoints in source.
+//!
+//! - **XO-CHIP**: Octo's extensions, from `ScrollUp` through `Pitch`.
+//!   `F000 nnnn` ([`Opcode::LdILong`]) is the only two-word instruction:
+//!   the address is the word after it, so skips must step over four bytes.
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+/// A decoded instruction. Register operands are indexes 0x0-0xF; each
+/// variant notes its encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    /// 0nnn - SYS addr (never one of the other `00xx` instructions)
+    Sys(u16),
+    /// 00E0 - CLS
+    Cls,
+    /// 00EE - RET
+    Ret,
+    /// 1nnn - JP addr
+    Jp(u16),
+    /// 2nnn - CALL addr
+    Call(u16),
+    /// 3xkk - SE Vx, byte
+    SeByte(u8, u8),
+    /// 4xkk - SNE Vx, byte
+    SneByte(u8, u8),
+    /// 5xy0 - SE Vx, Vy
+    SeReg(u8, u8),
+    /// 6xkk - LD Vx, byte
+    LdByte(u8, u8),
+    /// 7xkk - ADD Vx, byte
+    AddByte(u8, u8),
+    /// 8xy0 - LD Vx, Vy
+    LdReg(u8, u8),
+    /// 8xy1 - OR Vx, Vy
+    Or(u8, u8),
+    /// 8xy2 - AND Vx, Vy
+    And(u8, u8),
+    /// 8xy3 - XOR Vx, Vy
+    Xor(u8, u8),
+    /// 8xy4 - ADD Vx, Vy
+    AddReg(u8, u8),
+    /// 8xy5 - SUB Vx, Vy
+    Sub(u8, u8),
+    /// 8xy6 - SHR Vx {, Vy}
+    Shr(u8, u8),
+    /// 8xy7 - SUBN Vx, Vy
+    Subn(u8, u8),
+    /// 8xyE - SHL Vx {, Vy}
+    Shl(u8, u8),
+    /// 9xy0 - SNE Vx, Vy
+    SneReg(u8, u8),
+    /// Annn - LD I, addr
+    LdI(u16),
+    /// Bnnn - JP V0, addr
+    JpV0(u16),
+    /// Cxkk - RND Vx, byte
+    Rnd(u8, u8),
+    /// Dxyn - DRW Vx, Vy, nibble (n = 0 draws 16x16 on SUPER-CHIP)
+    Drw(u8, u8, u8),
+    /// Ex9E - SKP Vx
+    Skp(u8),
+    /// ExA1 - SKNP Vx
+    Sknp(u8),
+    /// Fx07 - LD Vx, DT
+    LdVxDt(u8),
+    /// Fx0A - LD Vx, K
+    LdVxK(u8),
+    /// Fx15 - LD DT, Vx
+    LdDtVx(u8),
+    /// Fx18 - LD ST, Vx
+    LdStVx(u8),
+    /// Fx1E - ADD I, Vx
+    AddI(u8),
+    /// Fx29 - LD F, Vx
+    LdF(u8),
+    /// Fx33 - LD B, Vx
+    LdB(u8),
+    /// Fx55 - LD [I], Vx
+    LdIVx(u8),
+    /// Fx65 - LD Vx, [I]
+    LdVxI(u8),
+    // SUPER-CHIP 1.1
+    /// 00Cn - SCD nibble: scroll down n pixels
+    ScrollDown(u8),
+    /// 00FB - SCR: scroll right 4 pixels
+    ScrollRight,
+    /// 00FC - SCL: scroll left 4 pixels
+    ScrollLeft,
+    /// 00FD - EXIT
+    Exit,
+    /// 00FE - LOW: 64x32 display
+    Lores,
+    /// 00FF - HIGH: 128x64 display
+    Hires,
+    /// Fx30 - LD HF, Vx: I = 10-byte digit sprite
+    LdHf(u8),
+    /// Fx75 - LD R, Vx: save V0..Vx to the flag registers
+    SaveFlags(u8),
+    /// Fx85 - LD Vx, R: load V0..Vx from the flag registers
+    LoadFlags(u8),
+
+    // XO-CHIP
+    /// 00Dn - SCROLL-UP nibble
+    ScrollUp(u8),
+    /// 5xy2 - SAVE Vx - Vy: store the range at I, in either direction
+    SaveRange(u8, u8),
+    /// 5xy3 - LOAD Vx - Vy: load the range from I, in either direction
+    LoadRange(u8, u8),
+    /// F000 nnnn - LD I, long addr (the address is the next word)
+    LdILong,
+    /// Fn01 - PLANE n: select the bitplanes (a mask, 0-3) later drawing
+    /// affects
+    Plane(u8),
+    /// F002 - AUDIO: load the 16-byte sample pattern from I
+    Audio,
+    /// Fx3A - PITCH Vx: set the pattern playback rate
+    Pitch(u8),
+
+    // Extensions, see the module documentation
+    /// FxE0 - LD [I], RTC (extension)
+    Rtc(u8),
+    /// FxF8 - BANK Vx (extension)
+    Bank(u8),
+    /// FxF9 - LD [I], PEN (extension)
+    Pen(u8),
+    /// FFFF - BRK (extension): a breakpoint for an attached debugger
+    Brk,
+}
+
+/// Decodes an instruction word, including the extension opcodes whether or
+/// not they are enabled. Returns `None` for words that aren't instructions.
+pub fn decode_opcode(op: u16) -> Option<Opcode> {
+    let x = ((op & 0x0F00) >> 8) as u8;
+    let y = ((op & 0x00F0) >> 4) as u8;
+    let addr = op & 0x0FFF;
+    let byte = (op & 0x00FF) as u8;
+    let n = (op & 0x000F) as u8;
+
+    let opcode = match (op & 0xF000) >> 12 {
+        0x0 => match addr {
+            0x0E0 => Opcode::Cls,
+            0x0EE => Opcode::Ret,
+            0x0C0..=0x0CF => Opcode::ScrollDown(n),
+            0x0D0..=0x0DF => Opcode::ScrollUp(n),
+            0x0FB => Opcode::ScrollRight,
+            0x0FC => Opcode::ScrollLeft,
+            0x0FD => Opcode::Exit,
+            0x0FE => Opcode::Lores,
+            0x0FF => Opcode::Hires,
+            _ => Opcode::Sys(addr),
+        },
+        0x1 => Opcode::Jp(addr),
+        0x2 => Opcode::Call(addr),
+        0x3 => Opcode::SeByte(x, byte),
+        0x4 => Opcode::SneByte(x, byte),
+        0x5 => match n {
+            0x0 => Opcode::SeReg(x, y),
+            0x2 => Opcode::SaveRange(x, y),
+            0x3 => Opcode::LoadRange(x, y),
+            _ => return None,
+        },
+        0x6 => Opcode::LdByte(x, byte),
+        0x7 => Opcode::AddByte(x, byte),
+        0x8 => match n {
+            0x0 => Opcode::LdReg(x, y),
+            0x1 => Opcode::Or(x, y),
+            0x2 => Opcode::And(x, y),
+            0x3 => Opcode::Xor(x, y),
+            0x4 => Opcode::AddReg(x, y),
+            0x5 => Opcode::Sub(x, y),
+            0x6 => Opcode::Shr(x, y),
+            0x7 => Opcode::Subn(x, y),
+            0xE => Opcode::Shl(x, y),
+            _ => return None,
+        },
+        0x9 if n == 0 => Opcode::SneReg(x, y),
+        0xA => Opcode::LdI(addr),
+        0xB => Opcode::JpV0(addr),
+        0xC => Opcode::Rnd(x, byte),
+        0xD => Opcode::Drw(x, y, n),
+        0xE => match byte {
+            0x9E => Opcode::Skp(x),
+            0xA1 => Opcode::Sknp(x),
+            _ => return None,
+        },
+        0xF if op == 0xF000 => Opcode::LdILong,
+        0xF if op == 0xF002 => Opcode::Audio,
+        0xF if op == 0xFFFF => Opcode::Brk,
+        0xF => match byte {
+            0x01 if x <= 3 => Opcode::Plane(x),
+            0x07 => Opcode::LdVxDt(x),
+            0x0A => Opcode::LdVxK(x),
+            0x15 => Opcode::LdDtVx(x),
+            0x18 => Opcode::LdStVx(x),
+            0x1E => Opcode::AddI(x),
+            0x29 => Opcode::LdF(x),
+            0x33 => Opcode::LdB(x),
+            0x55 => Opcode::LdIVx(x),
+            0x65 => Opcode::LdVxI(x),
+            0x30 => Opcode::LdHf(x),
+            0x3A => Opcode::Pitch(x),
+            0x75 => Opcode::SaveFlags(x),
+            0x85 => Opcode::LoadFlags(x),
+            0xE0 => Opcode::Rtc(x),
+            0xF8 => Opcode::Bank(x),
+            0xF9 => Opcode::Pen(x),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(opcode)
+}
+
+impl Opcode {
+    /// The instruction word for this opcode; `decode_opcode` undoes it.
+    /// Operands wider than their field are truncated to fit.
+    pub fn encode(&self) -> u16 {
+        let xy = |hi: u16, x: u8, y: u8, lo: u16| {
+            hi << 12 | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | lo
+        };
+        let xkk = |hi: u16, x: u8, kk: u8| hi << 12 | (x as u16 & 0xF) << 8 | kk as u16;
+        let fx = |x: u8, lo: u16| 0xF000 | (x as u16 & 0xF) << 8 | lo;
+
+        match *self {
+            Opcode::Sys(addr) => addr & 0x0FFF,
+            Opcode::Cls => 0x00E0,
+            Opcode::Ret => 0x00EE,
+            Opcode::Jp(addr) => 0x1000 | addr & 0x0FFF,
+            Opcode::Call(addr) => 0x2000 | addr & 0x0FFF,
+            Opcode::SeByte(x, kk) => xkk(0x3, x, kk),
+            Opcode::SneByte(x, kk) => xkk(0x4, x, kk),
+            Opcode::SeReg(x, y) => xy(0x5, x, y, 0x0),
+            Opcode::LdByte(x, kk) => xkk(0x6, x, kk),
+            Opcode::AddByte(x, kk) => xkk(0x7, x, kk),
+            Opcode::LdReg(x, y) => xy(0x8, x, y, 0x0),
+            Opcode::Or(x, y) => xy(0x8, x, y, 0x1),
+            Opcode::And(x, y) => xy(0x8, x, y, 0x2),
+            Opcode::Xor(x, y) => xy(0x8, x, y, 0x3),
+            Opcode::AddReg(x, y) => xy(0x8, x, y, 0x4),
+            Opcode::Sub(x, y) => xy(0x8, x, y, 0x5),
+            Opcode::Shr(x, y) => xy(0x8, x, y, 0x6),
+            Opcode::Subn(x, y) => xy(0x8, x, y, 0x7),
+            Opcode::Shl(x, y) => xy(0x8, x, y, 0xE),
+            Opcode::SneReg(x, y) => xy(0x9, x, y, 0x0),
+            Opcode::LdI(addr) => 0xA000 | addr & 0x0FFF,
+            Opcode::JpV0(addr) => 0xB000 | addr & 0x0FFF,
+            Opcode::Rnd(x, kk) => xkk(0xC, x, kk),
+            Opcode::Drw(x, y, n) => xy(0xD, x, y, n as u16 & 0xF),
+            Opcode::Skp(x) => xkk(0xE, x, 0x9E),
+            Opcode::Sknp(x) => xkk(0xE, x, 0xA1),
+            Opcode::LdVxDt(x) => fx(x, 0x07),
+            Opcode::LdVxK(x) => fx(x, 0x0A),
+            Opcode::LdDtVx(x) => fx(x, 0x15),
+            Opcode::LdStVx(x) => fx(x, 0x18),
+            Opcode::AddI(x) => fx(x, 0x1E),
+            Opcode::LdF(x) => fx(x, 0x29),
+            Opcode::LdB(x) => fx(x, 0x33),
+            Opcode::LdIVx(x) => fx(x, 0x55),
+            Opcode::LdVxI(x) => fx(x, 0x65),
+            Opcode::ScrollDown(n) => 0x00C0 | n as u16 & 0xF,
+            Opcode::ScrollRight => 0x00FB,
+            Opcode::ScrollLeft => 0x00FC,
+            Opcode::Exit => 0x00FD,
+            Opcode::Lores => 0x00FE,
+            Opcode::Hires => 0x00FF,
+            Opcode::LdHf(x) => fx(x, 0x30),
+            Opcode::SaveFlags(x) => fx(x, 0x75),
+            Opcode::LoadFlags(x) => fx(x, 0x85),
+            Opcode::ScrollUp(n) => 0x00D0 | n as u16 & 0xF,
+            Opcode::SaveRange(x, y) => xy(0x5, x, y, 0x2),
+            Opcode::LoadRange(x, y) => xy(0x5, x, y, 0x3),
+            Opcode::LdILong => 0xF000,
+            Opcode::Plane(n) => fx(n, 0x01),
+            Opcode::Audio => 0xF002,
+            Opcode::Pitch(x) => fx(x, 0x3A),
+            Opcode::Rtc(x) => fx(x, 0xE0),
+            Opcode::Bank(x) => fx(x, 0xF8),
+            Opcode::Pen(x) => fx(x, 0xF9),
+            Opcode::Brk => 0xFFFF,
+        }
+    }
+
+    /// Whether running this can change the display.
+    pub fn changes_display(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Cls
+                | Opcode::Drw(..)
+                | Opcode::ScrollDown(_)
+                | Opcode::ScrollUp(_)
+                | Opcode::ScrollLeft
+                | Opcode::ScrollRight
+                | Opcode::Lores
+                | Opcode::Hires
+        )
+    }
+
+    /// The instruction in the usual Cowgod-style syntax, with addresses and
+    /// bytes in hex. `F000`'s address is the following word, so it shows as
+    /// `LONG`.
+    #[cfg(feature = "alloc")]
+    pub fn mnemonic(&self) -> String {
+        use Opcode::*;
+
+        match *self {
+            Sys(addr) => format!("SYS {:#05X}", addr),
+            Cls => "CLS".to_string(),
+            Ret => "RET".to_string(),
+            Jp(addr) => format!("JP {:#05X}", addr),
+            Call(addr) => format!("CALL {:#05X}", addr),
+            SeByte(x, kk) => format!("SE V{:X}, {:#04X}", x, kk),
+            SneByte(x, kk) => format!("SNE V{:X}, {:#04X}", x, kk),
+            SeReg(x, y) => format!("SE V{:X}, V{:X}", x, y),
+            LdByte(x, kk) => format!("LD V{:X}, {:#04X}", x, kk),
+            AddByte(x, kk) => format!("ADD V{:X}, {:#04X}", x, kk),
+            LdReg(x, y) => format!("LD V{:X}, V{:X}", x, y),
+            Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+            And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+            Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+            AddReg(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+            Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+            Shr(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+            Subn(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+            Shl(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+            SneReg(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+            LdI(addr) => format!("LD I, {:#05X}", addr),
+            JpV0(addr) => format!("JP V0, {:#05X}", addr),
+            Rnd(x, kk) => format!("RND V{:X}, {:#04X}", x, kk),
+            Drw(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+            Skp(x) => format!("SKP V{:X}", x),
+            Sknp(x) => format!("SKNP V{:X}", x),
+            LdVxDt(x) => format!("LD V{:X}, DT", x),
+            LdVxK(x) => format!("LD V{:X}, K", x),
+            LdDtVx(x) => format!("LD DT, V{:X}", x),
+            LdStVx(x) => format!("LD ST, V{:X}", x),
+            AddI(x) => format!("ADD I, V{:X}", x),
+            LdF(x) => format!("LD F, V{:X}", x),
+            LdB(x) => format!("LD B, V{:X}", x),
+            LdIVx(x) => format!("LD [I], V{:X}", x),
+            LdVxI(x) => format!("LD V{:X}, [I]", x),
+            ScrollDown(n) => format!("SCD {}", n),
+            ScrollRight => "SCR".to_string(),
+            ScrollLeft => "SCL".to_string(),
+            Exit => "EXIT".to_string(),
+            Lores => "LOW".to_string(),
+            Hires => "HIGH".to_string(),
+            LdHf(x) => format!("LD HF, V{:X}", x),
+            SaveFlags(x) => format!("LD R, V{:X}", x),
+            LoadFlags(x) => format!("LD V{:X}, R", x),
+            ScrollUp(n) => format!("SCU {}", n),
+            SaveRange(x, y) => format!("SAVE V{:X} - V{:X}", x, y),
+            LoadRange(x, y) => format!("LOAD V{:X} - V{:X}", x, y),
+            LdILong => "LD I, LONG".to_string(),
+            Plane(n) => format!("PLANE {}", n),
+            Audio => "AUDIO".to_string(),
+            Pitch(x) => format!("PITCH V{:X}", x),
+            Rtc(x) => format!("LD [I], RTC V{:X}", x),
+            Bank(x) => format!("BANK V{:X}", x),
+            Pen(x) => format!("LD [I], PEN V{:X}", x),
+            Brk => "BRK".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_inverts_decode() {
+        for word in (0..=u16::MAX).step_by(if cfg!(miri) { 251 } else { 1 }) {
+            if let Some(op) = decode_opcode(word) {
+                assert_eq!(op.encode(), word, "{:04X} decodes to {:?}", word, op);
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! The emulator itself: the machine, the instruction set and memory, and
+//! the state they expose. Frontend concerns such as the run loop, the
+//! debugger, replays and messages live in `chip8-frontend`.
+//!
+//! Without the default `std` feature the crate builds as `no_std`, for
+//! running on microcontrollers. `alloc` adds back save states, ROM banking,
+//! RGBA rendering and error reports with disassembly for targets that have
+//! a heap.
+//!
+//! # Guarantees
+//!
+//! No ROM or configuration can make the core panic or run into undefined
+//! behaviour: a misbehaving guest gets a [`chip8::Error`] from
+//! [`chip8::Chip8::step`]. Embedders can rely on this, and it's held to
+//! by:
+//!
+//! - no `unsafe` code, which the crate forbids;
+//! - the `strict-core` feature, under which clippy rejects indexing,
+//!   `unwrap`, `expect` and `panic!` anywhere in the crate:
+//!   `cargo clippy -p chip8-core --features strict-core -- -D warnings`;
+//! - the tests under Miri, which checks the standard library code they
+//!   reach as well: `cargo +nightly miri test -p chip8-core`, which
+//!   takes a quarter of an hour, with the exhaustive tests thinned out;
+//! - the `step` fuzz target in `fuzz/`, which loads arbitrary ROMs under
+//!   arbitrary configurations: `cargo +nightly fuzz run step` from this
+//!   crate's directory.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![forbid(unsafe_code)]
+// With the strict-core feature, clippy rejects anything in the crate that
+// could panic on guest input; guest faults must surface as `Error`.
+#![cfg_attr(
+    feature = "strict-core",
+    deny(
+        clippy::panic,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::indexing_slicing,
+        clippy::panic_in_result_fn
+    )
+)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod chip8;
+pub mod isa;
+mod memory;
@@ -0,0 +1,85 @@
+use crate::chip8::MEMORY_SIZE;
+#[cfg(feature = "alloc")]
+use alloc::{sync::Arc, vec, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::ops::Range;
+
+const PAGE_SIZE: usize = 256;
+const PAGES: usize = MEMORY_SIZE / PAGE_SIZE;
+
+#[cfg(feature = "alloc")]
+type Pages = Vec<Arc<[u8; PAGE_SIZE]>>;
+#[cfg(not(feature = "alloc"))]
+type Pages = [[u8; PAGE_SIZE]; PAGES];
+
+/// Guest memory split into pages that clones share until one of them writes
+/// to a page, so snapshotting a `Chip8` only copies the pages that change.
+/// Without `alloc` the pages are plain arrays and clones copy them all.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    pages: Pages,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        // Every page starts out sharing one zeroed page
+        #[cfg(feature = "alloc")]
+        let pages = {
+            let zero = Arc::new([0; PAGE_SIZE]);
+            vec![zero; PAGES]
+        };
+        #[cfg(not(feature = "alloc"))]
+        let pages = [[0; PAGE_SIZE]; PAGES];
+
+        Memory { pages }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len() * PAGE_SIZE
+    }
+
+    pub fn get(&self, addr: usize) -> Option<u8> {
+        let page = self.pages.get(addr / PAGE_SIZE)?;
+        page.get(addr % PAGE_SIZE).copied()
+    }
+
+    /// Copies the page first if another clone still shares it.
+    pub fn get_mut(&mut self, addr: usize) -> Option<&mut u8> {
+        let page = self.pages.get_mut(addr / PAGE_SIZE)?;
+        #[cfg(feature = "alloc")]
+        let page = Arc::make_mut(page);
+        page.get_mut(addr % PAGE_SIZE)
+    }
+
+    /// Copies `data` in at `start`. `None`, writing nothing, if it doesn't
+    /// fit.
+    pub fn write_slice(&mut self, start: usize, data: &[u8]) -> Option<()> {
+        if start.checked_add(data.len())? > self.len() {
+            return None;
+        }
+
+        for (addr, &byte) in (start..).zip(data) {
+            if let Some(cell) = self.get_mut(addr) {
+                *cell = byte;
+            }
+        }
+        Some(())
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn fill(&mut self, range: Range<usize>, value: u8) {
+        for addr in range {
+            if let Some(cell) = self.get_mut(addr) {
+                *cell = value;
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.pages
+            .iter()
+            .flat_map(|page| page.iter().copied())
+            .collect()
+    }
+}
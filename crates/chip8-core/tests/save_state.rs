@@ -0,0 +1,91 @@
+//! The save state format against checked-in states. `boot.c8st` is a fresh
+//! machine and `running.c8st` is `running()`; if the format changes on
+//! purpose, bump its version and save these again.
+
+use chip8_core::chip8::{Chip8, StateError};
+
+const BOOT: &[u8] = include_bytes!("fixtures/boot.c8st");
+const RUNNING: &[u8] = include_bytes!("fixtures/running.c8st");
+
+/// Where the selected planes are in `RUNNING`, which has one return address
+/// on the stack: s+8193 in the format description, with s = 65562 + 2.
+const RUNNING_PLANES: usize = 65564 + 8193;
+
+/// A machine with something in every part of the state: a return address,
+/// high resolution on both planes, a flag register, an audio pattern and
+/// pitch, a held key and running timers.
+fn running() -> Chip8 {
+    let rom = [
+        0x00, 0xFF, // HIGH
+        0xF3, 0x01, // PLANE 3
+        0xA2, 0x30, // LD I, 0x230
+        0xF0, 0x02, // AUDIO
+        0x60, 0x2A, // LD V0, 0x2A
+        0x6F, 0x07, // LD VF, 7
+        0xFF, 0x75, // LD R, VF
+        0x61, 0x10, // LD V1, 16
+        0xD1, 0x15, // DRW V1, V1, 5
+        0xF0, 0x15, // LD DT, V0
+        0xF1, 0x18, // LD ST, V1
+        0x63, 0x40, // LD V3, 0x40
+        0xF3, 0x3A, // PITCH V3
+        0x22, 0x20, // CALL 0x220
+        0x12, 0x1C, // JP 0x21C
+        0x00, 0x00, // unused
+        0x12, 0x20, // 0x220: JP 0x220
+    ];
+    // At 0x230, the audio pattern, which doubles as the sprites
+    let data = [
+        0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0,
+        0xF0,
+    ];
+    let mut image = rom.to_vec();
+    image.resize(0x30, 0);
+    image.extend(data);
+
+    let mut cpu = Chip8::new(0);
+    assert!(cpu.load_rom_bytes(&image).is_ok());
+    for _ in 0..20 {
+        assert!(cpu.step().is_ok());
+    }
+    cpu.tick_timers();
+    cpu.set_keypad(5, true);
+    cpu
+}
+
+#[test]
+fn fixtures_load_and_save_back_unchanged() {
+    for fixture in [BOOT, RUNNING] {
+        let mut cpu = Chip8::new(0);
+        assert_eq!(cpu.load_state(fixture), Ok(()));
+        assert!(cpu.save_state() == fixture);
+    }
+}
+
+#[test]
+fn save_state_matches_fixtures() {
+    assert!(Chip8::new(0).save_state() == BOOT);
+    assert!(running().save_state() == RUNNING);
+}
+
+#[test]
+fn broken_states_are_rejected() {
+    let load = |state: &[u8]| Chip8::new(0).load_state(state);
+
+    assert_eq!(
+        load(&RUNNING[..RUNNING.len() - 1]),
+        Err(StateError::Corrupt)
+    );
+    assert_eq!(load(&[RUNNING, &[0]].concat()), Err(StateError::Corrupt));
+
+    let mut state = RUNNING.to_vec();
+    state[4] = 1;
+    assert_eq!(load(&state), Err(StateError::UnsupportedVersion(1)));
+
+    let mut state = RUNNING.to_vec();
+    assert_eq!(state[RUNNING_PLANES], 3);
+    state[RUNNING_PLANES] = 4;
+    assert_eq!(load(&state), Err(StateError::Corrupt));
+
+    assert_eq!(load(b"C8SX"), Err(StateError::NotAState));
+}
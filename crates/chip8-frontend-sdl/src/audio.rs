@@ -0,0 +1,157 @@
+use chip8_core::chip8::{DEFAULT_AUDIO_PATTERN, DEFAULT_PITCH};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+use std::collections::VecDeque;
+
+const SAMPLE_RATE: i32 = 44100;
+const DEFAULT_VOLUME: f32 = 0.1;
+/// What the default pattern sounds like at the default pitch.
+const DEFAULT_TONE: u32 = 500;
+const PATTERN_BITS: f32 = 128.0;
+/// The default pattern repeats every 8 bits, one cycle of the square wave.
+const DEFAULT_PATTERN_PERIOD: f32 = 8.0;
+/// How far timed sound may run ahead of the output, in frames, before it
+/// is pulled back in line, so that a burst of frames doesn't turn into
+/// lasting delay.
+const MAX_FRAMES_AHEAD: u64 = 3;
+
+/// How loud the beep is, from 0.0 to 1.0, and the frequency in Hz that
+/// plain CHIP-8 beeps play at.
+#[derive(Clone, Copy)]
+pub struct Sound {
+    pub volume: f32,
+    pub tone: u32,
+    /// Starts and stops the sound at the instruction that set the sound
+    /// timer rather than at the next frame, for music ROMs that toggle it
+    /// many times a frame. Costs a frame of latency.
+    pub timed: bool,
+}
+
+impl Default for Sound {
+    fn default() -> Sound {
+        Sound {
+            volume: DEFAULT_VOLUME,
+            tone: DEFAULT_TONE,
+            timed: false,
+        }
+    }
+}
+
+/// Plays a guest's 128-bit XO-CHIP audio pattern in a loop while its sound
+/// timer runs. Plain CHIP-8 ROMs get the core's default square wave.
+pub struct PatternVoice {
+    pattern: [u8; 16],
+    /// Pattern bits to advance per output sample
+    step: f32,
+    phase: f32,
+    playing: bool,
+    sample_rate: f32,
+    sound: Sound,
+    /// With `Sound::timed`, when the sound goes on or off, as samples
+    /// since the device opened.
+    schedule: VecDeque<(u64, bool)>,
+    /// Samples output so far.
+    clock: u64,
+    /// Where the next frame starts on `clock`.
+    next_frame: u64,
+}
+
+impl PatternVoice {
+    pub fn configure(&mut self, sound: Sound) {
+        self.sound = sound;
+    }
+
+    /// Called once per frame with the state of the guest being heard.
+    /// `changes` says when in the frame, as a fraction of it, the sound
+    /// went on or off. With `Sound::timed` the frame is played out over
+    /// the next frame's worth of samples with the changes in place;
+    /// otherwise `playing` takes effect at once and they're ignored.
+    pub fn update(&mut self, changes: &[(f32, bool)], playing: bool, pattern: [u8; 16], pitch: u8) {
+        // XO-CHIP plays 4000 bits per second at pitch 64, one octave per 48.
+        // A guest that never set a pattern gets the configured beep instead.
+        let bit_rate = match pattern == DEFAULT_AUDIO_PATTERN && pitch == DEFAULT_PITCH {
+            true => self.sound.tone as f32 * DEFAULT_PATTERN_PERIOD,
+            false => 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0),
+        };
+
+        self.pattern = pattern;
+        self.step = bit_rate / self.sample_rate;
+
+        if !self.sound.timed {
+            self.schedule.clear();
+            self.playing = playing;
+            return;
+        }
+
+        let frame = (self.sample_rate / 60.0) as u64;
+        if self.next_frame < self.clock || self.next_frame > self.clock + MAX_FRAMES_AHEAD * frame {
+            if let Some(&(_, on)) = self.schedule.back() {
+                self.playing = on;
+            }
+            self.schedule.clear();
+            self.next_frame = self.clock;
+        }
+
+        for &(at, on) in changes {
+            let at = self.next_frame + (at * frame as f32) as u64;
+            self.schedule.push_back((at, on));
+        }
+        self.next_frame += frame;
+        self.schedule.push_back((self.next_frame, playing));
+    }
+}
+
+impl AudioCallback for PatternVoice {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            while let Some(&(at, on)) = self.schedule.front() {
+                if at > self.clock {
+                    break;
+                }
+                self.playing = on;
+                self.schedule.pop_front();
+            }
+            self.clock += 1;
+
+            if !self.playing {
+                *sample = 0.0;
+                continue;
+            }
+
+            let bit = self.phase as usize;
+            let on = self.pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+
+            let volume = self.sound.volume;
+            *sample = if on { volume } else { -volume };
+            self.phase = (self.phase + self.step) % PATTERN_BITS;
+        }
+    }
+}
+
+/// Opens and starts a mono output device, silent until a voice is updated.
+pub fn open(sdl: &Sdl) -> Result<AudioDevice<PatternVoice>, String> {
+    let desired = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE),
+        channels: Some(1),
+        samples: None,
+    };
+
+    let device = sdl
+        .audio()?
+        .open_playback(None, &desired, |spec| PatternVoice {
+            pattern: [0; 16],
+            step: 0.0,
+            phase: 0.0,
+            playing: false,
+            sample_rate: spec.freq as f32,
+            sound: Sound::default(),
+            schedule: VecDeque::new(),
+            clock: 0,
+            next_frame: 0,
+        })?;
+    device.resume();
+
+    Ok(device)
+}
@@ -0,0 +1,303 @@
+//! The TOML config file, for settings that outlive a command line. The
+//! `[keymap]` table maps CHIP-8 keys to host keys, and `[controller]` maps
+//! controller buttons to CHIP-8 keys, both named as SDL names them.
+//! `[fast_forward]` sets what the `--fast-forward-*` options do, which
+//! override it. A `[rom.<sha1>]` table holds the instructions per frame
+//! `--calibrate` found for the ROM file with that SHA-1, used when neither
+//! `--ipf` nor `--speed` is given. `filters` under `[video]` lists the
+//! video filters to use when `--filter` doesn't:
+//!
+//! ```toml
+//! [keymap]
+//! 0 = "X"
+//! 1 = "1"
+//! A = "Left Shift"
+//!
+//! [controller]
+//! a = "6"
+//! dpup = "5"
+//! start = "F"
+//!
+//! [fast_forward]
+//! max = 8
+//! ramp = 1
+//! mute = true
+//!
+//! [video]
+//! filters = ["phosphor", "scanlines", "curvature"]
+//!
+//! # TETRIS
+//! [rom.5f518084744bf3cb8733f6e5454dfd1634320563]
+//! ipf = 15
+//! ```
+//!
+//! Anything left out keeps its default.
+
+use crate::filter::{Filter, DEFAULT_PHOSPHOR_FRAMES};
+use crate::sdlgui::FastForward;
+use chip8_frontend::i18n::tr_args;
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What a config file sets.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The host key for each CHIP-8 key.
+    pub keys: [String; 16],
+    /// Controller buttons to bind over the defaults, with their CHIP-8 keys.
+    pub buttons: Vec<(Button, usize)>,
+    pub fast_forward: FastForward,
+    /// Calibrated instructions per frame, by `rom_hash`.
+    pub rom_ipf: HashMap<String, u32>,
+    /// The video filters, with phosphor fading over its default frames.
+    pub filters: Vec<Filter>,
+}
+
+impl Config {
+    /// The settings with no config file, with `defaults` for the keymap.
+    pub fn new(defaults: &[&str; 16]) -> Config {
+        Config {
+            keys: defaults.map(str::to_string),
+            buttons: vec![],
+            fast_forward: FastForward::default(),
+            rom_ipf: HashMap::new(),
+            filters: vec![],
+        }
+    }
+}
+
+/// Parses a CHIP-8 key, a single hex digit.
+fn chip8_key(s: &str) -> Option<usize> {
+    match s.len() {
+        1 => usize::from_str_radix(s, 16).ok(),
+        _ => None,
+    }
+}
+
+/// Where the config file is read from when none is given:
+/// `$XDG_CONFIG_HOME/chip8-rust/config.toml`, or under `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(dir.join("chip8-rust").join("config.toml"))
+}
+
+/// Reads the config file at `path`, with `defaults` for the keymap.
+pub fn load(path: &Path, defaults: &[&str; 16]) -> Result<Config, String> {
+    let src =
+        fs::read_to_string(path).map_err(|e| tr_args("err-read-config", &[&path.display(), &e]))?;
+    parse(&src, defaults).map_err(|e| tr_args("err-config", &[&path.display(), &e]))
+}
+
+/// Parses a config file, with `defaults` for the keymap.
+pub fn parse(src: &str, defaults: &[&str; 16]) -> Result<Config, String> {
+    let config: toml::Table = src
+        .parse()
+        .map_err(|e: toml::de::Error| e.message().to_string())?;
+    let table = |name: &str| match config.get(name) {
+        Some(value) => value
+            .as_table()
+            .map(Some)
+            .ok_or_else(|| tr_args("err-config-table", &[&name])),
+        None => Ok(None),
+    };
+
+    Ok(Config {
+        keys: keymap(table("keymap")?, defaults)?,
+        buttons: buttons(table("controller")?)?,
+        fast_forward: fast_forward(table("fast_forward")?)?,
+        rom_ipf: rom_ipf(table("rom")?)?,
+        filters: video(table("video")?)?,
+    })
+}
+
+/// The key a ROM's settings are stored under: the SHA-1 of its file, in
+/// lowercase hex, as ROM databases list them.
+pub fn rom_hash(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Stores `ipf` as the calibrated instructions per frame for the ROM with
+/// hash `rom` in the config file at `path`, creating the file if need be.
+/// The rest of the file, comments included, is left as it was.
+pub fn save_ipf(path: &Path, rom: &str, ipf: u32) -> Result<(), String> {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(tr_args("err-read-config", &[&path.display(), &e])),
+    };
+    let invalid = |e: String| tr_args("err-config", &[&path.display(), &e]);
+    let mut doc: toml_edit::DocumentMut = src
+        .parse()
+        .map_err(|e: toml_edit::TomlError| invalid(e.message().to_string()))?;
+
+    // Only the [rom.<sha1>] headers are written, not an empty [rom]
+    let roms = doc.entry("rom").or_insert_with(|| {
+        let mut roms = toml_edit::Table::new();
+        roms.set_implicit(true);
+        roms.into()
+    });
+    let settings = roms
+        .as_table_mut()
+        .ok_or_else(|| invalid(tr_args("err-config-table", &[&"rom"])))?
+        .entry(rom)
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| invalid(tr_args("err-config-table", &[&format!("rom.{}", rom)])))?;
+    settings["ipf"] = toml_edit::value(i64::from(ipf));
+
+    let write_error = |e: io::Error| tr_args("err-write-config", &[&path.display(), &e]);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+    fs::write(path, doc.to_string()).map_err(write_error)
+}
+
+/// A whole number in `range`, for the setting `name`.
+fn number(name: &str, value: &toml::Value, range: RangeInclusive<u32>) -> Result<u32, String> {
+    value
+        .as_integer()
+        .and_then(|n| u32::try_from(n).ok())
+        .filter(|n| range.contains(n))
+        .ok_or_else(|| tr_args("err-config-number", &[&name, range.start(), range.end()]))
+}
+
+/// The `[rom.<sha1>]` tables of a `[rom]` table, with the instructions per
+/// frame each sets.
+fn rom_ipf(roms: Option<&toml::Table>) -> Result<HashMap<String, u32>, String> {
+    let mut rom_ipf = HashMap::new();
+
+    for (hash, settings) in roms.into_iter().flatten() {
+        let name = format!("rom.{}", hash);
+        let settings = settings
+            .as_table()
+            .ok_or_else(|| tr_args("err-config-table", &[&name]))?;
+        for (key, value) in settings {
+            let setting = format!("{}.{}", name, key);
+            match key.as_str() {
+                "ipf" => {
+                    rom_ipf.insert(hash.to_lowercase(), number(&setting, value, 1..=u32::MAX)?);
+                }
+                _ => return Err(tr_args("err-config-unknown", &[&setting])),
+            }
+        }
+    }
+
+    Ok(rom_ipf)
+}
+
+/// The filters of a `[video]` table, named as `--filter` names them.
+fn video(table: Option<&toml::Table>) -> Result<Vec<Filter>, String> {
+    let mut filters = vec![];
+
+    for (name, value) in table.into_iter().flatten() {
+        let setting = format!("video.{}", name);
+        if name != "filters" {
+            return Err(tr_args("err-config-unknown", &[&setting]));
+        }
+        let names = value
+            .as_array()
+            .ok_or_else(|| tr_args("err-config-list", &[&setting]))?;
+        for name in names {
+            let filter = match name.as_str() {
+                Some("scanlines") => Filter::Scanlines,
+                Some("grid") => Filter::Grid,
+                Some("phosphor") => Filter::Phosphor {
+                    frames: DEFAULT_PHOSPHOR_FRAMES,
+                },
+                Some("curvature") => Filter::Curvature,
+                _ => return Err(tr_args("err-config-filter", &[name])),
+            };
+            filters.push(filter);
+        }
+    }
+
+    Ok(filters)
+}
+
+/// A `[fast_forward]` table over the defaults, in the ranges the
+/// `--fast-forward-*` options take.
+fn fast_forward(table: Option<&toml::Table>) -> Result<FastForward, String> {
+    let mut fast_forward = FastForward::default();
+
+    for (name, value) in table.into_iter().flatten() {
+        let setting = format!("fast_forward.{}", name);
+        match name.as_str() {
+            "max" => fast_forward.max = number(&setting, value, 2..=16)?,
+            "ramp" => {
+                let secs = number(&setting, value, 0..=60)?;
+                fast_forward.ramp = Duration::from_secs(secs.into());
+            }
+            "mute" => {
+                fast_forward.mute = value
+                    .as_bool()
+                    .ok_or_else(|| tr_args("err-config-bool", &[&setting]))?;
+            }
+            _ => return Err(tr_args("err-config-unknown", &[&setting])),
+        }
+    }
+
+    Ok(fast_forward)
+}
+
+/// The host key for each CHIP-8 key, from a `[keymap]` table over
+/// `defaults`. Host key names are checked with SDL and returned as SDL
+/// spells them, and no host key may press two CHIP-8 keys.
+fn keymap(keymap: Option<&toml::Table>, defaults: &[&str; 16]) -> Result<[String; 16], String> {
+    let mut keys = defaults.map(str::to_string);
+
+    let mut seen = [false; 16];
+    for (chip8, host) in keymap.into_iter().flatten() {
+        let key = chip8_key(chip8).ok_or_else(|| tr_args("err-keymap-chip8-key", &[chip8]))?;
+        // TOML only rejects exact repeats, not "a" next to "A"
+        if mem::replace(&mut seen[key], true) {
+            return Err(tr_args("err-keymap-twice", &[&format!("{:X}", key)]));
+        }
+
+        let keycode = host
+            .as_str()
+            .and_then(Keycode::from_name)
+            .ok_or_else(|| tr_args("err-keymap-host-key", &[host, chip8]))?;
+        keys[key] = keycode.name();
+    }
+
+    for a in 0..keys.len() {
+        if let Some(b) = (a + 1..keys.len()).find(|&b| keys[b] == keys[a]) {
+            let (a_key, b_key) = (format!("{:X}", a), format!("{:X}", b));
+            return Err(tr_args("err-keymap-duplicate", &[&keys[a], &a_key, &b_key]));
+        }
+    }
+
+    Ok(keys)
+}
+
+/// The buttons of a `[controller]` table, checked with SDL, and the CHIP-8
+/// keys they press.
+fn buttons(controller: Option<&toml::Table>) -> Result<Vec<(Button, usize)>, String> {
+    controller
+        .into_iter()
+        .flatten()
+        .map(|(name, key)| {
+            let button = Button::from_string(name)
+                .ok_or_else(|| tr_args("err-controller-button", &[name]))?;
+            let key = key
+                .as_str()
+                .and_then(chip8_key)
+                .ok_or_else(|| tr_args("err-controller-key", &[name, key]))?;
+            Ok((button, key))
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+use crate::ramsearch::Filter;
+use chip8_frontend::debugger::{parse_addr, Access, OpcodePattern};
+use chip8_frontend::i18n::{tr, tr_args};
+use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/*
+    search      start a new RAM search from the current memory
+    inc         keep addresses whose value increased
+    dec         keep addresses whose value decreased
+    same        keep addresses whose value did not change
+    diff        keep addresses whose value changed
+    eq <n>      keep addresses whose value equals n (decimal or 0x hex)
+    list        print the remaining candidates and their values
+    calls <f>   write the call graph seen so far to f as Graphviz DOT
+    break <a>   pause before the instruction at address a
+    breakop <p> pause before opcodes matching p, with x as a wildcard (Dxxx)
+    watch <a>[-<b>] [r|w|rw]
+                pause after an instruction reads or writes (default rw)
+                address a, or a through b
+    clear       remove all breakpoints and watchpoints
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Search,
+    Filter(Filter),
+    List,
+    CallGraph(PathBuf),
+    Break(u16),
+    BreakOpcode(OpcodePattern),
+    Watch(RangeInclusive<usize>, Access),
+    ClearBreakpoints,
+}
+
+fn parse_byte(s: &str) -> Option<u8> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+
+    let cmd = match words.next() {
+        Some("search") => Command::Search,
+        Some("inc") => Command::Filter(Filter::Increased),
+        Some("dec") => Command::Filter(Filter::Decreased),
+        Some("same") => Command::Filter(Filter::Unchanged),
+        Some("diff") => Command::Filter(Filter::Changed),
+        Some("eq") => {
+            let arg = words.next().ok_or(tr("err-usage-eq"))?;
+            let n = parse_byte(arg).ok_or_else(|| tr_args("err-invalid-byte", &[&arg]))?;
+            Command::Filter(Filter::EqualTo(n))
+        }
+        Some("list") => Command::List,
+        Some("calls") => {
+            let path = words.next().ok_or(tr("err-usage-calls"))?;
+            Command::CallGraph(PathBuf::from(path))
+        }
+        Some("break") => {
+            let arg = words.next().ok_or(tr("err-usage-break"))?;
+            let addr = parse_addr(arg).ok_or_else(|| tr_args("err-invalid-addr", &[&arg]))?;
+            Command::Break(addr)
+        }
+        Some("breakop") => {
+            let arg = words.next().ok_or(tr("err-usage-breakop"))?;
+            let pattern =
+                OpcodePattern::parse(arg).ok_or_else(|| tr_args("err-invalid-pattern", &[&arg]))?;
+            Command::BreakOpcode(pattern)
+        }
+        Some("watch") => {
+            let arg = words.next().ok_or(tr("err-usage-watch"))?;
+            let invalid = || tr_args("err-invalid-addr", &[&arg]);
+            let (start, end) = match arg.split_once('-') {
+                Some((start, end)) => (
+                    parse_addr(start).ok_or_else(invalid)?,
+                    parse_addr(end).ok_or_else(invalid)?,
+                ),
+                None => {
+                    let addr = parse_addr(arg).ok_or_else(invalid)?;
+                    (addr, addr)
+                }
+            };
+            let access = match words.next() {
+                Some(s) => Access::parse(s).ok_or(tr("err-usage-watch"))?,
+                None => Access::Any,
+            };
+            Command::Watch(start as usize..=end as usize, access)
+        }
+        Some("clear") => Command::ClearBreakpoints,
+        Some(other) => return Err(tr_args("err-unknown-command", &[&other])),
+        None => return Err(tr("err-empty-command").to_string()),
+    };
+
+    Ok(cmd)
+}
+
+/// Reads commands from stdin on a background thread so the frontend can poll
+/// them between frames.
+pub fn spawn() -> Receiver<Command> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse(&line) {
+                Ok(cmd) => {
+                    if tx.send(cmd).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    });
+
+    rx
+}
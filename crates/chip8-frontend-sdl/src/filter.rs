@@ -0,0 +1,318 @@
+//! Post-processing for each instance's picture, as render-target passes.
+//! Filters draw with the renderer into textures that are kept from frame to
+//! frame, so with an accelerated renderer the work, afterglow included,
+//! happens on the GPU. The host only refills the framebuffer texture when
+//! the guest draws, and otherwise issues copies and fills. Scanlines and
+//! the grid are drawn once for each scale and background colour.
+
+use chip8_core::chip8::{Palette, HIRES_HEIGHT, HIRES_WIDTH, RGBA_SIZE};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+/// How dark scanlines are, as alpha over black.
+const SCANLINE_ALPHA: u8 = 96;
+/// How much the grid lets the pixels under it show through.
+const GRID_ALPHA: u8 = 160;
+/// How close afterglow has come to the background after the configured
+/// number of frames.
+const GLOW_CUTOFF: u8 = 16;
+/// The phosphor filter's fade unless configured: about halving each frame.
+pub const DEFAULT_PHOSPHOR_FRAMES: u32 = 4;
+/// How much narrower curvature makes the picture along its top and bottom
+/// than through the middle, and shorter down its sides, as a fraction.
+const CURVATURE: f32 = 0.06;
+/// Curvature bends the picture in strips this many window pixels wide.
+const CURVATURE_STRIP: u32 = 4;
+
+/// One step of the pipeline. Scanlines and the grid draw in the order
+/// given, over the framebuffer. Phosphor changes the picture they draw
+/// over, and curvature bends the result, wherever either is listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Darkens the lower half of each framebuffer row.
+    Scanlines,
+    /// Lines in the background colour between pixels, like an LCD.
+    Grid,
+    /// Pixels that go dark fade out over `frames` drawn frames, which
+    /// also smooths the flicker of sprites erased and redrawn with XOR.
+    Phosphor { frames: u32 },
+    /// Bulges the picture like the glass of a CRT, with the corners
+    /// rounded off into black.
+    Curvature,
+}
+
+/// The textures one instance is drawn through. They borrow the texture
+/// creator, which is why the window is opened ahead of `SDLGui`.
+pub(crate) struct Screen<'r> {
+    creator: &'r TextureCreator<WindowContext>,
+    /// The framebuffer, with the background transparent so the lit pixels
+    /// can go over a fill or over the afterglow.
+    pixels: Texture<'r>,
+    /// For phosphor, the picture as last drawn at framebuffer resolution.
+    /// Each frame it fades toward the background before the lit pixels go
+    /// on top.
+    glow: Option<Texture<'r>>,
+    /// Scanlines and the grid at window resolution, with the scale and
+    /// background colour they were drawn for.
+    overlay: Option<((u32, [u8; 4]), Texture<'r>)>,
+    /// For curvature, the picture drawn flat and then bent across, at the
+    /// scale they are sized for.
+    curve: Option<(u32, Texture<'r>, Texture<'r>)>,
+}
+
+impl<'r> Screen<'r> {
+    pub(crate) fn new(creator: &'r TextureCreator<WindowContext>) -> Screen<'r> {
+        let mut pixels = creator
+            .create_texture_streaming(
+                PixelFormatEnum::RGBA32,
+                HIRES_WIDTH as u32,
+                HIRES_HEIGHT as u32,
+            )
+            .unwrap();
+        pixels.set_blend_mode(BlendMode::Blend);
+
+        Screen {
+            creator,
+            pixels,
+            glow: None,
+            overlay: None,
+            curve: None,
+        }
+    }
+
+    /// A texture the renderer can draw into, copied without blending.
+    fn target(&self, width: u32, height: u32) -> Texture<'r> {
+        let mut texture = self
+            .creator
+            .create_texture_target(PixelFormatEnum::RGBA8888, width, height)
+            .unwrap();
+        texture.set_blend_mode(BlendMode::None);
+        texture
+    }
+
+    /// Draws instance `n` from `video` through `filters`. The framebuffer
+    /// texture is only refilled from `video` when it's `dirty`. The scale
+    /// is per low-resolution pixel, so framebuffer pixels are half that;
+    /// with odd scales their sizes alternate. Every filter needs render
+    /// targets, which `SDLGui::set_filters` checks for.
+    pub(crate) fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        filters: &[Filter],
+        palette: Palette,
+        scale: u32,
+        n: usize,
+        (video, dirty): (&[u8], bool),
+    ) {
+        if dirty {
+            let mut lit = palette;
+            lit.colors[0][3] = 0;
+            let mut pixels = [0; RGBA_SIZE];
+            lit.render(video, &mut pixels);
+            self.pixels.update(None, &pixels, HIRES_WIDTH * 4).unwrap();
+        }
+
+        let edge = |n: usize| n as u32 * scale / 2;
+        let rect = Rect::new(
+            edge(n * HIRES_WIDTH) as i32,
+            0,
+            edge(HIRES_WIDTH),
+            edge(HIRES_HEIGHT),
+        );
+
+        if let Some(frames) = filters.iter().find_map(|filter| match filter {
+            Filter::Phosphor { frames } => Some(*frames),
+            _ => None,
+        }) {
+            self.fade(canvas, palette, frames);
+        }
+
+        let lines: Vec<Filter> = filters
+            .iter()
+            .copied()
+            .filter(|filter| matches!(filter, Filter::Scanlines | Filter::Grid))
+            .collect();
+        let key = (scale, palette.colors[0]);
+        if lines.is_empty() {
+            self.overlay = None;
+        } else if self.overlay.as_ref().is_none_or(|(drawn, _)| *drawn != key) {
+            let mut overlay = self.target(rect.width(), rect.height());
+            canvas
+                .with_texture_canvas(&mut overlay, |c| draw_lines(c, &lines, palette, scale))
+                .unwrap();
+            overlay.set_blend_mode(BlendMode::Blend);
+            self.overlay = Some((key, overlay));
+        }
+
+        let picture = Picture {
+            pixels: &self.pixels,
+            glow: self.glow.as_ref(),
+            overlay: self.overlay.as_ref().map(|(_, overlay)| overlay),
+            background: palette.colors[0],
+        };
+        if !filters.contains(&Filter::Curvature) {
+            picture.draw(canvas, rect);
+            return;
+        }
+
+        if self
+            .curve
+            .as_ref()
+            .is_none_or(|(drawn, ..)| *drawn != scale)
+        {
+            let flat = self.target(rect.width(), rect.height());
+            let bent = self.target(rect.width(), rect.height());
+            self.curve = Some((scale, flat, bent));
+        }
+        if let Some((_, flat, bent)) = &mut self.curve {
+            let whole = Rect::new(0, 0, rect.width(), rect.height());
+            canvas
+                .with_texture_canvas(flat, |c| picture.draw(c, whole))
+                .unwrap();
+            bend(canvas, flat, bent, rect);
+        }
+    }
+
+    /// Fades the afterglow toward the background so that it reaches the
+    /// cutoff after `frames`, then lays the lit pixels over it.
+    fn fade(&mut self, canvas: &mut Canvas<Window>, palette: Palette, frames: u32) {
+        let [r, g, b, _] = palette.colors[0];
+        let fade = (GLOW_CUTOFF as f32 / u8::MAX as f32).powf(1.0 / frames.max(1) as f32);
+        let alpha = ((1.0 - fade) * u8::MAX as f32).round() as u8;
+
+        if self.glow.is_none() {
+            let mut glow = self.target(HIRES_WIDTH as u32, HIRES_HEIGHT as u32);
+            canvas
+                .with_texture_canvas(&mut glow, |c| {
+                    c.set_draw_color(Color::RGB(r, g, b));
+                    c.clear();
+                })
+                .unwrap();
+            self.glow = Some(glow);
+        }
+
+        if let Some(glow) = &mut self.glow {
+            let pixels = &self.pixels;
+            canvas
+                .with_texture_canvas(glow, |c| {
+                    c.set_blend_mode(BlendMode::Blend);
+                    c.set_draw_color(Color::RGBA(r, g, b, alpha));
+                    c.fill_rect(None).unwrap();
+                    c.copy(pixels, None, None).unwrap();
+                    c.set_blend_mode(BlendMode::None);
+                })
+                .unwrap();
+        }
+    }
+}
+
+/// What goes into an instance's place before any bending.
+struct Picture<'a, 'r> {
+    pixels: &'a Texture<'r>,
+    /// Stands in for the background and pixels with phosphor.
+    glow: Option<&'a Texture<'r>>,
+    overlay: Option<&'a Texture<'r>>,
+    background: [u8; 4],
+}
+
+impl Picture<'_, '_> {
+    fn draw(&self, canvas: &mut Canvas<Window>, rect: Rect) {
+        match self.glow {
+            Some(glow) => canvas.copy(glow, None, rect).unwrap(),
+            None => {
+                let [r, g, b, a] = self.background;
+                canvas.set_draw_color(Color::RGBA(r, g, b, a));
+                canvas.fill_rect(rect).unwrap();
+                canvas.copy(self.pixels, None, rect).unwrap();
+            }
+        }
+        if let Some(overlay) = self.overlay {
+            canvas.copy(overlay, None, rect).unwrap();
+        }
+    }
+}
+
+/// Draws scanlines and the grid into a cleared overlay at `scale`, in the
+/// order given. Each writes its colour and alpha straight into the texture,
+/// over whatever the one before drew where they cross.
+fn draw_lines(canvas: &mut Canvas<Window>, lines: &[Filter], palette: Palette, scale: u32) {
+    let edge = |n: usize| (n as u32 * scale / 2) as i32;
+    let (width, height) = (edge(HIRES_WIDTH), edge(HIRES_HEIGHT));
+
+    canvas.set_blend_mode(BlendMode::None);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
+    canvas.clear();
+
+    for filter in lines {
+        match filter {
+            Filter::Scanlines => {
+                let rects: Vec<Rect> = (0..HIRES_HEIGHT)
+                    .filter_map(|y| {
+                        let rows = edge(y + 1) - edge(y);
+                        let top = edge(y) + rows / 2;
+                        (rows >= 2)
+                            .then(|| Rect::new(0, top, width as u32, (edge(y + 1) - top) as u32))
+                    })
+                    .collect();
+                canvas.set_draw_color(Color::RGBA(0, 0, 0, SCANLINE_ALPHA));
+                canvas.fill_rects(&rects).unwrap();
+            }
+            Filter::Grid => {
+                let columns = (0..HIRES_WIDTH).map(|x| Rect::new(edge(x), 0, 1, height as u32));
+                let rows = (0..HIRES_HEIGHT).map(|y| Rect::new(0, edge(y), width as u32, 1));
+                let rects: Vec<Rect> = columns.chain(rows).collect();
+
+                let [r, g, b, _] = palette.colors[0];
+                canvas.set_draw_color(Color::RGBA(r, g, b, GRID_ALPHA));
+                canvas.fill_rects(&rects).unwrap();
+            }
+            Filter::Phosphor { .. } | Filter::Curvature => {}
+        }
+    }
+}
+
+/// How much of its length a strip `strip` wide at `pos` along `size`
+/// keeps when bent: all of it through the middle, down to `1 - CURVATURE`
+/// at the ends.
+fn squeeze(pos: u32, strip: u32, size: u32) -> f32 {
+    let middle = (pos as f32 + strip as f32 / 2.0) / size as f32 * 2.0 - 1.0;
+    1.0 - CURVATURE * middle * middle
+}
+
+/// Bends `flat` onto `rect` of the window in two passes of strips, each
+/// squeezed toward the middle: rows across into `bent`, then its columns
+/// down. Between them the edges bow out and the corners round off.
+fn bend(canvas: &mut Canvas<Window>, flat: &Texture, bent: &mut Texture, rect: Rect) {
+    let (width, height) = (rect.width(), rect.height());
+
+    canvas
+        .with_texture_canvas(bent, |c| {
+            c.set_draw_color(Color::RGB(0, 0, 0));
+            c.clear();
+            for y in (0..height).step_by(CURVATURE_STRIP as usize) {
+                let rows = CURVATURE_STRIP.min(height - y);
+                let across = (width as f32 * squeeze(y, rows, height)).round() as u32;
+                let from = Rect::new(0, y as i32, width, rows);
+                let to = Rect::new(((width - across) / 2) as i32, y as i32, across, rows);
+                c.copy(flat, from, to).unwrap();
+            }
+        })
+        .unwrap();
+
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.fill_rect(rect).unwrap();
+    for x in (0..width).step_by(CURVATURE_STRIP as usize) {
+        let columns = CURVATURE_STRIP.min(width - x);
+        let down = (height as f32 * squeeze(x, columns, width)).round() as u32;
+        let from = Rect::new(x as i32, 0, columns, height);
+        let to = Rect::new(
+            rect.x() + x as i32,
+            rect.y() + ((height - down) / 2) as i32,
+            columns,
+            down,
+        );
+        canvas.copy(bent, from, to).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use chip8_frontend::i18n::{tr, tr_args};
+use std::time::{Duration, Instant};
+
+/// Upper bounds, in milliseconds, of the histogram buckets. A 60 Hz frame is
+/// 16.7ms, so anything past the second bucket is a visible stutter.
+const BUCKETS: [f64; 6] = [15.0, 18.0, 25.0, 34.0, 50.0, 100.0];
+
+const BAR_WIDTH: usize = 40;
+
+/// Records the time between successive presents.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    last: Option<Instant>,
+    times: Vec<Duration>,
+}
+
+impl FrameStats {
+    pub fn present(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            self.times.push(now - last);
+        }
+        self.last = Some(now);
+    }
+
+    fn percentile(sorted: &[f64], p: usize) -> f64 {
+        sorted[(sorted.len() - 1) * p / 100]
+    }
+
+    pub fn report(&self) -> String {
+        if self.times.is_empty() {
+            return tr("frame-stats-empty").to_string();
+        }
+
+        let mut ms: Vec<f64> = self
+            .times
+            .iter()
+            .map(|t| t.as_secs_f64() * 1000.0)
+            .collect();
+        ms.sort_by(f64::total_cmp);
+
+        let mut out = tr_args(
+            "frame-stats-summary",
+            &[
+                &ms.len(),
+                &format!("{:.1}", Self::percentile(&ms, 50)),
+                &format!("{:.1}", Self::percentile(&ms, 95)),
+                &format!("{:.1}", Self::percentile(&ms, 99)),
+                &format!("{:.1}", ms[ms.len() - 1]),
+            ],
+        );
+
+        let mut counts = [0; BUCKETS.len() + 1];
+        for &t in &ms {
+            let bucket = BUCKETS.iter().position(|&b| t < b).unwrap_or(BUCKETS.len());
+            counts[bucket] += 1;
+        }
+
+        let most = counts.iter().copied().max().unwrap_or(1).max(1);
+        for (n, &count) in counts.iter().enumerate() {
+            let label = match n {
+                0 => format!("      <{:>3}ms", BUCKETS[0]),
+                n if n == BUCKETS.len() => format!("     >={:>3}ms", BUCKETS[n - 1]),
+                n => format!("{:>3}-{:>3}ms", BUCKETS[n - 1], BUCKETS[n]),
+            };
+            let bar = "#".repeat(count * BAR_WIDTH / most);
+
+            out.push_str(&format!("\n  {:>12} {:>6} {}", label, count, bar));
+        }
+
+        out
+    }
+}
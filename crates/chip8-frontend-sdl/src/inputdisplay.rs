@@ -0,0 +1,59 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The COSMAC VIP keypad, row by row.
+pub(crate) const LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Draws a keypad as a 4x4 grid in the COSMAC VIP layout, showing pressed
+/// keys by their hex digit and released keys as `.`.
+pub fn render(keypad: &[bool]) -> String {
+    let mut out = String::new();
+
+    for row in LAYOUT {
+        let keys: Vec<String> = row
+            .iter()
+            .map(|&key| match keypad[key] {
+                true => format!("{:X}", key),
+                false => ".".to_string(),
+            })
+            .collect();
+
+        out.push_str(&keys.join(" "));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Mirrors the pressed keys to a text file, e.g. for an OBS text source.
+/// The file is only rewritten when the keys change.
+#[derive(Debug, Clone)]
+pub struct InputDisplay {
+    path: PathBuf,
+    last: Option<String>,
+}
+
+impl InputDisplay {
+    pub fn new(path: PathBuf) -> InputDisplay {
+        InputDisplay { path, last: None }
+    }
+
+    /// Writes one grid per instance, separated by blank lines.
+    pub fn update<'a>(&mut self, keypads: impl Iterator<Item = &'a [bool]>) -> io::Result<()> {
+        let text = keypads.map(render).collect::<Vec<_>>().join("\n");
+
+        if self.last.as_ref() == Some(&text) {
+            return Ok(());
+        }
+
+        fs::write(&self.path, &text)?;
+        self.last = Some(text);
+        Ok(())
+    }
+}
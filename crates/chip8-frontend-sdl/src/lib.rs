@@ -0,0 +1,17 @@
+//! The SDL window, with its audio, controllers, overlays and the stdin
+//! debug console.
+
+mod audio;
+pub mod config;
+pub mod console;
+mod filter;
+mod framestats;
+pub mod inputdisplay;
+mod overlay;
+mod ramsearch;
+mod savestate;
+pub mod sdlgui;
+pub mod status;
+
+pub use audio::Sound;
+pub use filter::{Filter, DEFAULT_PHOSPHOR_FRAMES};
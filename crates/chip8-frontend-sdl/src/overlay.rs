@@ -0,0 +1,84 @@
+//! A tiny built-in font for text drawn over the display, such as the
+//! performance HUD. Glyphs are 3x5 pixels in a 4x6 cell and cover digits,
+//! letters (lowercase is drawn as uppercase) and a little punctuation;
+//! anything else is drawn as `?`.
+//!
+//! [`pixels`] only yields coordinates, so any frontend can plot them at
+//! whatever size and colour suits it.
+
+/// Width and height of a character cell, including spacing.
+pub const CELL_WIDTH: u32 = 4;
+pub const CELL_HEIGHT: u32 = 6;
+
+/// Five rows per glyph, three bits per row with the leftmost pixel in the
+/// highest bit.
+const FONT: [(char, [u8; 5]); 44] = [
+    ('0', [7, 5, 5, 5, 7]),
+    ('1', [2, 6, 2, 2, 7]),
+    ('2', [7, 1, 7, 4, 7]),
+    ('3', [7, 1, 7, 1, 7]),
+    ('4', [5, 5, 7, 1, 1]),
+    ('5', [7, 4, 7, 1, 7]),
+    ('6', [7, 4, 7, 5, 7]),
+    ('7', [7, 1, 2, 2, 2]),
+    ('8', [7, 5, 7, 5, 7]),
+    ('9', [7, 5, 7, 1, 7]),
+    ('A', [2, 5, 7, 5, 5]),
+    ('B', [6, 5, 6, 5, 6]),
+    ('C', [3, 4, 4, 4, 3]),
+    ('D', [6, 5, 5, 5, 6]),
+    ('E', [7, 4, 6, 4, 7]),
+    ('F', [7, 4, 6, 4, 4]),
+    ('G', [3, 4, 5, 5, 3]),
+    ('H', [5, 5, 7, 5, 5]),
+    ('I', [7, 2, 2, 2, 7]),
+    ('J', [1, 1, 1, 5, 2]),
+    ('K', [5, 5, 6, 5, 5]),
+    ('L', [4, 4, 4, 4, 7]),
+    ('M', [5, 7, 7, 5, 5]),
+    ('N', [6, 5, 5, 5, 5]),
+    ('O', [2, 5, 5, 5, 2]),
+    ('P', [6, 5, 6, 4, 4]),
+    ('Q', [2, 5, 5, 6, 3]),
+    ('R', [6, 5, 6, 5, 5]),
+    ('S', [3, 4, 2, 1, 6]),
+    ('T', [7, 2, 2, 2, 2]),
+    ('U', [5, 5, 5, 5, 7]),
+    ('V', [5, 5, 5, 5, 2]),
+    ('W', [5, 5, 7, 7, 5]),
+    ('X', [5, 5, 2, 5, 5]),
+    ('Y', [5, 5, 2, 2, 2]),
+    ('Z', [7, 1, 2, 4, 7]),
+    (' ', [0, 0, 0, 0, 0]),
+    ('.', [0, 0, 0, 0, 2]),
+    (':', [0, 2, 0, 2, 0]),
+    ('/', [1, 1, 2, 4, 4]),
+    ('-', [0, 0, 7, 0, 0]),
+    ('%', [5, 1, 2, 4, 5]),
+    ('+', [0, 2, 7, 2, 0]),
+    ('?', [7, 1, 2, 0, 2]),
+];
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    // '?' is last, so it's the fallback
+    FONT.iter()
+        .find(|&&(g, _)| g == c)
+        .or(FONT.last())
+        .map_or([0; 5], |&(_, rows)| rows)
+}
+
+/// The lit pixels of `text`, in font pixels from the top left of the first
+/// character. `\n` starts a new line.
+pub fn pixels(text: &str) -> impl Iterator<Item = (u32, u32)> + '_ {
+    text.lines().enumerate().flat_map(|(line, chars)| {
+        chars.chars().enumerate().flat_map(move |(col, c)| {
+            let rows = glyph(c);
+            (0..5u32).flat_map(move |y| {
+                (0..3u32)
+                    .filter(move |&x| rows[y as usize] & (4 >> x) != 0)
+                    .map(move |x| (col as u32 * CELL_WIDTH + x, line as u32 * CELL_HEIGHT + y))
+            })
+        })
+    })
+}
@@ -0,0 +1,68 @@
+use chip8_frontend::i18n::{tr, tr_args};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Increased,
+    Decreased,
+    Unchanged,
+    Changed,
+    EqualTo(u8),
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::Increased => write!(f, "{}", tr("filter-increased")),
+            Filter::Decreased => write!(f, "{}", tr("filter-decreased")),
+            Filter::Unchanged => write!(f, "{}", tr("filter-unchanged")),
+            Filter::Changed => write!(f, "{}", tr("filter-changed")),
+            Filter::EqualTo(n) => {
+                let n = format!("{:#04X}", n);
+                write!(f, "{}", tr_args("filter-equal-to", &[&n]))
+            }
+        }
+    }
+}
+
+/// Narrows down the addresses of a game variable by comparing successive
+/// snapshots of guest memory.
+#[derive(Debug, Clone)]
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<usize>,
+}
+
+impl RamSearch {
+    pub fn new(mem: &[u8]) -> RamSearch {
+        RamSearch {
+            snapshot: mem.to_vec(),
+            candidates: (0..mem.len()).collect(),
+        }
+    }
+
+    /// Keeps the candidates whose current value satisfies `filter` relative
+    /// to the previous snapshot, then takes a new snapshot.
+    pub fn filter(&mut self, mem: &[u8], filter: Filter) -> usize {
+        let prev = &self.snapshot;
+
+        self.candidates.retain(|&addr| {
+            let (old, new) = (prev[addr], mem[addr]);
+
+            match filter {
+                Filter::Increased => new > old,
+                Filter::Decreased => new < old,
+                Filter::Unchanged => new == old,
+                Filter::Changed => new != old,
+                Filter::EqualTo(n) => new == n,
+            }
+        });
+
+        self.snapshot.copy_from_slice(mem);
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+}
@@ -0,0 +1,80 @@
+//! Where F5 keeps its states, guarding against a stray press wiping out
+//! a save worth keeping: the save it replaces is kept for undo, and an old
+//! save can be set to need a second F5 before it's overwritten.
+
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// How soon the second F5 must follow the first to confirm an overwrite.
+const CONFIRM_WINDOW: Duration = Duration::from_secs(3);
+
+/// One save: every instance's state, and the replay position then.
+#[derive(Debug, Clone)]
+pub(crate) struct Save {
+    pub(crate) states: Vec<Vec<u8>>,
+    pub(crate) frame: usize,
+    saved_at: Instant,
+}
+
+/// What a press of F5 did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Saved {
+    Saved,
+    /// The save it would replace is this old; press again to overwrite.
+    NeedsConfirm(Duration),
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SaveSlot {
+    current: Option<Save>,
+    /// The save `current` replaced, for undo.
+    previous: Option<Save>,
+    /// Saves at least this old need confirming before they're overwritten.
+    confirm_after: Option<Duration>,
+    /// When an overwrite was last refused, awaiting confirmation.
+    pending: Option<Instant>,
+}
+
+impl SaveSlot {
+    pub(crate) fn set_confirm_after(&mut self, age: Duration) {
+        self.confirm_after = Some(age);
+    }
+
+    pub(crate) fn current(&self) -> Option<&Save> {
+        self.current.as_ref()
+    }
+
+    /// Saves `states`, unless that would overwrite a save old enough to
+    /// need confirming and this isn't the confirming press.
+    pub(crate) fn save(&mut self, states: Vec<Vec<u8>>, frame: usize, now: Instant) -> Saved {
+        if let (Some(current), Some(limit)) = (&self.current, self.confirm_after) {
+            let age = now - current.saved_at;
+            let confirmed = self
+                .pending
+                .take()
+                .is_some_and(|pending| now - pending <= CONFIRM_WINDOW);
+            if age >= limit && !confirmed {
+                self.pending = Some(now);
+                return Saved::NeedsConfirm(age);
+            }
+        }
+
+        let save = Save {
+            states,
+            frame,
+            saved_at: now,
+        };
+        self.previous = self.current.replace(save);
+        Saved::Saved
+    }
+
+    /// Brings back the save the last one replaced. Undoing again redoes
+    /// the save. Returns false when there's nothing to go back to.
+    pub(crate) fn undo(&mut self) -> bool {
+        if self.previous.is_none() {
+            return false;
+        }
+        mem::swap(&mut self.current, &mut self.previous);
+        true
+    }
+}
@@ -0,0 +1,1374 @@
+extern crate sdl2;
+
+use crate::audio::{self, PatternVoice, Sound};
+use crate::console::Command;
+use crate::filter::{Filter, Screen};
+use crate::framestats::FrameStats;
+use crate::inputdisplay::{self, InputDisplay};
+use crate::overlay;
+use crate::ramsearch::RamSearch;
+use crate::savestate::{SaveSlot, Saved};
+use crate::status::{self, IdleDetector, Status};
+use chip8_core::chip8::Chip8;
+use chip8_core::chip8::Palette;
+use chip8_core::chip8::StateError;
+use chip8_core::chip8::VIDEO_HEIGHT;
+use chip8_core::chip8::VIDEO_WIDTH;
+use chip8_core::chip8::{HIRES_HEIGHT, HIRES_WIDTH, RGBA_SIZE};
+use chip8_frontend::debugger::StepMode;
+use chip8_frontend::frontend::{self, FramePlan, FrameReport, Frontend, Machine, FRAME, KEYMAPS};
+use chip8_frontend::i18n::{tr, tr_args};
+use chip8_frontend::replay::Replay;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, TextureCreator};
+use sdl2::EventPump;
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::ops::ControlFlow;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sdl2::audio::AudioDevice;
+use sdl2::controller::{Axis as StickAxis, Button, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::surface::Surface;
+use sdl2::video::{Window, WindowContext};
+use sdl2::{GameControllerSubsystem, Sdl};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/*
+    F11         cycles through the 1x-20x window sizes
+    F5          saves every instance's state in memory
+    Shift+F5    undoes the last save, bringing back the one it replaced
+    F8          restores the states saved with F5
+    Backspace   rewinds while held (with --rewind-seconds)
+    Tab         fast-forwards while held, speeding up the longer it's held
+    Ctrl+P      pauses or continues every instance
+    Ctrl+R      resets every instance, keeping the ROMs loaded
+    F6          pauses or continues the first instance
+    F7          runs one instruction while paused, printing the CPU state
+    Shift+F7    continues until the current subroutine returns
+    Ctrl+F7     runs to the end of the frame while paused
+    Alt+F7      runs until the display changes while paused
+    F9          prints frame-time stats (with --frame-stats)
+    F12         saves each instance's framebuffer at 1x
+    Shift+F12   saves the window as rendered
+*/
+
+const MAX_SCALE: u32 = 20;
+
+/// Keypad overlay colours for keys held, keys the game reads and the rest.
+const KEY_HELD: Color = Color::RGBA(255, 200, 40, 255);
+const KEY_USED: Color = Color::RGBA(220, 220, 220, 200);
+const KEY_UNUSED: Color = Color::RGBA(220, 220, 220, 50);
+
+/*
+    With --auto-keys, each axis goes to the first pair of keys the game reads
+
+    Up/Down         W/S     2/8, 2/5, 1/4
+    Left/Right      A/D     4/6, 7/9
+
+    and any other keys it reads go to Space, then Return.
+*/
+
+type Axis = (
+    [&'static str; 2],
+    [&'static str; 2],
+    &'static [(usize, usize)],
+);
+
+const AUTO_AXES: [Axis; 2] = [
+    (
+        ["Up", "W"],
+        ["Down", "S"],
+        &[(0x2, 0x8), (0x2, 0x5), (0x1, 0x4)],
+    ),
+    (["Left", "A"], ["Right", "D"], &[(0x4, 0x6), (0x7, 0x9)]),
+];
+const AUTO_BUTTONS: [&str; 2] = ["Space", "Return"];
+
+fn rgba([r, g, b, a]: [u8; 4]) -> Color {
+    Color::RGBA(r, g, b, a)
+}
+
+/// Loads a state but leaves the keypad as the host keys are held now, since
+/// any releases since the state was taken were already delivered.
+fn load_keeping_keys(cpu: &mut Chip8, state: &[u8]) -> Result<(), StateError> {
+    let keypad = cpu.get_keypad().to_vec();
+    cpu.load_state(state)?;
+    for (key, &pressed) in keypad.iter().enumerate() {
+        cpu.set_keypad(key, pressed);
+    }
+    Ok(())
+}
+
+/// Picks ergonomic host keys for the keypad keys a game reads.
+pub fn auto_bindings(keys: &[bool; 16]) -> Vec<(&'static str, usize)> {
+    let mut unused = *keys;
+    let mut bindings = vec![];
+
+    for (low, high, pairs) in AUTO_AXES {
+        if let Some(&(a, b)) = pairs.iter().find(|&&(a, b)| unused[a] && unused[b]) {
+            unused[a] = false;
+            unused[b] = false;
+            bindings.extend(low.map(|name| (name, a)));
+            bindings.extend(high.map(|name| (name, b)));
+        }
+    }
+
+    let rest = (0..16).filter(|&key| unused[key]);
+    bindings.extend(AUTO_BUTTONS.into_iter().zip(rest));
+
+    bindings
+}
+
+/// Varies the number of instructions run each frame by up to `amount` in
+/// either direction, mimicking the uneven timing of real hardware.
+#[derive(Debug, Clone)]
+pub struct Jitter {
+    amount: u32,
+    rng: ChaCha8Rng,
+}
+
+impl Jitter {
+    pub fn new(amount: u32, seed: u64) -> Jitter {
+        Jitter {
+            amount,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    fn instructions(&mut self, base: u32) -> u32 {
+        let low = base.saturating_sub(self.amount).max(1);
+        let high = base + self.amount;
+
+        self.rng.gen_range(low..=high)
+    }
+}
+
+/// How far the speed moves toward its target each frame, so a focus change
+/// eases in over a third of a second instead of jumping.
+const SPEED_STEP: f32 = 0.05;
+
+/// Fast-forward starts at this multiple of normal speed.
+const FAST_FORWARD_START: f32 = 2.0;
+
+/// How fast-forward ramps up: from 2x when Tab is pressed to `max` once it
+/// has been held for `ramp`, rising by the same factor each second. `mute`
+/// silences the guest meanwhile; otherwise it keeps its normal pitch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastForward {
+    pub max: u32,
+    pub ramp: Duration,
+    pub mute: bool,
+}
+
+impl Default for FastForward {
+    fn default() -> FastForward {
+        FastForward {
+            max: 16,
+            ramp: Duration::from_secs(3),
+            mute: false,
+        }
+    }
+}
+
+impl FastForward {
+    /// The speed after fast-forwarding for `held`.
+    fn speed(&self, held: Duration) -> f32 {
+        let max = (self.max as f32).max(FAST_FORWARD_START);
+        let progress = match self.ramp.is_zero() {
+            true => 1.0,
+            false => (held.as_secs_f32() / self.ramp.as_secs_f32()).min(1.0),
+        };
+
+        FAST_FORWARD_START * (max / FAST_FORWARD_START).powf(progress)
+    }
+}
+
+/// Slows emulation while the window is unfocused, and paces frames faster
+/// while fast-forwarding.
+#[derive(Debug, Clone)]
+struct Throttle {
+    background: f32,
+    focused: bool,
+    speed: f32,
+    carry: f32,
+    fast_forward: FastForward,
+    /// When Tab was pressed, while it's held.
+    held_since: Option<Instant>,
+}
+
+impl Throttle {
+    fn new() -> Throttle {
+        Throttle {
+            background: 1.0,
+            focused: true,
+            speed: 1.0,
+            carry: 0.0,
+            fast_forward: FastForward::default(),
+            held_since: None,
+        }
+    }
+
+    /// How long a frame of `base` length takes at the fast-forward speed.
+    /// Whole frames run faster, timers and all, so games speed up evenly.
+    fn frame_duration(&self, base: Duration) -> Duration {
+        match self.held_since {
+            Some(since) => base.div_f32(self.fast_forward.speed(since.elapsed())),
+            None => base,
+        }
+    }
+
+    fn muted(&self) -> bool {
+        self.held_since.is_some() && self.fast_forward.mute
+    }
+
+    /// Scales a frame's instruction budget by the current speed, carrying
+    /// fractions over so that e.g. 10% of 10 still runs one per frame.
+    fn instructions(&mut self, ipf: u32) -> u32 {
+        let target = if self.focused { 1.0 } else { self.background };
+        self.speed += (target - self.speed).clamp(-SPEED_STEP, SPEED_STEP);
+
+        self.carry += ipf as f32 * self.speed;
+        let n = self.carry as u32;
+        self.carry -= n as f32;
+        n
+    }
+}
+
+/*
+    A game controller's left stick and D-pad press 2/4/6/8, the usual
+    directions, and A/B/X/Y press 5/0/A/B unless the config file's
+    [controller] table says otherwise. Controllers drive instances in the
+    order they're connected; any beyond the last instance share it.
+*/
+
+const STICK_KEYS: [(StickAxis, [usize; 2]); 2] = [
+    (StickAxis::LeftX, [0x4, 0x6]),
+    (StickAxis::LeftY, [0x2, 0x8]),
+];
+
+const BUTTON_KEYS: [(Button, usize); 8] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadDown, 0x8),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::A, 0x5),
+    (Button::B, 0x0),
+    (Button::X, 0xA),
+    (Button::Y, 0xB),
+];
+
+/// The first instance's input, while a replay is being recorded or played.
+#[derive(Debug, Clone)]
+enum ReplayRun {
+    Recording(Replay),
+    Playing { replay: Replay, frame: usize },
+}
+
+impl ReplayRun {
+    /// How many frames in the run is.
+    fn position(&self) -> usize {
+        match self {
+            ReplayRun::Recording(replay) => replay.len(),
+            ReplayRun::Playing { frame, .. } => *frame,
+        }
+    }
+}
+
+/// When an analog stick counts as pushed, as percentages of full travel.
+/// A direction presses at `deadzone` and releases only once the stick falls
+/// `hysteresis` below that, so a stick resting near the edge doesn't chatter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stick {
+    pub deadzone: u32,
+    pub hysteresis: u32,
+}
+
+impl Default for Stick {
+    fn default() -> Stick {
+        Stick {
+            deadzone: 50,
+            hysteresis: 10,
+        }
+    }
+}
+
+impl Stick {
+    /// Which way (-1, 0 or 1) an axis is held after moving to `value`, given
+    /// which way it was held before.
+    fn direction(&self, held: i8, value: i16) -> i8 {
+        let travel = |percent: u32| percent.min(100) as i32 * i16::MAX as i32 / 100;
+        let press = travel(self.deadzone);
+        let release = travel(self.deadzone.saturating_sub(self.hysteresis));
+        let value = value as i32;
+
+        if value >= press {
+            1
+        } else if value <= -press {
+            -1
+        } else if value * held as i32 > release {
+            held
+        } else {
+            0
+        }
+    }
+}
+
+/// A connected controller, the direction each of its `STICK_KEYS` axes is
+/// held and the bound buttons it holds down.
+struct Controller {
+    _device: GameController,
+    id: u32,
+    held: [i8; 2],
+    buttons: Vec<Button>,
+}
+
+/// Collects how many instructions a game runs each frame before it stalls
+/// on Fx0A or a delay-timer loop, to suggest an instructions-per-frame value.
+#[derive(Debug, Clone, Default)]
+pub struct Calibration {
+    frames: u32,
+    work: Vec<u32>,
+}
+
+impl Calibration {
+    fn record(&mut self, work: Option<u32>) {
+        self.frames += 1;
+        self.work.extend(work);
+    }
+
+    /// Suggests enough instructions per frame to cover nearly every frame's
+    /// work with some headroom, or `None` if the game rarely stalls and so
+    /// has no pace of its own to calibrate against, or if no frame ran.
+    pub fn suggest(&self) -> Option<u32> {
+        if self.work.is_empty() || self.work.len() * 10 < self.frames as usize {
+            return None;
+        }
+
+        let mut work = self.work.clone();
+        work.sort_unstable();
+        let p95 = work[(work.len() - 1) * 95 / 100];
+
+        Some((p95 * 5).div_ceil(4).max(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screenshot {
+    Framebuffer,
+    Window,
+}
+
+/// The window, opened ahead of `SDLGui` so that the textures it draws
+/// through can borrow the window's texture creator.
+pub struct Display {
+    sdl_context: Sdl,
+    canvas: Canvas<Window>,
+    instances: usize,
+    scale: u32,
+    scale_step: u32,
+}
+
+impl Display {
+    /// Opens a window for `instances` side by side at `scale`, rounded to
+    /// an even scale with `hires_snap`.
+    pub fn open(instances: usize, scale: u32, hires_snap: bool) -> Display {
+        assert!(
+            (1..=KEYMAPS.len()).contains(&instances),
+            "Unsupported number of instances"
+        );
+
+        // Snapping to 128x64 means every pixel is drawn an even number of
+        // screen pixels wide.
+        let scale_step = if hires_snap { 2 } else { 1 };
+        let scale = (scale / scale_step).clamp(1, MAX_SCALE / scale_step) * scale_step;
+
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+
+        let window = video_subsystem
+            .window(
+                "CHIP8 Rust",
+                VIDEO_WIDTH as u32 * scale * instances as u32,
+                VIDEO_HEIGHT as u32 * scale,
+            )
+            .position_centered()
+            .resizable()
+            .opengl()
+            .build()
+            .unwrap();
+
+        let canvas = window.into_canvas().build().unwrap();
+
+        Display {
+            sdl_context,
+            canvas,
+            instances,
+            scale,
+            scale_step,
+        }
+    }
+
+    pub fn texture_creator(&self) -> TextureCreator<WindowContext> {
+        self.canvas.texture_creator()
+    }
+}
+
+/// Shows the instances side by side in a window, driven by
+/// `frontend::run`.
+pub struct SDLGui<'r> {
+    _sdl_context: Sdl,
+    canvas: Canvas<Window>,
+    /// The textures each instance is drawn through.
+    screens: Vec<Screen<'r>>,
+    event_pump: EventPump,
+    instances: usize,
+    scale: u32,
+    scale_step: u32,
+    keymap: HashMap<String, (usize, usize)>,
+    console: Option<Receiver<Command>>,
+    ram_search: Option<RamSearch>,
+    idle: Option<Vec<IdleDetector>>,
+    jitter: Option<Jitter>,
+    perf_hud: bool,
+    /// Per instance, the keys drawn lit on the keypad overlay.
+    keypad_overlay: Option<Vec<[bool; 16]>>,
+    screenshot: Option<Screenshot>,
+    input_display: Option<InputDisplay>,
+    throttle: Throttle,
+    calibration: Option<Calibration>,
+    frame_stats: Option<FrameStats>,
+    palette: Palette,
+    filters: Vec<Filter>,
+    audio: Option<AudioDevice<PatternVoice>>,
+    controller_subsystem: Option<GameControllerSubsystem>,
+    controllers: Vec<Controller>,
+    buttons: HashMap<Button, usize>,
+    stick: Stick,
+    states: SaveSlot,
+    replay: Option<ReplayRun>,
+    rewinding: bool,
+    /// Paused with Ctrl+P, as opposed to by the debugger.
+    paused: bool,
+    /// How many of the 60 frames a second are drawn.
+    fps: u32,
+    /// Frames run so far, to pick which ones to draw.
+    frames: u64,
+}
+
+impl<'r> SDLGui<'r> {
+    /// Takes over `display`, drawing through textures from `creator`,
+    /// which must be the display's.
+    pub fn new(
+        display: Display,
+        creator: &'r TextureCreator<WindowContext>,
+        announce: bool,
+        jitter: Option<Jitter>,
+        perf_hud: bool,
+        console: Option<Receiver<Command>>,
+    ) -> SDLGui<'r> {
+        let Display {
+            sdl_context,
+            canvas,
+            instances,
+            scale,
+            scale_step,
+        } = display;
+        let screens = (0..instances).map(|_| Screen::new(creator)).collect();
+        let event_pump = sdl_context.event_pump().unwrap();
+
+        // Sound is optional; a machine without an audio device still runs
+        let audio = audio::open(&sdl_context)
+            .map_err(|e| eprintln!("{}", tr_args("err-audio", &[&e])))
+            .ok();
+
+        // Likewise controllers; they're opened as SDL reports them, which
+        // includes any already connected at startup
+        let controller_subsystem = sdl_context.game_controller().ok();
+
+        let keymap = KEYMAPS
+            .iter()
+            .take(instances)
+            .enumerate()
+            .flat_map(|(instance, keys)| {
+                keys.iter()
+                    .enumerate()
+                    .map(move |(key, name)| (name.to_string(), (instance, key)))
+            })
+            .collect();
+
+        let idle = announce.then(|| vec![IdleDetector::new(); instances]);
+
+        SDLGui {
+            _sdl_context: sdl_context,
+            canvas,
+            screens,
+            event_pump,
+            instances,
+            scale,
+            scale_step,
+            keymap,
+            console,
+            ram_search: None,
+            idle,
+            jitter,
+            perf_hud,
+            keypad_overlay: None,
+            screenshot: None,
+            input_display: None,
+            throttle: Throttle::new(),
+            calibration: None,
+            frame_stats: None,
+            palette: Palette::default(),
+            filters: vec![],
+            audio,
+            controller_subsystem,
+            controllers: vec![],
+            buttons: BUTTON_KEYS.into_iter().collect(),
+            stick: Stick::default(),
+            states: SaveSlot::default(),
+            replay: None,
+            rewinding: false,
+            paused: false,
+            fps: 60,
+            frames: 0,
+        }
+    }
+
+    /// Binds extra host keys to an instance's keypad, replacing any existing
+    /// binding for those host keys.
+    pub fn bind_keys(&mut self, instance: usize, bindings: &[(&'static str, usize)]) {
+        for &(name, key) in bindings {
+            self.keymap.insert(name.to_string(), (instance, key));
+        }
+    }
+
+    /// Replaces an instance's keyboard layout with `keys`, indexed by the
+    /// CHIP-8 key they press, as `config::load` returns them.
+    pub fn set_keymap(&mut self, instance: usize, keys: &[String; 16]) {
+        self.keymap.retain(|_, &mut (n, _)| n != instance);
+        for (key, name) in keys.iter().enumerate() {
+            self.keymap.insert(name.clone(), (instance, key));
+        }
+    }
+
+    /// Has F5 ask for a second press before replacing states saved at
+    /// least `age` ago.
+    pub fn set_confirm_overwrite(&mut self, age: Duration) {
+        self.states.set_confirm_after(age);
+    }
+
+    pub fn set_input_display(&mut self, input_display: InputDisplay) {
+        self.input_display = Some(input_display);
+    }
+
+    /// Draws a keypad in the corner of each instance, dimming the keys
+    /// `used` doesn't list for it, e.g. from `analysis::keys_read`.
+    pub fn set_keypad_overlay(&mut self, used: Vec<[bool; 16]>) {
+        self.keypad_overlay = Some(used);
+    }
+
+    /// Colours the display and screenshots.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Draws `fps` of the 60 frames a second, spread evenly, for slow hosts
+    /// or displays. Emulation and timers stay at 60 Hz.
+    pub fn set_display_fps(&mut self, fps: u32) {
+        self.fps = fps.clamp(1, 60);
+    }
+
+    pub fn start_calibration(&mut self) {
+        self.calibration = Some(Calibration::default());
+    }
+
+    pub fn start_recording(&mut self, replay: Replay) {
+        self.replay = Some(ReplayRun::Recording(replay));
+    }
+
+    pub fn start_playback(&mut self, replay: Replay) {
+        self.replay = Some(ReplayRun::Playing { replay, frame: 0 });
+    }
+
+    /// The replay being recorded, with whatever was branched away dropped.
+    pub fn recording(&self) -> Option<&Replay> {
+        match &self.replay {
+            Some(ReplayRun::Recording(replay)) => Some(replay),
+            _ => None,
+        }
+    }
+
+    pub fn start_frame_stats(&mut self) {
+        self.frame_stats = Some(FrameStats::default());
+    }
+
+    pub fn frame_stats(&self) -> Option<&FrameStats> {
+        self.frame_stats.as_ref()
+    }
+
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    /// Sets the speed, as a fraction of normal, to run at while the window
+    /// is unfocused.
+    pub fn set_background_speed(&mut self, speed: f32) {
+        self.throttle.background = speed;
+    }
+
+    /// Sets how the beep sounds; `None` closes the audio device.
+    pub fn set_sound(&mut self, sound: Option<Sound>) {
+        match sound {
+            Some(sound) => {
+                if let Some(device) = &mut self.audio {
+                    device.lock().configure(sound);
+                }
+            }
+            None => self.audio = None,
+        }
+    }
+
+    pub fn set_fast_forward(&mut self, fast_forward: FastForward) {
+        self.throttle.fast_forward = fast_forward;
+    }
+
+    /// Post-processing drawn over the framebuffers, in order. Dropped with
+    /// a warning where the renderer can't draw into textures.
+    pub fn set_filters(&mut self, filters: Vec<Filter>) {
+        self.filters = filters;
+        if !self.filters.is_empty() && !self.canvas.render_target_supported() {
+            eprintln!("{}", tr("filters-unsupported"));
+            self.filters.clear();
+        }
+    }
+
+    pub fn set_stick(&mut self, stick: Stick) {
+        self.stick = stick;
+    }
+
+    /// Binds controller buttons to CHIP-8 keys, replacing the defaults for
+    /// those buttons.
+    pub fn bind_buttons(&mut self, buttons: &[(Button, usize)]) {
+        self.buttons.extend(buttons.iter().copied());
+    }
+
+    fn add_controller(&mut self, index: u32) {
+        let Some(subsystem) = &self.controller_subsystem else {
+            return;
+        };
+        if let Ok(device) = subsystem.open(index) {
+            let id = device.instance_id();
+            self.controllers.push(Controller {
+                _device: device,
+                id,
+                held: [0; 2],
+                buttons: vec![],
+            });
+        }
+    }
+
+    fn remove_controller(&mut self, cpus: &mut [Chip8], id: u32) {
+        if let Some(n) = self.controllers.iter().position(|c| c.id == id) {
+            for axis in 0..STICK_KEYS.len() {
+                self.move_stick(cpus, n, axis, 0);
+            }
+            for button in mem::take(&mut self.controllers[n].buttons) {
+                self.press_button(cpus, n, button, false);
+            }
+            self.controllers.remove(n);
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        let (id, status) = match self.paused {
+            true => ("emulation-paused", Status::Paused),
+            false => ("emulation-resumed", Status::Unpaused),
+        };
+        println!("{}", tr(id));
+        if self.idle.is_some() {
+            status::announce(status);
+        }
+    }
+
+    /// Soft-resets every instance. A replay only holds input, so it can't
+    /// follow a reset and they're refused while one runs.
+    fn reset(&mut self, cpus: &mut [Chip8]) {
+        if self.replay.is_some() {
+            println!("{}", tr("reset-replay"));
+            return;
+        }
+
+        for cpu in cpus.iter_mut() {
+            cpu.reset();
+        }
+        println!("{}", tr("reset-done"));
+        if self.idle.is_some() {
+            status::announce(Status::Reset);
+        }
+    }
+
+    /// Presses or releases the key bound to `button` on the `n`th controller.
+    fn press_button(&mut self, cpus: &mut [Chip8], n: usize, button: Button, down: bool) {
+        let instance = n.min(self.instances - 1);
+        let Some(&key) = self.buttons.get(&button) else {
+            return;
+        };
+
+        let held = &mut self.controllers[n].buttons;
+        held.retain(|&b| b != button);
+        if down {
+            held.push(button);
+        }
+        cpus[instance].set_keypad(key, down);
+    }
+
+    /// Updates the keypad for the `axis`th of `STICK_KEYS` on the `n`th
+    /// controller.
+    fn move_stick(&mut self, cpus: &mut [Chip8], n: usize, axis: usize, value: i16) {
+        let instance = n.min(self.instances - 1);
+        let [low, high] = STICK_KEYS[axis].1;
+        let held = self.controllers[n].held[axis];
+        let next = self.stick.direction(held, value);
+        if next == held {
+            return;
+        }
+
+        self.controllers[n].held[axis] = next;
+        let key = |direction: i8| if direction < 0 { low } else { high };
+        if held != 0 {
+            cpus[instance].set_keypad(key(held), false);
+        }
+        if next != 0 {
+            cpus[instance].set_keypad(key(next), true);
+        }
+    }
+
+    /// Largest scale, in multiples of `scale_step`, whose display fits in a
+    /// window of the given size.
+    fn snap_scale(&self, width: i32, height: i32) -> u32 {
+        let cols = (VIDEO_WIDTH * self.instances) as u32;
+        let fit = (width.max(0) as u32 / cols).min(height.max(0) as u32 / VIDEO_HEIGHT as u32);
+
+        (fit / self.scale_step).clamp(1, MAX_SCALE / self.scale_step) * self.scale_step
+    }
+
+    fn set_scale(&mut self, scale: u32) {
+        self.scale = scale;
+
+        if self.idle.is_some() {
+            status::announce(Status::Scale(scale));
+        }
+
+        let width = (VIDEO_WIDTH * self.instances) as u32 * scale;
+        let height = VIDEO_HEIGHT as u32 * scale;
+        self.canvas.window_mut().set_size(width, height).unwrap();
+    }
+
+    fn read_keys(&mut self, machine: &mut Machine) -> bool {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in events {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return false,
+                Event::Window {
+                    win_event: WindowEvent::Resized(w, h),
+                    ..
+                } => {
+                    let scale = self.snap_scale(w, h);
+                    self.set_scale(scale);
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => self.throttle.focused = true,
+                // Focus depends on the host, so it can't affect deterministic runs.
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } if !machine.deterministic => self.throttle.focused = false,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => {
+                    let scale = if self.scale + self.scale_step > MAX_SCALE {
+                        self.scale_step
+                    } else {
+                        self.scale + self.scale_step
+                    };
+                    self.set_scale(scale);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    match self.states.undo() {
+                        true => println!("{}", tr("state-save-undone")),
+                        false => println!("{}", tr("state-nothing-to-undo")),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => self.save_states(&machine.cpus),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F8),
+                    repeat: false,
+                    ..
+                } => self.load_states(&mut machine.cpus),
+                // Plain P and R are keypad keys
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => self.toggle_pause(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::R),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.reset(&mut machine.cpus)
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => self.rewinding = true,
+                Event::KeyUp {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => self.rewinding = false,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => {
+                    self.throttle.held_since.get_or_insert_with(Instant::now);
+                }
+                Event::KeyUp {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => self.throttle.held_since = None,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    repeat: false,
+                    ..
+                } => {
+                    if machine.debugger.is_paused() {
+                        machine.debugger.resume(&machine.cpus[0]);
+                        println!("{}", tr("debugger-resumed"));
+                    } else {
+                        machine.debugger.pause();
+                        println!("{}\n{}", tr("debugger-paused"), machine.cpus[0]);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    keymod,
+                    ..
+                } if machine.debugger.is_paused() => {
+                    let mode = if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        StepMode::Frame
+                    } else if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+                        StepMode::UntilDisplayChanges
+                    } else {
+                        StepMode::Instruction
+                    };
+
+                    let (cpu, debugger) = (&mut machine.cpus[0], &mut machine.debugger);
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        debugger.run_until_return(cpu);
+                    } else {
+                        match debugger.advance(cpu, mode, machine.ipf) {
+                            Ok(()) => println!("{}", cpu),
+                            Err(e) => eprintln!("{}", tr_args("err-guest", &[&e])),
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(stats) = &self.frame_stats {
+                        println!("{}", stats.report());
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    keymod,
+                    repeat: false,
+                    ..
+                } => {
+                    self.screenshot = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        Some(Screenshot::Window)
+                    } else {
+                        Some(Screenshot::Framebuffer)
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(k), ..
+                } => {
+                    if let Some(&(cpu, key)) = self.keymap.get(k.to_string().as_str()) {
+                        machine.cpus[cpu].set_keypad(key, true);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(k), ..
+                } => {
+                    if let Some(&(cpu, key)) = self.keymap.get(k.to_string().as_str()) {
+                        machine.cpus[cpu].set_keypad(key, false);
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => self.add_controller(which),
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.remove_controller(&mut machine.cpus, which)
+                }
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } => {
+                    let n = self.controllers.iter().position(|c| c.id == which);
+                    let axis = STICK_KEYS.iter().position(|&(a, _)| a == axis);
+                    if let (Some(n), Some(axis)) = (n, axis) {
+                        self.move_stick(&mut machine.cpus, n, axis, value);
+                    }
+                }
+                Event::ControllerButtonDown { which, button, .. } => {
+                    if let Some(n) = self.controllers.iter().position(|c| c.id == which) {
+                        self.press_button(&mut machine.cpus, n, button, true);
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some(n) = self.controllers.iter().position(|c| c.id == which) {
+                        self.press_button(&mut machine.cpus, n, button, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.read_pen(&mut machine.cpus);
+
+        true
+    }
+
+    /// Passes the mouse to each instance as a light pen, in that instance's
+    /// display pixels. Buttons only register on the instance under the cursor.
+    fn read_pen(&mut self, cpus: &mut [Chip8]) {
+        let mouse = self.event_pump.mouse_state();
+        let x = mouse.x().max(0) as u32 * 2 / self.scale;
+        let y = (mouse.y().max(0) as u32 * 2 / self.scale).min(HIRES_HEIGHT as u32 - 1);
+        let buttons = mouse.left() as u8 | (mouse.right() as u8) << 1;
+
+        let hovered = x as usize / HIRES_WIDTH;
+        for (n, cpu) in cpus.iter_mut().enumerate() {
+            let local = x.saturating_sub((n * HIRES_WIDTH) as u32);
+            let local = local.min(HIRES_WIDTH as u32 - 1);
+            let buttons = if n == hovered { buttons } else { 0 };
+
+            match cpu.is_hires() {
+                true => cpu.set_pen(local as u8, y as u8, buttons),
+                false => cpu.set_pen((local / 2) as u8, (y / 2) as u8, buttons),
+            }
+        }
+    }
+
+    fn run_console(&mut self, machine: &mut Machine) {
+        let Some(console) = &self.console else {
+            return;
+        };
+
+        for cmd in console.try_iter() {
+            let mem = &machine.cpus[0].get_memory();
+
+            match cmd {
+                Command::Search => {
+                    let search = RamSearch::new(mem);
+                    let n = search.candidates().len();
+                    println!("{}", tr_args("ramsearch-started", &[&n]));
+                    self.ram_search = Some(search);
+                }
+                Command::Filter(filter) => match &mut self.ram_search {
+                    Some(search) => {
+                        let n = search.filter(mem, filter);
+                        println!("{}", tr_args("ramsearch-filtered", &[&n, &filter]));
+                    }
+                    None => println!("{}", tr("ramsearch-none")),
+                },
+                Command::CallGraph(path) => match fs::write(&path, machine.call_graph.to_dot()) {
+                    Ok(()) => println!("{}", tr_args("callgraph-saved", &[&path.display()])),
+                    Err(e) => eprintln!("{}", tr_args("err-callgraph", &[&e])),
+                },
+                Command::Break(addr) => {
+                    machine.debugger.add_breakpoint(addr);
+                    let addr = format!("{:#05X}", addr);
+                    println!("{}", tr_args("breakpoint-set", &[&addr]));
+                }
+                Command::BreakOpcode(pattern) => {
+                    machine.debugger.add_opcode_breakpoint(pattern);
+                    println!("{}", tr("breakpoint-opcode-set"));
+                }
+                Command::Watch(range, access) => {
+                    let text = format!("{:#05X}-{:#05X}", range.start(), range.end());
+                    machine.debugger.add_watchpoint(range, access);
+                    machine.cpus[0].set_trace_memory(true);
+                    println!("{}", tr_args("watchpoint-set", &[&text]));
+                }
+                Command::ClearBreakpoints => {
+                    machine.debugger.clear_breakpoints();
+                    machine.cpus[0].set_trace_memory(false);
+                    println!("{}", tr("breakpoints-cleared"));
+                }
+                Command::List => match &self.ram_search {
+                    Some(search) => {
+                        for &addr in search.candidates().iter().take(64) {
+                            println!("  {:#05X}: {:#04X}", addr, mem[addr]);
+                        }
+                        if search.candidates().len() > 64 {
+                            let more = search.candidates().len() - 64;
+                            println!("{}", tr_args("ramsearch-more", &[&more]));
+                        }
+                    }
+                    None => println!("{}", tr("ramsearch-none")),
+                },
+            }
+        }
+    }
+
+    /// Draws `text` in the overlay font with its top left at `x`, `y`, each
+    /// font pixel `size` screen pixels square.
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, size: u32, color: Color) {
+        let rects: Vec<Rect> = overlay::pixels(text)
+            .map(|(px, py)| Rect::new(x + (px * size) as i32, y + (py * size) as i32, size, size))
+            .collect();
+
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rects(&rects).unwrap();
+    }
+
+    /// Draws a bar along the top of each instance showing how much of this
+    /// frame's instruction budget ran before stalling on Fx0A, with the
+    /// count below it. The bar turns red when the whole budget was used.
+    fn draw_perf_hud(&mut self, used: &[u32], ipf: u32) {
+        let width = VIDEO_WIDTH as u32 * self.scale;
+        let height = (self.scale / 4).max(2);
+        let size = (self.scale / 4).max(1);
+
+        for (n, &used) in used.iter().enumerate() {
+            let color = if used >= ipf {
+                Color::RGB(220, 40, 40)
+            } else {
+                Color::RGB(40, 200, 40)
+            };
+
+            let x = (n as u32 * width) as i32;
+            let rect = Rect::new(x, 0, width * used / ipf.max(1), height);
+            self.canvas.set_draw_color(color);
+            self.canvas.fill_rect(rect).unwrap();
+
+            let text = format!("{}/{}", used, ipf);
+            let y = (height + size) as i32;
+            self.draw_text(&text, x + size as i32, y, size, color);
+        }
+    }
+
+    /// Draws the keypad in the bottom right of each instance: held keys
+    /// bright, keys the game uses plain and the rest dimmed.
+    fn draw_keypad_overlay(&mut self, cpus: &[Chip8], used: &[[bool; 16]]) {
+        let width = VIDEO_WIDTH as u32 * self.scale;
+        let size = (self.scale / 4).max(1);
+        let (cell_width, cell_height) = (overlay::CELL_WIDTH * size, overlay::CELL_HEIGHT * size);
+        let top = (VIDEO_HEIGHT as u32 * self.scale - 4 * cell_height - size) as i32;
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        for (n, used) in used.iter().enumerate() {
+            let left = ((n as u32 + 1) * width - 4 * cell_width - size) as i32;
+            let keypad = cpus[n].get_keypad().to_vec();
+
+            for (row, keys) in inputdisplay::LAYOUT.iter().enumerate() {
+                for (column, &key) in keys.iter().enumerate() {
+                    let color = match (keypad[key], used[key]) {
+                        (true, _) => KEY_HELD,
+                        (false, true) => KEY_USED,
+                        (false, false) => KEY_UNUSED,
+                    };
+                    let x = left + (column as u32 * cell_width) as i32;
+                    let y = top + (row as u32 * cell_height) as i32;
+                    self.draw_text(&format!("{:X}", key), x, y, size, color);
+                }
+            }
+        }
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    /// Saves every instance's state for F8.
+    fn save_states(&mut self, cpus: &[Chip8]) {
+        let states = cpus.iter().map(Chip8::save_state).collect();
+        let frame = self.replay.as_ref().map_or(0, ReplayRun::position);
+
+        match self.states.save(states, frame, Instant::now()) {
+            Saved::Saved => println!("{}", tr("state-saved")),
+            Saved::NeedsConfirm(age) => {
+                let minutes = age.as_secs() / 60;
+                println!("{}", tr_args("state-confirm-overwrite", &[&minutes]));
+            }
+        }
+    }
+
+    /// Restores the states saved with F5.
+    fn load_states(&mut self, cpus: &mut [Chip8]) {
+        let Some(save) = self.states.current() else {
+            println!("{}", tr("state-none"));
+            return;
+        };
+        let saved_frame = save.frame;
+
+        for (cpu, state) in cpus.iter_mut().zip(&save.states) {
+            if let Err(e) = load_keeping_keys(cpu, state) {
+                eprintln!("{}", tr_args("err-load-state", &[&e]));
+                return;
+            }
+        }
+        println!("{}", tr("state-loaded"));
+
+        match &mut self.replay {
+            Some(ReplayRun::Recording(replay)) => {
+                replay.branch(saved_frame);
+                let frame = saved_frame;
+                println!(
+                    "{}",
+                    tr_args("replay-branched", &[&frame, &replay.rerecords])
+                );
+            }
+            Some(ReplayRun::Playing { frame, .. }) => *frame = saved_frame,
+            None => {}
+        }
+    }
+
+    /// Adds the first instance's keypad to the replay being recorded, or
+    /// sets it from the one being played back.
+    fn replay_frame(&mut self, cpus: &mut [Chip8]) {
+        match &mut self.replay {
+            Some(ReplayRun::Recording(replay)) => replay.record(cpus[0].get_keypad()),
+            Some(ReplayRun::Playing { replay, frame }) => match replay.keys(*frame) {
+                Some(keys) => {
+                    for (key, held) in keys.into_iter().enumerate() {
+                        cpus[0].set_keypad(key, held);
+                    }
+                    *frame += 1;
+                }
+                None => {
+                    println!("{}", tr("replay-finished"));
+                    self.replay = None;
+                }
+            },
+            None => {}
+        }
+    }
+
+    /// Saves every instance's raw framebuffer, one pixel per CHIP-8 pixel.
+    fn save_framebuffers(&self, cpus: &[Chip8], stamp: u64) -> Result<(), String> {
+        for (n, cpu) in cpus.iter().enumerate() {
+            let mut pixels = vec![0; RGBA_SIZE];
+            cpu.render_rgba(&mut pixels, self.palette);
+            let surface = Surface::from_data(
+                &mut pixels,
+                HIRES_WIDTH as u32,
+                HIRES_HEIGHT as u32,
+                HIRES_WIDTH as u32 * 4,
+                PixelFormatEnum::RGBA32,
+            )?;
+
+            let path = format!("screenshot-{}-{}.bmp", stamp, n);
+            surface.save_bmp(&path)?;
+            println!("{}", tr_args("screenshot-saved", &[&path]));
+        }
+
+        Ok(())
+    }
+
+    /// Saves the current back buffer, including anything drawn over the
+    /// display. Must run before the frame is presented.
+    fn save_window(&self, stamp: u64) -> Result<(), String> {
+        let format = PixelFormatEnum::RGB24;
+        let (width, height) = self.canvas.output_size()?;
+        let mut pixels = self.canvas.read_pixels(None, format)?;
+
+        let surface = Surface::from_data(&mut pixels, width, height, width * 3, format)?;
+        let path = format!("screenshot-{}-window.bmp", stamp);
+        surface.save_bmp(&path)?;
+        println!("{}", tr_args("screenshot-saved", &[&path]));
+
+        Ok(())
+    }
+
+    fn take_screenshot(&mut self, cpus: &[Chip8]) {
+        let Some(kind) = self.screenshot.take() else {
+            return;
+        };
+
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let res = match kind {
+            Screenshot::Framebuffer => self.save_framebuffers(cpus, stamp),
+            Screenshot::Window => self.save_window(stamp),
+        };
+
+        if let Err(e) = res {
+            eprintln!("{}", tr_args("err-screenshot", &[&e]));
+        }
+    }
+
+    fn quit(&self) {
+        if self.idle.is_some() {
+            status::announce(Status::Quit);
+        }
+        if let Some(audio) = &self.audio {
+            audio.pause();
+        }
+    }
+
+    /// Runs `machine` in the window through `frontend::run` until the
+    /// window is closed, every instance has run EXIT or one faults.
+    pub fn run(&mut self, machine: &mut Machine) {
+        if let Err(e) = frontend::run(self, machine) {
+            eprintln!("{}", tr_args("err-guest", &[&e]));
+        }
+        self.quit();
+    }
+}
+
+impl Frontend for SDLGui<'_> {
+    /// Reads the window's events. SDL reports SIGINT and SIGTERM as a quit
+    /// event, so those also end the run here.
+    fn poll_input(&mut self, machine: &mut Machine) -> ControlFlow<()> {
+        match self.read_keys(machine) {
+            true => ControlFlow::Continue(()),
+            false => ControlFlow::Break(()),
+        }
+    }
+
+    /// Takes the console's commands, then plays back or records the
+    /// replay and applies fast-forward, jitter and the background speed.
+    fn plan_frame(&mut self, machine: &mut Machine) -> FramePlan {
+        self.run_console(machine);
+
+        if let Some(display) = &mut self.input_display {
+            let keypads = machine.cpus.iter().map(|cpu| cpu.get_keypad());
+
+            if let Err(e) = display.update(keypads) {
+                eprintln!("{}", tr_args("err-input-file", &[&e]));
+            }
+        }
+
+        // The replay and throttle only count frames that run
+        let rewinding = self.rewinding && machine.rewind.is_some();
+        let frozen = rewinding || self.paused || machine.debugger.is_paused();
+        if !frozen {
+            self.replay_frame(&mut machine.cpus);
+        }
+
+        let ipf = match &mut self.jitter {
+            Some(jitter) => jitter.instructions(machine.ipf),
+            None => machine.ipf,
+        };
+        let ipf = match frozen {
+            true => 0,
+            false => self.throttle.instructions(ipf),
+        };
+
+        FramePlan {
+            duration: self.throttle.frame_duration(FRAME),
+            ipf,
+            rewinding: self.rewinding,
+            paused: self.paused,
+        }
+    }
+
+    fn play_audio(&mut self, machine: &Machine, report: &FrameReport) {
+        let Some(device) = &mut self.audio else {
+            return;
+        };
+
+        let heard = machine.cpus.iter().find(|cpu| cpu.is_sound_on());
+        let (pattern, pitch) = heard.map_or(([0; 16], 0), |cpu| {
+            (*cpu.get_audio_pattern(), cpu.get_pitch())
+        });
+
+        let audible = !self.throttle.muted() && !self.paused;
+        let changes = match audible {
+            true => &report.sound_changes[..],
+            false => &[],
+        };
+        let playing = heard.is_some() && audible;
+        device.lock().update(changes, playing, pattern, pitch);
+    }
+
+    /// Also feeds calibration and the idle detectors, which watch every
+    /// frame, including the ones `--fps` leaves undrawn.
+    fn draw(&mut self, machine: &mut Machine, report: &FrameReport) {
+        if let Some(calibration) = &mut self.calibration {
+            calibration.record(report.work.iter().flatten().copied().max());
+        }
+
+        if let Some(idle) = &mut self.idle {
+            for (instance, (cpu, detector)) in machine.cpus.iter().zip(idle).enumerate() {
+                match detector.update(cpu.get_video()) {
+                    Some(true) => status::announce(Status::GameOver { instance }),
+                    Some(false) => status::announce(Status::Resumed { instance }),
+                    None => {}
+                }
+            }
+        }
+
+        self.frames += 1;
+        let fps = self.fps as u64;
+        if self.frames * fps / 60 == (self.frames - 1) * fps / 60 {
+            return;
+        }
+
+        self.canvas.clear();
+        for (n, (cpu, screen)) in machine.cpus.iter_mut().zip(&mut self.screens).enumerate() {
+            let dirty = cpu.take_video_dirty();
+            let video = cpu.get_video();
+            let (palette, scale) = (self.palette, self.scale);
+            let filters = &self.filters;
+            screen.draw(&mut self.canvas, filters, palette, scale, n, (video, dirty));
+        }
+
+        if self.perf_hud {
+            self.draw_perf_hud(&report.used, report.ipf);
+        }
+        if let Some(keys) = self.keypad_overlay.take() {
+            self.draw_keypad_overlay(&machine.cpus, &keys);
+            self.keypad_overlay = Some(keys);
+        }
+
+        self.take_screenshot(&machine.cpus);
+
+        self.canvas.present();
+
+        if let Some(stats) = &mut self.frame_stats {
+            stats.present(Instant::now());
+        }
+        self.canvas.set_draw_color(rgba(self.palette.colors[0]));
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// How many frames (3 seconds at 60 Hz) a screen has to stay unchanged
+/// before it is reported as a probable game over.
+const IDLE_FRAMES: u32 = 180;
+
+/*
+    status started instance=0 rom=roms/PONG
+    status scale value=12
+    status game-over instance=0
+    status resumed instance=0
+    status paused
+    status unpaused
+    status reset
+    status quit
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status<'a> {
+    Started { instance: usize, rom: &'a str },
+    Scale(u32),
+    GameOver { instance: usize },
+    Resumed { instance: usize },
+    Paused,
+    Unpaused,
+    Reset,
+    Quit,
+}
+
+impl fmt::Display for Status<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Started { instance, rom } => {
+                write!(f, "status started instance={} rom={:?}", instance, rom)
+            }
+            Status::Scale(value) => write!(f, "status scale value={}", value),
+            Status::GameOver { instance } => write!(f, "status game-over instance={}", instance),
+            Status::Resumed { instance } => write!(f, "status resumed instance={}", instance),
+            Status::Paused => write!(f, "status paused"),
+            Status::Unpaused => write!(f, "status unpaused"),
+            Status::Reset => write!(f, "status reset"),
+            Status::Quit => write!(f, "status quit"),
+        }
+    }
+}
+
+/// Prints one status line on stdout for screen readers and other tools.
+pub fn announce(status: Status) {
+    println!("{}", status);
+}
+
+/// Guesses that a game is over when its framebuffer stops changing.
+#[derive(Debug, Clone)]
+pub(crate) struct IdleDetector {
+    last: Vec<u8>,
+    unchanged: u32,
+    idle: bool,
+}
+
+impl IdleDetector {
+    pub fn new() -> IdleDetector {
+        IdleDetector {
+            last: vec![],
+            unchanged: 0,
+            idle: false,
+        }
+    }
+
+    /// Called once per frame. Returns `Some(true)` when the screen has just
+    /// gone idle and `Some(false)` when it starts changing again.
+    pub fn update(&mut self, video: &[u8]) -> Option<bool> {
+        if self.last != video {
+            self.last = video.to_vec();
+            self.unchanged = 0;
+
+            if self.idle {
+                self.idle = false;
+                return Some(false);
+            }
+        } else if !self.idle {
+            self.unchanged += 1;
+            if self.unchanged < IDLE_FRAMES {
+                return None;
+            }
+
+            self.idle = true;
+            return Some(true);
+        }
+
+        None
+    }
+}
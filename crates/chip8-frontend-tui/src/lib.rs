@@ -0,0 +1,4 @@
+//! Terminal frontends: the full-screen debugger and the plain display.
+
+pub mod terminal;
+pub mod tui;
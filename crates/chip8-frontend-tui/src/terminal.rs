@@ -0,0 +1,185 @@
+use crate::tui;
+use chip8_core::chip8::{Chip8, HIRES_WIDTH};
+use chip8_frontend::frontend::{self, FrameReport, Frontend, Machine, KEYMAPS};
+use chip8_frontend::i18n::{tr, tr_args};
+use ratatui::crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use ratatui::crossterm::{execute, terminal};
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::DefaultTerminal;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+/*
+    The keypad is the first instance's layout from the SDL frontend. Most
+    terminals only report key presses, so a key stays held for KEY_HOLD
+    frames after its last press or auto-repeat; terminals that report
+    releases let it go straight away. Esc or Ctrl+C quits, and the sound
+    timer rings the terminal bell.
+*/
+
+/// Frames a key stays held after a press, long enough to bridge the gap
+/// before a terminal starts auto-repeating.
+const KEY_HOLD: u32 = 30;
+
+/// A frontend that draws the display in the terminal, for when there is
+/// no window system, e.g. over SSH.
+struct TerminalGui {
+    terminal: DefaultTerminal,
+    /// Frames left before each key is released.
+    held: [u32; 16],
+    sound: bool,
+    mute: bool,
+    /// Set once a frame has needed the full 128x64, after which the
+    /// display keeps that size.
+    hires: bool,
+    quit: bool,
+    /// The first terminal error, which ends the run.
+    error: Option<io::Error>,
+}
+
+impl TerminalGui {
+    fn new(terminal: DefaultTerminal, mute: bool) -> TerminalGui {
+        TerminalGui {
+            terminal,
+            held: [0; 16],
+            sound: false,
+            mute,
+            hires: false,
+            quit: false,
+            error: None,
+        }
+    }
+
+    fn key(&mut self, cpu: &mut Chip8, key: KeyEvent) {
+        if key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        {
+            self.quit = true;
+            return;
+        }
+
+        let KeyCode::Char(c) = key.code else {
+            return;
+        };
+        let name = c.to_ascii_uppercase().to_string();
+        let Some(n) = KEYMAPS[0].iter().position(|&k| k == name) else {
+            return;
+        };
+
+        match key.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                self.held[n] = KEY_HOLD;
+                cpu.set_keypad(n, true);
+            }
+            KeyEventKind::Release => {
+                self.held[n] = 0;
+                cpu.set_keypad(n, false);
+            }
+        }
+    }
+
+    fn release_keys(&mut self, cpu: &mut Chip8) {
+        for (n, frames) in self.held.iter_mut().enumerate() {
+            if *frames > 0 {
+                *frames -= 1;
+                if *frames == 0 {
+                    cpu.set_keypad(n, false);
+                }
+            }
+        }
+    }
+
+    fn read_events(&mut self, cpu: &mut Chip8) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                self.key(cpu, key);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Frontend for TerminalGui {
+    fn draw(&mut self, machine: &mut Machine, _report: &FrameReport) {
+        let video = machine.cpus[0].get_video();
+        // Lores frames are every pixel doubled, so those lose nothing
+        // drawn at half size
+        let doubled = video.chunks_exact(HIRES_WIDTH * 2).all(|rows| {
+            let (top, bottom) = rows.split_at(HIRES_WIDTH);
+            top == bottom && top.chunks_exact(2).all(|pair| pair[0] == pair[1])
+        });
+        self.hires |= !doubled;
+
+        let lines: Vec<Line> = tui::screen(video, self.hires);
+        let width = lines.first().map_or(0, |line| line.width()) as u16;
+        let height = lines.len() as u16;
+
+        let res = self.terminal.draw(|frame| {
+            let area = Rect::new(0, 0, width + 2, height + 2).intersection(frame.area());
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::bordered().title_bottom(tr("terminal-hint"))),
+                area,
+            );
+        });
+        if let Err(e) = res {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn poll_input(&mut self, machine: &mut Machine) -> ControlFlow<()> {
+        let cpu = &mut machine.cpus[0];
+        self.release_keys(cpu);
+        if let Err(e) = self.read_events(cpu) {
+            self.error.get_or_insert(e);
+        }
+
+        match self.quit || self.error.is_some() {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    }
+
+    /// Rings the bell as each sound starts; terminals can't hold a tone,
+    /// let alone play a pattern.
+    fn play_audio(&mut self, machine: &Machine, _report: &FrameReport) {
+        let on = machine.cpus[0].is_sound_on();
+        if on && !self.sound && !self.mute {
+            print!("\x07");
+            let _ = io::stdout().flush();
+        }
+        self.sound = on;
+    }
+}
+
+/// Runs `cpu` in the terminal until the user quits, without the bell if
+/// `mute` is set. Errors are returned ready to print, once the terminal has
+/// been restored.
+pub fn run(cpu: Chip8, ipf: u32, mute: bool) -> Result<(), String> {
+    let terminal = ratatui::try_init().map_err(|e| tr_args("err-terminal", &[&e]))?;
+
+    // Ask for key releases where the terminal can report them
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if releases {
+        let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+        let _ = execute!(io::stdout(), PushKeyboardEnhancementFlags(flags));
+    }
+
+    let mut gui = TerminalGui::new(terminal, mute);
+    let res = frontend::run(&mut gui, &mut Machine::new(vec![cpu], ipf));
+
+    if releases {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    ratatui::restore();
+
+    if let Some(e) = gui.error {
+        return Err(tr_args("err-terminal", &[&e]));
+    }
+    res.map_err(|e| tr_args("err-guest", &[&e]))
+}
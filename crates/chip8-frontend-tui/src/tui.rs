@@ -0,0 +1,528 @@
+use chip8_core::chip8::{Chip8, HIRES_HEIGHT, HIRES_WIDTH};
+use chip8_core::isa::decode_opcode;
+use chip8_frontend::debugger::{parse_addr, Debugger, StepMode};
+use chip8_frontend::i18n::{tr, tr_args};
+use chip8_tools::testscript::{self, parse_target, Target};
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Margin, Position, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::{Duration, Instant};
+
+/*
+    step [n]        pause and run n instructions (default 1)
+    step frame      pause and run to the end of a frame
+    step draw       pause and run until the display changes
+    continue        resume running
+    pause           pause before the next instruction
+    break <a>       pause before the instruction at address a
+    clear           remove all breakpoints
+    mem <a> [n]     show n bytes (default 64) of memory from address a
+    set <t> <v>     set mem[a], v0-vf, i or pc to v
+    undo            undo the last set
+    key <k>         press or release keypad key k (0-F)
+    quit            leave the debugger
+
+    Stepping over more than one instruction stops at breakpoints. An
+    empty line repeats the last command. Esc and Ctrl+C also quit.
+    Clicking a byte in the memory panel or a register starts a set command
+    for it, so only the new value needs typing.
+*/
+
+const FRAME: Duration = Duration::from_micros(16_667);
+
+/// Instructions listed before the one at PC.
+const DISASM_BEFORE: u16 = 6;
+
+const MEM_LEN: usize = 64;
+const MEM_PER_LINE: usize = 8;
+
+/// Width of the registers panel, borders included.
+const REGISTERS_WIDTH: u16 = 28;
+
+/// Columns in the panels where clickable values start, counted inside the
+/// border: the bytes of a memory line, and the second register of a line.
+const MEM_BYTES_COLUMN: u16 = 8;
+const REGS_RIGHT_COLUMN: u16 = 8;
+const INDEX_COLUMN: u16 = 10;
+
+/// The line of the registers panel holding PC and I.
+const PC_LINE: u16 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Step(StepMode),
+    Continue,
+    Pause,
+    Break(u16),
+    Clear,
+    Mem(u16, usize),
+    Set(Target, u16),
+    Undo,
+    Key(usize),
+    Quit,
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+
+    let cmd = match words.next() {
+        Some("step" | "s") => match words.next() {
+            Some("frame" | "f") => Command::Step(StepMode::Frame),
+            Some("draw" | "d") => Command::Step(StepMode::UntilDisplayChanges),
+            Some(arg) => {
+                let n = arg.parse().map_err(|_| tr("err-usage-step"))?;
+                Command::Step(StepMode::Instructions(n))
+            }
+            None => Command::Step(StepMode::Instruction),
+        },
+        Some("continue" | "c") => Command::Continue,
+        Some("pause" | "p") => Command::Pause,
+        Some("break" | "b") => {
+            let arg = words.next().ok_or(tr("err-usage-break"))?;
+            let addr = parse_addr(arg).ok_or_else(|| tr_args("err-invalid-addr", &[&arg]))?;
+            Command::Break(addr)
+        }
+        Some("clear") => Command::Clear,
+        Some("mem" | "m") => {
+            let arg = words.next().ok_or(tr("err-usage-mem"))?;
+            let addr = parse_addr(arg).ok_or_else(|| tr_args("err-invalid-addr", &[&arg]))?;
+            let len = match words.next() {
+                Some(arg) => parse_addr(arg).ok_or(tr("err-usage-mem"))? as usize,
+                None => MEM_LEN,
+            };
+            Command::Mem(addr, len)
+        }
+        Some("set") => {
+            let (Some(target), Some(value)) = (words.next(), words.next()) else {
+                return Err(tr("err-usage-set").to_string());
+            };
+            let target = parse_target(target).ok_or(tr("err-usage-set"))?;
+            let value = parse_addr(value)
+                .filter(|&v| v <= 0xFF || matches!(target, Target::I | Target::Pc))
+                .ok_or(tr("err-usage-set"))?;
+            Command::Set(target, value)
+        }
+        Some("undo" | "u") => Command::Undo,
+        Some("key" | "k") => {
+            let arg = words.next().ok_or(tr("err-usage-key"))?;
+            let key = u8::from_str_radix(arg, 16)
+                .ok()
+                .filter(|&k| k < 16)
+                .ok_or(tr("err-usage-key"))?;
+            Command::Key(key as usize)
+        }
+        Some("quit" | "q") => Command::Quit,
+        Some(other) => return Err(tr_args("err-unknown-command", &[&other])),
+        None => return Err(tr("err-empty-command").to_string()),
+    };
+
+    Ok(cmd)
+}
+
+/// A framebuffer in half-block characters, two pixel rows per line. Lores
+/// pixels are stored doubled, so unless `hires` only every other one is
+/// drawn.
+pub fn screen(video: &[u8], hires: bool) -> Vec<Line<'static>> {
+    let size = if hires { 1 } else { 2 };
+    let lit = |x: usize, y: usize| video[y * HIRES_WIDTH + x] != 0;
+
+    (0..HIRES_HEIGHT)
+        .step_by(2 * size)
+        .map(|y| {
+            let row: String = (0..HIRES_WIDTH)
+                .step_by(size)
+                .map(|x| match (lit(x, y), lit(x, y + size)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            Line::from(row)
+        })
+        .collect()
+}
+
+fn registers(cpu: &Chip8) -> Vec<Line<'static>> {
+    let reg = cpu.get_registers();
+    let (dt, st) = cpu.get_timers();
+
+    let mut lines: Vec<Line> = (0..8)
+        .map(|n| {
+            Line::from(format!(
+                "V{:X} {:02X}   V{:X} {:02X}",
+                n,
+                reg[n],
+                n + 8,
+                reg[n + 8]
+            ))
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "PC {:#05X}  I {:#06X}",
+        cpu.get_pc(),
+        cpu.get_index()
+    )));
+    lines.push(Line::from(format!("DT {:02X}     ST {:02X}", dt, st)));
+
+    let keys: String = cpu
+        .get_keypad()
+        .iter()
+        .enumerate()
+        .filter(|(_, &held)| held)
+        .map(|(key, _)| format!("{:X}", key))
+        .collect();
+    lines.push(Line::from(format!("{} {}", tr("tui-keys"), keys)));
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(tr("tui-stack")));
+    for (depth, addr) in cpu.get_stack().iter().enumerate().rev() {
+        lines.push(Line::from(format!("  {:2} {:#05X}", depth, addr)));
+    }
+    lines
+}
+
+/// Where the clickable panels were last drawn.
+#[derive(Debug, Clone, Copy, Default)]
+struct Panes {
+    registers: Rect,
+    memory: Rect,
+}
+
+/// A CHIP-8 debugger running in the terminal on a single instance.
+struct Tui {
+    cpu: Chip8,
+    debugger: Debugger,
+    ipf: u32,
+    input: String,
+    last: String,
+    message: String,
+    mem: (u16, usize),
+    /// Values replaced by `set`, newest last.
+    undo: Vec<(Target, u16)>,
+    panes: Panes,
+    quit: bool,
+}
+
+impl Tui {
+    fn new(cpu: Chip8, ipf: u32) -> Tui {
+        let mut debugger = Debugger::default();
+        debugger.set_guest_breakpoints(true);
+        debugger.pause();
+
+        Tui {
+            cpu,
+            debugger,
+            ipf,
+            input: String::new(),
+            last: String::new(),
+            message: tr("tui-help").to_string(),
+            mem: (0x200, MEM_LEN),
+            undo: vec![],
+            panes: Panes::default(),
+            quit: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut next_frame = Instant::now();
+
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) => self.key(key),
+                    Event::Mouse(MouseEvent {
+                        kind: MouseEventKind::Down(MouseButton::Left),
+                        column,
+                        row,
+                        ..
+                    }) => self.click(column, row),
+                    _ => {}
+                }
+                continue;
+            }
+
+            next_frame = (next_frame + FRAME).max(Instant::now());
+            self.frame();
+        }
+
+        Ok(())
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => {
+                let line = std::mem::take(&mut self.input);
+                let line = if line.trim().is_empty() {
+                    self.last.clone()
+                } else {
+                    line
+                };
+                match parse(&line) {
+                    Ok(cmd) => self.execute(cmd),
+                    Err(e) => self.message = e,
+                }
+                self.last = line;
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts a `set` for the byte or register under the cursor.
+    fn click(&mut self, x: u16, y: u16) {
+        let inside = |area: Rect| {
+            let inner = area.inner(Margin::new(1, 1));
+            inner
+                .contains(Position::new(x, y))
+                .then(|| (x - inner.x, y - inner.y))
+        };
+
+        let target = if let Some((col, row)) = inside(self.panes.memory) {
+            let (start, len) = self.mem;
+            let Some(col) = col.checked_sub(MEM_BYTES_COLUMN) else {
+                return;
+            };
+            let col = (col / 3) as usize;
+            let offset = row as usize * MEM_PER_LINE + col;
+            if col >= MEM_PER_LINE || offset >= len {
+                return;
+            }
+            format!("mem[{:#05X}]", start as usize + offset)
+        } else if let Some((col, row)) = inside(self.panes.registers) {
+            match row {
+                0..=7 if col >= REGS_RIGHT_COLUMN => format!("v{:x}", row + 8),
+                0..=7 => format!("v{:x}", row),
+                PC_LINE if col >= INDEX_COLUMN => "i".to_string(),
+                PC_LINE => "pc".to_string(),
+                _ => return,
+            }
+        } else {
+            return;
+        };
+
+        self.input = format!("set {} 0x", target);
+    }
+
+    fn execute(&mut self, cmd: Command) {
+        match cmd {
+            Command::Step(mode) => {
+                self.debugger.pause();
+                let pc = self.cpu.get_pc();
+                if let Err(e) = self.debugger.advance(&mut self.cpu, mode, self.ipf) {
+                    self.message = tr_args("err-guest", &[&e.fault]);
+                    return;
+                }
+                let from = format!("{:#05X}", pc);
+                let to = format!("{:#05X}", self.cpu.get_pc());
+                self.message = tr_args("tui-stepped", &[&from, &to]);
+            }
+            Command::Continue => {
+                self.debugger.resume(&self.cpu);
+                self.message = tr("debugger-resumed").to_string();
+            }
+            Command::Pause => {
+                self.debugger.pause();
+                self.message = tr("debugger-paused").to_string();
+            }
+            Command::Break(addr) => {
+                self.debugger.add_breakpoint(addr);
+                self.message = tr_args("breakpoint-set", &[&format!("{:#05X}", addr)]);
+            }
+            Command::Clear => {
+                self.debugger.clear_breakpoints();
+                self.message = tr("breakpoints-cleared").to_string();
+            }
+            Command::Mem(addr, len) => self.mem = (addr, len),
+            Command::Set(target, value) => {
+                self.undo
+                    .push((target, testscript::read(&self.cpu, target)));
+                testscript::write(&mut self.cpu, target, value);
+                let value = testscript::read(&self.cpu, target);
+                self.message = format!("{} = {:#04X}", target, value);
+            }
+            Command::Undo => match self.undo.pop() {
+                Some((target, old)) => {
+                    testscript::write(&mut self.cpu, target, old);
+                    self.message = tr_args("tui-undone", &[&format!("{} = {:#04X}", target, old)]);
+                }
+                None => self.message = tr("tui-nothing-to-undo").to_string(),
+            },
+            Command::Key(key) => {
+                let held = self.cpu.get_keypad()[key];
+                self.cpu.set_keypad(key, !held);
+            }
+            Command::Quit => self.quit = true,
+        }
+    }
+
+    /// Runs one 60 Hz frame of the guest unless paused.
+    fn frame(&mut self) {
+        if self.debugger.is_paused() {
+            return;
+        }
+
+        for _ in 0..self.ipf {
+            if self.debugger.check(&self.cpu) {
+                let pc = format!("{:#05X}", self.cpu.get_pc());
+                self.message = tr_args("tui-paused-at", &[&pc]);
+                return;
+            }
+            if let Err(e) = self.cpu.step() {
+                self.debugger.pause();
+                self.message = tr_args("err-guest", &[&e.fault]);
+                return;
+            }
+            if let Some(pc) = self.debugger.check_guest_break(&self.cpu) {
+                let pc = format!("{:#05X}", pc);
+                self.message = tr_args("guest-break", &[&pc]);
+                return;
+            }
+            // Pause rather than quit, so the final state can be inspected
+            if self.cpu.exited() {
+                self.debugger.pause();
+                let pc = format!("{:#05X}", self.cpu.get_pc());
+                self.message = tr_args("tui-exited", &[&pc]);
+                return;
+            }
+            if self.cpu.waiting_for_vblank() {
+                break;
+            }
+        }
+        self.cpu.tick_timers();
+    }
+
+    /// Instructions from a little before PC on. Going backwards from PC is
+    /// guesswork, so the earlier lines assume word-aligned code.
+    fn disassembly(&self, rows: u16) -> Vec<Line<'static>> {
+        let pc = self.cpu.get_pc();
+        let byte = |addr: u16| self.cpu.read_memory(addr as usize).unwrap_or(0) as u16;
+        let start = pc.saturating_sub(DISASM_BEFORE * 2);
+
+        (0..rows)
+            .map(|n| {
+                let addr = start.wrapping_add(n * 2);
+                let op = (byte(addr) << 8) | byte(addr.wrapping_add(1));
+                let text = decode_opcode(op).map_or("??".to_string(), |opcode| opcode.mnemonic());
+                let marker = match (addr == pc, self.debugger.is_breakpoint(addr)) {
+                    (true, _) => '>',
+                    (false, true) => '*',
+                    (false, false) => ' ',
+                };
+                let line = Line::from(format!("{} {:#05X}  {:04X}  {}", marker, addr, op, text));
+                match addr == pc {
+                    true => line.style(Style::default().add_modifier(Modifier::REVERSED)),
+                    false => line,
+                }
+            })
+            .collect()
+    }
+
+    fn memory(&self) -> Vec<Line<'static>> {
+        let (start, len) = self.mem;
+        let start = start as usize;
+
+        (start..start + len)
+            .step_by(MEM_PER_LINE)
+            .map(|addr| {
+                let end = (addr + MEM_PER_LINE).min(start + len);
+                let bytes: Vec<String> = (addr..end)
+                    .map(|a| match self.cpu.read_memory(a) {
+                        Some(b) => format!("{:02X}", b),
+                        None => "--".to_string(),
+                    })
+                    .collect();
+                Line::from(format!("{:#06X}  {}", addr, bytes.join(" ")))
+            })
+            .collect()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let (width, height) = match self.cpu.is_hires() {
+            true => (HIRES_WIDTH, HIRES_HEIGHT / 2),
+            false => (HIRES_WIDTH / 2, HIRES_HEIGHT / 4),
+        };
+        let [top, middle, bottom] = Layout::vertical([
+            Constraint::Length(height as u16 + 2),
+            Constraint::Min(0),
+            Constraint::Length(4),
+        ])
+        .areas(frame.area());
+        let [display, regs] = Layout::horizontal([
+            Constraint::Length(width as u16 + 2),
+            Constraint::Length(REGISTERS_WIDTH),
+        ])
+        .areas(top);
+        let [code, mem] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Min(0)]).areas(middle);
+        self.panes = Panes {
+            registers: regs,
+            memory: mem,
+        };
+
+        let state = match self.debugger.is_paused() {
+            true => tr("debugger-paused"),
+            false => tr("tui-running"),
+        };
+        frame.render_widget(
+            Paragraph::new(screen(self.cpu.get_video(), self.cpu.is_hires()))
+                .block(Block::bordered().title(format!(" {} ({}) ", tr("tui-display"), state))),
+            display,
+        );
+        frame.render_widget(
+            Paragraph::new(registers(&self.cpu))
+                .block(Block::bordered().title(format!(" {} ", tr("tui-registers")))),
+            regs,
+        );
+        frame.render_widget(
+            Paragraph::new(self.disassembly(code.height.saturating_sub(2)))
+                .block(Block::bordered().title(format!(" {} ", tr("tui-disassembly")))),
+            code,
+        );
+        frame.render_widget(
+            Paragraph::new(self.memory())
+                .block(Block::bordered().title(format!(" {} ", tr("tui-memory")))),
+            mem,
+        );
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(self.message.clone()),
+                Line::from(format!("> {}", self.input)),
+            ])
+            .block(Block::bordered()),
+            bottom,
+        );
+    }
+}
+
+/// Runs `cpu` under the terminal debugger until the user quits. The guest
+/// starts paused at its first instruction.
+pub fn run(cpu: Chip8, ipf: u32) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    execute!(io::stdout(), EnableMouseCapture)?;
+    let res = Tui::new(cpu, ipf).run(&mut terminal);
+    let _ = execute!(io::stdout(), DisableMouseCapture);
+    ratatui::restore();
+    res
+}
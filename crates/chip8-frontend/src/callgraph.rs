@@ -0,0 +1,55 @@
+use chip8_core::chip8::{Chip8, GuestEvent, MEMORY_START};
+use std::collections::HashMap;
+
+/// Calls observed while a ROM runs, between the entry points of the
+/// calling and called subroutines. Code outside any subroutine counts as
+/// being in one starting at 0x200.
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    edges: HashMap<(u16, u16), u32>,
+    /// Entry points of the subroutines on the guest stack, outermost first.
+    frames: Vec<u16>,
+}
+
+impl Default for CallGraph {
+    fn default() -> CallGraph {
+        CallGraph {
+            edges: HashMap::new(),
+            frames: vec![MEMORY_START as u16],
+        }
+    }
+}
+
+impl CallGraph {
+    /// Call after each step. Returns aren't reported, but the depth of the
+    /// next call shows how many frames were left.
+    pub fn record(&mut self, cpu: &Chip8) {
+        for event in cpu.events() {
+            if let GuestEvent::StackPushed { depth, .. } = *event {
+                self.frames.truncate(depth);
+                let caller = self.frames.last().copied().unwrap_or(MEMORY_START as u16);
+                let callee = cpu.get_pc();
+
+                *self.edges.entry((caller, callee)).or_default() += 1;
+                self.frames.push(callee);
+            }
+        }
+    }
+
+    /// The graph in Graphviz DOT format, with call counts as edge labels.
+    pub fn to_dot(&self) -> String {
+        let mut edges: Vec<_> = self.edges.iter().collect();
+        edges.sort();
+
+        let mut out =
+            String::from("digraph calls {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for (&(caller, callee), count) in edges {
+            out.push_str(&format!(
+                "    \"{:#05X}\" -> \"{:#05X}\" [label=\"{}\"];\n",
+                caller, callee, count
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
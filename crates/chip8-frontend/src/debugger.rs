@@ -0,0 +1,270 @@
+use chip8_core::chip8::{Chip8, Error, GuestEvent};
+use chip8_core::isa::decode_opcode;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// Which accesses a watchpoint stops on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Any,
+}
+
+impl Access {
+    pub fn parse(s: &str) -> Option<Access> {
+        match s {
+            "r" => Some(Access::Read),
+            "w" => Some(Access::Write),
+            "rw" => Some(Access::Any),
+            _ => None,
+        }
+    }
+}
+
+/// How far `Debugger::advance` runs a paused guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    Instruction,
+    Instructions(u32),
+    /// One frame's instructions, then the timers tick.
+    Frame,
+    /// Frames until an instruction changes the display.
+    UntilDisplayChanges,
+}
+
+/// Frames `StepMode::UntilDisplayChanges` gives up after: ten seconds.
+const DISPLAY_WAIT_FRAMES: u32 = 600;
+
+/// Whether the last step ran an instruction that can change the display.
+fn touched_display(cpu: &Chip8) -> bool {
+    cpu.events().iter().any(|event| match *event {
+        GuestEvent::InstructionExecuted { op, .. } => {
+            decode_opcode(op).is_some_and(|opcode| opcode.changes_display())
+        }
+        _ => false,
+    })
+}
+
+/// A data access that hit a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub pc: u16,
+    pub addr: usize,
+    pub write: bool,
+}
+
+/// An opcode to break on, with `x` nibbles matching anything: `Dxxx`
+/// breaks on every draw, `00E0` only on CLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodePattern {
+    value: u16,
+    mask: u16,
+}
+
+impl OpcodePattern {
+    pub fn parse(s: &str) -> Option<OpcodePattern> {
+        if s.chars().count() != 4 {
+            return None;
+        }
+
+        let mut pattern = OpcodePattern { value: 0, mask: 0 };
+        for c in s.chars() {
+            pattern.value <<= 4;
+            pattern.mask <<= 4;
+            if c != 'x' && c != 'X' {
+                pattern.value |= c.to_digit(16)? as u16;
+                pattern.mask |= 0xF;
+            }
+        }
+        Some(pattern)
+    }
+
+    fn matches(&self, op: u16) -> bool {
+        op & self.mask == self.value
+    }
+}
+
+/// Breakpoints and stepping for one instance. The frontend asks `check`
+/// before each instruction and stops running the guest while paused.
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    breakpoints: HashSet<u16>,
+    opcodes: Vec<OpcodePattern>,
+    watchpoints: Vec<(RangeInclusive<usize>, Access)>,
+    paused: bool,
+    /// Pause once the stack is shallower than this, i.e. after a RET.
+    until_depth: Option<usize>,
+    /// Where the guest was resumed, so the breakpoint it stopped on doesn't
+    /// stop it again straight away.
+    resumed_at: Option<u16>,
+    /// Whether the guest's own BRK instructions pause it.
+    guest_breaks: bool,
+}
+
+impl Debugger {
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn add_opcode_breakpoint(&mut self, pattern: OpcodePattern) {
+        self.opcodes.push(pattern);
+    }
+
+    /// Pauses after any instruction that accesses `range` as `access`. The
+    /// instance must have memory tracing on for this to see anything.
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<usize>, access: Access) {
+        self.watchpoints.push((range, access));
+    }
+
+    /// Makes BRK in the guest pause it, for frontends where someone is
+    /// there to resume. Off by default, leaving BRK a NOP.
+    pub fn set_guest_breakpoints(&mut self, on: bool) {
+        self.guest_breaks = on;
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.opcodes.clear();
+        self.watchpoints.clear();
+    }
+
+    pub fn is_breakpoint(&self, addr: u16) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.until_depth = None;
+    }
+
+    pub fn resume(&mut self, cpu: &Chip8) {
+        self.paused = false;
+        self.resumed_at = Some(cpu.get_pc());
+    }
+
+    /// Resumes until the current subroutine returns.
+    pub fn run_until_return(&mut self, cpu: &Chip8) {
+        self.resume(cpu);
+        self.until_depth = Some(cpu.get_stack().len());
+    }
+
+    /// Runs the guest as far as `mode` says while paused, with `ipf`
+    /// instructions to a frame. Anything longer than one instruction also
+    /// stops in front of a breakpoint.
+    pub fn advance(&mut self, cpu: &mut Chip8, mode: StepMode, ipf: u32) -> Result<(), Error> {
+        self.resumed_at = None;
+        let at_breakpoint = |cpu: &Chip8| {
+            self.breakpoints.contains(&cpu.get_pc()) || self.guest_break(cpu).is_some()
+        };
+
+        match mode {
+            StepMode::Instruction => cpu.step(),
+            StepMode::Instructions(n) => cpu.run_batch(n, at_breakpoint).map(drop),
+            StepMode::Frame => {
+                if cpu.run_batch(ipf, at_breakpoint)?.is_none() {
+                    cpu.tick_timers();
+                }
+                Ok(())
+            }
+            StepMode::UntilDisplayChanges => {
+                let before = cpu.get_video().to_vec();
+                let changed =
+                    |cpu: &Chip8| touched_display(cpu) && cpu.get_video() != before.as_slice();
+
+                for _ in 0..DISPLAY_WAIT_FRAMES {
+                    let stop = |cpu: &Chip8| changed(cpu) || at_breakpoint(cpu);
+                    if cpu.run_batch(ipf, stop)?.is_some() {
+                        break;
+                    }
+                    cpu.tick_timers();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks the accesses of the last step against the watchpoints and
+    /// pauses on the first hit.
+    pub fn check_accesses(&mut self, cpu: &Chip8) -> Option<WatchHit> {
+        let hit = cpu.events().iter().find_map(|event| {
+            let hit = match *event {
+                GuestEvent::MemoryRead { pc, addr } => WatchHit {
+                    pc,
+                    addr,
+                    write: false,
+                },
+                GuestEvent::MemoryWritten { pc, addr, .. } => WatchHit {
+                    pc,
+                    addr,
+                    write: true,
+                },
+                _ => return None,
+            };
+            let watched = self.watchpoints.iter().any(|(range, access)| {
+                range.contains(&hit.addr)
+                    && match access {
+                        Access::Read => !hit.write,
+                        Access::Write => hit.write,
+                        Access::Any => true,
+                    }
+            });
+            watched.then_some(hit)
+        })?;
+
+        self.pause();
+        Some(hit)
+    }
+
+    /// Where the last step ran a BRK, if guest breakpoints are on.
+    fn guest_break(&self, cpu: &Chip8) -> Option<u16> {
+        if !self.guest_breaks {
+            return None;
+        }
+        cpu.events().iter().find_map(|event| match *event {
+            GuestEvent::BreakRequested { pc } => Some(pc),
+            _ => None,
+        })
+    }
+
+    /// Checks whether the last step ran a BRK and pauses if so, returning
+    /// its address.
+    pub fn check_guest_break(&mut self, cpu: &Chip8) -> Option<u16> {
+        let pc = self.guest_break(cpu)?;
+        self.pause();
+        Some(pc)
+    }
+
+    /// Whether to pause before the instruction at the current PC. Pauses
+    /// the debugger when it says so.
+    pub fn check(&mut self, cpu: &Chip8) -> bool {
+        let pc = cpu.get_pc();
+        if self.resumed_at.take() == Some(pc) {
+            return false;
+        }
+
+        let returned = self
+            .until_depth
+            .is_some_and(|depth| cpu.get_stack().len() < depth);
+        let byte = |addr: u16| cpu.read_memory(addr as usize).unwrap_or(0) as u16;
+        let op = (byte(pc) << 8) | byte(pc.wrapping_add(1));
+        let hit = self.breakpoints.contains(&pc) || self.opcodes.iter().any(|p| p.matches(op));
+
+        if returned || hit {
+            self.pause();
+        }
+        self.paused
+    }
+}
+
+/// Parses an address or value as decimal, or hex with a 0x prefix.
+pub fn parse_addr(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
@@ -0,0 +1,447 @@
+use crate::callgraph::CallGraph;
+use crate::debugger::Debugger;
+use crate::i18n::{tr, tr_args};
+use crate::rewind::Rewind;
+use chip8_core::chip8::{Chip8, Error, GuestEvent};
+use std::mem;
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One frame at 60 Hz.
+pub const FRAME: Duration = Duration::new(0, 1_000_000_000 / 60);
+
+/// How far behind its schedule the loop may fall, e.g. while a window is
+/// dragged, before it gives up catching up and starts afresh from now.
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+pub const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+/*
+    1	2	3	4
+    Q	W	E	R
+    A   S   D   F
+    Z   X   C   V
+
+    1	2	3	C
+    4	5	6	D
+    7	8	9	E
+    A	0	B	F
+
+    The second instance in split-screen mode uses
+
+    7	8	9	0
+    U	I	O	P
+    J   K   L   ;
+    M   ,   .   /
+*/
+
+/// Each instance's keyboard keys, indexed by the CHIP-8 key they press.
+pub const KEYMAPS: [[&str; 16]; 2] = [
+    [
+        "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+    ],
+    [
+        ",", "7", "8", "9", "U", "I", "O", "J", "K", "L", "M", ".", "0", "P", ";", "/",
+    ],
+];
+
+/// The instances a frontend runs, with what `run` keeps alongside them:
+/// the debugger and call graph, which watch the first instance, and each
+/// instance's rewind history.
+#[derive(Debug, Clone)]
+pub struct Machine {
+    pub cpus: Vec<Chip8>,
+    pub debugger: Debugger,
+    pub call_graph: CallGraph,
+    /// Per instance, with rewinding on.
+    pub rewind: Option<Vec<Rewind>>,
+    /// Instructions per frame, before any the frontend's `plan_frame`
+    /// adds or takes away.
+    pub ipf: u32,
+    /// Runs frames back to back instead of pacing them to the wall clock.
+    pub deterministic: bool,
+    /// Slices each frame's instructions are spread over, with input read
+    /// before each, so keys reach the guest sooner than the next frame.
+    pub input_polls: u32,
+    /// Instances already warned about going over their draw budget.
+    over_budget: Vec<bool>,
+}
+
+impl Machine {
+    pub fn new(cpus: Vec<Chip8>, ipf: u32) -> Machine {
+        let over_budget = vec![false; cpus.len()];
+
+        Machine {
+            cpus,
+            debugger: Debugger::default(),
+            call_graph: CallGraph::default(),
+            rewind: None,
+            ipf,
+            deterministic: false,
+            input_polls: 1,
+            over_budget,
+        }
+    }
+
+    /// Keeps `capacity` snapshots per instance, one every `interval` frames.
+    pub fn start_rewind(&mut self, capacity: usize, interval: u32) {
+        self.rewind = Some(vec![Rewind::new(capacity, interval); self.cpus.len()]);
+    }
+
+    /// Steps every instance back one snapshot, staying put at the oldest.
+    fn rewind_frame(&mut self) {
+        let Some(rewind) = &mut self.rewind else {
+            return;
+        };
+
+        for (cpu, rewind) in self.cpus.iter_mut().zip(rewind) {
+            if let Some(older) = rewind.step_back() {
+                cpu.restore(older);
+            }
+        }
+    }
+}
+
+/// How the frontend wants the coming frame run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramePlan {
+    /// How long the frame lasts on the wall clock, shorter to fast-forward.
+    pub duration: Duration,
+    /// Instructions to run.
+    pub ipf: u32,
+    /// Steps back through the rewind history instead of running.
+    pub rewinding: bool,
+    /// Holds every instance where it is, timers included.
+    pub paused: bool,
+}
+
+/// What a frame did, for the frontend to play and show.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameReport {
+    /// The instructions the frame had to run, 0 while rewinding, paused or
+    /// held by the debugger.
+    pub ipf: u32,
+    /// Per instance, the instructions that ran rather than waited on Fx0A.
+    pub used: Vec<u32>,
+    /// Per instance, the instructions run before the guest first waited
+    /// on a key or read the delay timer, i.e. the frame's real work.
+    pub work: Vec<Option<u32>>,
+    /// When any instance's sound went on or off, as fractions of the frame.
+    pub sound_changes: Vec<(f32, bool)>,
+}
+
+/// What the emulation loop needs from a user interface. `run` drives the
+/// instances through any implementation, so a new renderer only has to
+/// feed input, play the sound and show frames.
+pub trait Frontend {
+    /// Applies pending input to the instances' keypads, and handles the
+    /// frontend's own keys and commands, debugger ones included. Called at
+    /// the start of each frame and between its input slices. `Break` ends
+    /// the run.
+    fn poll_input(&mut self, machine: &mut Machine) -> ControlFlow<()>;
+
+    /// Decides how to run the coming frame, once its input is in. Runs it
+    /// at normal speed unless overridden.
+    fn plan_frame(&mut self, machine: &mut Machine) -> FramePlan {
+        FramePlan {
+            duration: FRAME,
+            ipf: machine.ipf,
+            rewinding: false,
+            paused: false,
+        }
+    }
+
+    /// Called once a frame to play the instances' sound, as from
+    /// `Chip8::is_sound_on`, `Chip8::get_audio_pattern` and
+    /// `Chip8::get_pitch`. Frontends that can only beep may ignore the
+    /// pattern, and those that can't time within a frame the report's
+    /// `sound_changes`.
+    fn play_audio(&mut self, machine: &Machine, report: &FrameReport);
+
+    /// Called once a frame to show the instances' displays, as from
+    /// `Chip8::get_video`.
+    fn draw(&mut self, machine: &mut Machine, report: &FrameReport);
+}
+
+/// Runs the instances at a fixed 60 Hz timestep until the frontend asks to
+/// stop, every instance has run EXIT or one faults. Each frame runs its
+/// instructions, ticks the timers once and is handed to the frontend.
+/// Frames are paced against a running deadline rather than their own
+/// length, so time spent drawing or on a slow frame is made up and the
+/// rate doesn't drift with the host.
+pub fn run(frontend: &mut impl Frontend, machine: &mut Machine) -> Result<(), Error> {
+    let mut deadline = Instant::now();
+
+    loop {
+        if frontend.poll_input(machine).is_break() {
+            return Ok(());
+        }
+
+        let plan = frontend.plan_frame(machine);
+        deadline += plan.duration;
+
+        // Rewinding replaces emulation for the frame
+        let rewinding = plan.rewinding && machine.rewind.is_some();
+        if rewinding {
+            machine.rewind_frame();
+        }
+        let frozen = rewinding || plan.paused || machine.debugger.is_paused();
+        let ipf = if frozen { 0 } else { plan.ipf };
+
+        let Some(report) = run_frame(frontend, machine, ipf, plan.duration)? else {
+            return Ok(());
+        };
+
+        if !frozen {
+            for cpu in machine.cpus.iter_mut() {
+                cpu.tick_timers();
+            }
+            if let Some(rewind) = &mut machine.rewind {
+                for (cpu, rewind) in machine.cpus.iter().zip(rewind) {
+                    rewind.record(cpu);
+                }
+            }
+        }
+
+        frontend.play_audio(machine, &report);
+        frontend.draw(machine, &report);
+
+        if !frozen && machine.cpus.iter().all(Chip8::exited) {
+            return Ok(());
+        }
+
+        if !machine.deterministic {
+            let now = Instant::now();
+            if now < deadline {
+                thread::sleep(deadline - now);
+            } else if now - deadline > MAX_LAG {
+                deadline = now;
+            }
+        }
+    }
+}
+
+/// Runs one frame of `ipf` instructions, in `machine.input_polls` slices
+/// spread over `duration`. `None` if the frontend asked to stop between
+/// slices.
+fn run_frame(
+    frontend: &mut impl Frontend,
+    machine: &mut Machine,
+    ipf: u32,
+    duration: Duration,
+) -> Result<Option<FrameReport>, Error> {
+    let instances = machine.cpus.len();
+    let mut report = FrameReport {
+        ipf,
+        used: vec![0; instances],
+        work: vec![None; instances],
+        sound_changes: vec![],
+    };
+    // Instances whose frame a draw has ended, with display wait
+    let mut vblank = vec![false; instances];
+    let mut sounding = machine.cpus.iter().any(Chip8::is_sound_on);
+
+    let polls = machine.input_polls.max(1);
+    let start = Instant::now();
+    'frame: for k in 0..ipf {
+        // Between slices, wait for the slice's share of the frame and read
+        // input again
+        let slice = k * polls / ipf;
+        if k > 0 && slice != (k - 1) * polls / ipf {
+            let due = duration * slice / polls;
+            let spent = start.elapsed();
+            if !machine.deterministic && spent < due {
+                thread::sleep(due - spent);
+            }
+            if frontend.poll_input(machine).is_break() {
+                return Ok(None);
+            }
+        }
+
+        let mut sound_set = false;
+        let counts = report.used.iter_mut().zip(report.work.iter_mut());
+        for (n, (cpu, ((used, work), vblank))) in machine
+            .cpus
+            .iter_mut()
+            .zip(counts.zip(vblank.iter_mut()))
+            .enumerate()
+        {
+            if *vblank {
+                continue;
+            }
+            if n == 0 && machine.debugger.check(cpu) {
+                println!("{}\n{}", tr("debugger-paused"), cpu);
+                break 'frame;
+            }
+            cpu.step()?;
+            // The debugger and call graph look at the first instance
+            if n == 0 {
+                machine.call_graph.record(cpu);
+
+                if let Some(hit) = machine.debugger.check_accesses(cpu) {
+                    let addr = format!("{:#05X}", hit.addr);
+                    let pc = format!("{:#05X}", hit.pc);
+                    let id = match hit.write {
+                        true => "watchpoint-write",
+                        false => "watchpoint-read",
+                    };
+                    println!("{}\n{}", tr_args(id, &[&addr, &pc]), cpu);
+                    break 'frame;
+                }
+                if let Some(pc) = machine.debugger.check_guest_break(cpu) {
+                    let pc = format!("{:#05X}", pc);
+                    println!("{}\n{}", tr_args("guest-break", &[&pc]), cpu);
+                    break 'frame;
+                }
+            }
+            let events = cpu.events();
+            let waited = events
+                .iter()
+                .any(|e| matches!(e, GuestEvent::KeyWaited { .. }));
+            let polled = events
+                .iter()
+                .any(|e| matches!(e, GuestEvent::TimerPolled { .. }));
+
+            if !waited {
+                *used += 1;
+            }
+            if work.is_none() && (waited || polled) {
+                *work = Some(*used);
+            }
+            *vblank = cpu.waiting_for_vblank();
+            // Warned about once, as it tends to happen every frame
+            for event in events {
+                if let GuestEvent::DrawBudgetExceeded { pc, rows } = *event {
+                    if !mem::replace(&mut machine.over_budget[n], true) {
+                        let pc = format!("{:#05X}", pc);
+                        eprintln!("{}", tr_args("warn-draw-budget", &[&n, &rows, &pc]));
+                    }
+                }
+            }
+            sound_set |= events.iter().any(|e| {
+                matches!(
+                    e,
+                    GuestEvent::SoundStarted { .. } | GuestEvent::SoundStopped { .. }
+                )
+            });
+        }
+
+        if sound_set && machine.cpus.iter().any(Chip8::is_sound_on) != sounding {
+            sounding = !sounding;
+            report.sound_changes.push((k as f32 / ipf as f32, sounding));
+        }
+    }
+
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts what `run` calls, and quits after `frames` frames.
+    #[derive(Default)]
+    struct Counting {
+        frames: u32,
+        polls: u32,
+        drawn: u32,
+        rewinding: bool,
+    }
+
+    impl Frontend for Counting {
+        fn poll_input(&mut self, _machine: &mut Machine) -> ControlFlow<()> {
+            self.polls += 1;
+            match self.drawn < self.frames {
+                true => ControlFlow::Continue(()),
+                false => ControlFlow::Break(()),
+            }
+        }
+
+        fn plan_frame(&mut self, machine: &mut Machine) -> FramePlan {
+            FramePlan {
+                duration: FRAME,
+                ipf: machine.ipf,
+                rewinding: self.rewinding,
+                paused: false,
+            }
+        }
+
+        fn play_audio(&mut self, _machine: &Machine, _report: &FrameReport) {}
+
+        fn draw(&mut self, _machine: &mut Machine, _report: &FrameReport) {
+            self.drawn += 1;
+        }
+    }
+
+    fn machine(rom: &[u8], ipf: u32) -> Machine {
+        let mut cpu = Chip8::new(0);
+        assert!(cpu.load_rom_bytes(rom).is_ok());
+        let mut machine = Machine::new(vec![cpu], ipf);
+        machine.deterministic = true;
+        machine
+    }
+
+    #[test]
+    fn runs_until_every_instance_exits() {
+        // ADD V0, 1 then EXIT
+        let mut machine = machine(&[0x70, 0x01, 0x00, 0xFD], 10);
+        let mut frontend = Counting {
+            frames: 100,
+            ..Counting::default()
+        };
+
+        assert!(run(&mut frontend, &mut machine).is_ok());
+        assert_eq!(frontend.drawn, 1);
+        assert_eq!(machine.cpus[0].get_registers()[0], 1);
+    }
+
+    #[test]
+    fn polls_input_between_slices() {
+        // JP 0x200
+        let mut machine = machine(&[0x12, 0x00], 12);
+        machine.input_polls = 3;
+        let mut frontend = Counting {
+            frames: 2,
+            ..Counting::default()
+        };
+
+        assert!(run(&mut frontend, &mut machine).is_ok());
+        // Three polls a frame, and the one that ends the run
+        assert_eq!(frontend.polls, 2 * 3 + 1);
+    }
+
+    #[test]
+    fn stops_at_breakpoints() {
+        // ADD V0, 1 then JP 0x200
+        let mut machine = machine(&[0x70, 0x01, 0x12, 0x00], 10);
+        machine.debugger.add_breakpoint(0x202);
+        let mut frontend = Counting {
+            frames: 3,
+            ..Counting::default()
+        };
+
+        assert!(run(&mut frontend, &mut machine).is_ok());
+        assert!(machine.debugger.is_paused());
+        assert_eq!(machine.cpus[0].get_pc(), 0x202);
+        assert_eq!(machine.cpus[0].get_registers()[0], 1);
+    }
+
+    #[test]
+    fn rewinds_instead_of_running() {
+        // ADD V0, 1 then JP 0x200: V0 counts the loops
+        let mut machine = machine(&[0x70, 0x01, 0x12, 0x00], 2);
+        machine.start_rewind(10, 1);
+        let mut frontend = Counting {
+            frames: 5,
+            ..Counting::default()
+        };
+        assert!(run(&mut frontend, &mut machine).is_ok());
+        assert_eq!(machine.cpus[0].get_registers()[0], 5);
+
+        frontend.frames = 7;
+        frontend.rewinding = true;
+        assert!(run(&mut frontend, &mut machine).is_ok());
+        assert_eq!(machine.cpus[0].get_registers()[0], 3);
+    }
+}
@@ -0,0 +1,107 @@
+//! Passing finished frames from an emulation thread to a render thread.
+//!
+//! [`FrameHandoff`] is a double buffer made of two owned `Vec`s. The
+//! emulation side owns the back buffer and draws into it freely; the
+//! shared front buffer sits behind a mutex. Publishing swaps the two
+//! under the lock, so no pixels are ever copied or shared by pointer.
+//!
+//! # Synchronization
+//!
+//! The front buffer is only touched while its lock is held, and the back
+//! buffer is only reachable through the publisher's `&mut`. So the render
+//! thread sees either the whole previous frame or the whole new one:
+//!
+//! - [`FrameHandoff::read`] holds the lock for its entire callback, so a
+//!   publish that starts meanwhile waits rather than swapping the frame
+//!   out from under it.
+//! - [`FrameHandoff::publish`] only does a `mem::swap` and a counter
+//!   increment under the lock, so it can't panic halfway and leave a
+//!   mixed frame. A panic inside a `read` callback poisons the lock but
+//!   can't modify the frame, so the lock is recovered instead of
+//!   propagating the poison.
+
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+struct Front {
+    pixels: Vec<u8>,
+    frame: u64,
+}
+
+/// The shared half of a double-buffered framebuffer. Wrap it in an `Arc`
+/// to share it between threads.
+pub struct FrameHandoff {
+    front: Mutex<Front>,
+}
+
+impl FrameHandoff {
+    /// Starts with a blank front buffer of `len` bytes, as frame 0.
+    pub fn new(len: usize) -> FrameHandoff {
+        FrameHandoff {
+            front: Mutex::new(Front {
+                pixels: vec![0; len],
+                frame: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Front> {
+        self.front.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Makes `back` the current frame. `back` gets the previous front
+    /// buffer in exchange, ready to be drawn over.
+    pub fn publish(&self, back: &mut Vec<u8>) {
+        let mut front = self.lock();
+        mem::swap(&mut front.pixels, back);
+        front.frame += 1;
+    }
+
+    /// Calls `f` with the current frame and how many have been published,
+    /// so a renderer can skip redrawing when the count hasn't changed.
+    pub fn read<T>(&self, f: impl FnOnce(&[u8], u64) -> T) -> T {
+        let front = self.lock();
+        f(&front.pixels, front.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn reader_only_sees_whole_frames_in_order() {
+        const FRAMES: u64 = 2000;
+        let handoff = Arc::new(FrameHandoff::new(4096));
+
+        // Frame n is filled with n as a byte, so a mix of two frames shows
+        // up as differing bytes
+        let publisher = {
+            let handoff = Arc::clone(&handoff);
+            thread::spawn(move || {
+                let mut back = vec![0; 4096];
+                for frame in 1..=FRAMES {
+                    back.fill(frame as u8);
+                    handoff.publish(&mut back);
+                }
+            })
+        };
+
+        let mut last = 0;
+        while last < FRAMES {
+            let frame = handoff.read(|pixels, frame| {
+                assert!(
+                    pixels.iter().all(|&p| p == frame as u8),
+                    "frame {} is mixed",
+                    frame
+                );
+                frame
+            });
+            assert!(frame >= last, "frame {} came after {}", frame, last);
+            last = frame;
+        }
+        publisher.join().unwrap();
+    }
+}
@@ -0,0 +1,1069 @@
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl Lang {
+    /// Parses a language tag such as `de`, `de-AT` or a POSIX locale such as
+    /// `de_DE.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Lang> {
+        let code = tag.split(['_', '-', '.', '@']).next()?;
+
+        match code.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+
+    /// Picks the language from the usual locale environment variables,
+    /// falling back to English.
+    pub fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::from_tag(&value))
+            .unwrap_or_default()
+    }
+}
+
+/// (id, English, German); `{}` marks where `tr_args` inserts arguments.
+const MESSAGES: &[(&str, &str, &str)] = &[
+    (
+        "about",
+        "Chip-8 Emulator in Rust",
+        "Chip-8-Emulator in Rust",
+    ),
+    (
+        "cmd-run",
+        "Run one ROM, or two side-by-side in split-screen",
+        "Ein ROM ausführen, oder zwei nebeneinander im geteilten Bildschirm",
+    ),
+    (
+        "cmd-gen-rom",
+        "Write a diagnostic ROM for checking input, timers or display",
+        "Ein Diagnose-ROM zum Prüfen von Eingabe, Timern oder Anzeige schreiben",
+    ),
+    (
+        "arg-kind",
+        "Which diagnostic ROM to generate",
+        "Welches Diagnose-ROM erzeugt werden soll",
+    ),
+    ("arg-output", "Output file", "Ausgabedatei"),
+    (
+        "cmd-completions",
+        "Print shell completions to stdout",
+        "Shell-Vervollständigungen auf stdout ausgeben",
+    ),
+    (
+        "arg-shell",
+        "Shell to generate completions for",
+        "Shell, für die Vervollständigungen erzeugt werden",
+    ),
+    (
+        "cmd-manpage",
+        "Print a man page to stdout",
+        "Eine Manpage auf stdout ausgeben",
+    ),
+    (
+        "cmd-test",
+        "Run a ROM headless against a script of inputs and assertions",
+        "Ein ROM ohne Fenster gegen ein Skript aus Eingaben und Prüfungen ausführen",
+    ),
+    ("arg-script", "Script file", "Skriptdatei"),
+    (
+        "cmd-record-gif",
+        "Render a ROM to an animated GIF without opening a window",
+        "Ein ROM ohne Fenster als animiertes GIF aufnehmen",
+    ),
+    (
+        "arg-gif-script",
+        "Test script whose key presses and releases drive the input",
+        "Testskript, dessen Tastendrücke die Eingabe steuern",
+    ),
+    (
+        "arg-gif-frames",
+        "Frames of guest time to record, at 60 per second",
+        "Aufzunehmende Frames Programmzeit, 60 pro Sekunde",
+    ),
+    (
+        "arg-gif-scale",
+        "Pixels per framebuffer pixel",
+        "Bildpunkte pro Framebuffer-Pixel",
+    ),
+    ("arg-palette", "Colour theme", "Farbschema"),
+    (
+        "arg-bg",
+        "Background colour as hex RRGGBB, replacing the theme's",
+        "Hintergrundfarbe als Hex-RRGGBB, anstelle der des Farbschemas",
+    ),
+    (
+        "arg-fg",
+        "Foreground colour as hex RRGGBB, replacing the theme's",
+        "Vordergrundfarbe als Hex-RRGGBB, anstelle der des Farbschemas",
+    ),
+    (
+        "arg-fg2",
+        "Colour of XO-CHIP's second plane",
+        "Farbe der zweiten XO-CHIP-Ebene",
+    ),
+    (
+        "arg-blend",
+        "Colour where both XO-CHIP planes are lit",
+        "Farbe, wo beide XO-CHIP-Ebenen leuchten",
+    ),
+    (
+        "cmd-batch-capture",
+        "Capture a screenshot and info JSON of every ROM in a directory, for galleries",
+        "Von jedem ROM eines Verzeichnisses ein Bildschirmfoto und Info-JSON aufnehmen, für Galerien",
+    ),
+    ("arg-capture-dir", "Directory of ROMs", "Verzeichnis mit ROMs"),
+    (
+        "arg-capture-out",
+        "Directory to write the screenshots and JSON to",
+        "Verzeichnis für die Bildschirmfotos und das JSON",
+    ),
+    (
+        "arg-capture-frames",
+        "Frames of guest time to run each ROM for before capturing it",
+        "Frames Programmzeit, die jedes ROM vor der Aufnahme läuft",
+    ),
+    (
+        "arg-jobs",
+        "ROMs to run at once; defaults to one per CPU",
+        "Gleichzeitig laufende ROMs; standardmäßig eines pro CPU",
+    ),
+    ("arg-fps", "GIF frames per second", "GIF-Bilder pro Sekunde"),
+    (
+        "arg-display-fps",
+        "Frames to draw per second; emulation stays at 60 Hz",
+        "Pro Sekunde gezeichnete Bilder; die Emulation bleibt bei 60 Hz",
+    ),
+    (
+        "cmd-info",
+        "Describe which keys, sound and features a ROM uses",
+        "Beschreiben, welche Tasten, Töne und Funktionen ein ROM nutzt",
+    ),
+    ("arg-rom-file", "ROM file to load", "Zu ladende ROM-Datei"),
+    (
+        "arg-rom-files",
+        "ROM files to load",
+        "Zu ladende ROM-Dateien",
+    ),
+    ("arg-scale", "Graphics scale", "Grafikskalierung"),
+    (
+        "arg-console",
+        "Read debugger commands (RAM search, breakpoints, call graph) from stdin",
+        "Debugger-Befehle (RAM-Suche, Haltepunkte, Aufrufgraph) von stdin lesen",
+    ),
+    (
+        "arg-announce",
+        "Announce state changes on stdout for screen readers",
+        "Zustandsänderungen für Screenreader auf stdout melden",
+    ),
+    (
+        "arg-config",
+        "Config file with [keymap], [controller] and [fast_forward] settings, instead of ~/.config/chip8-rust/config.toml",
+        "Konfigurationsdatei mit [keymap]-, [controller]- und [fast_forward]-Einstellungen, statt ~/.config/chip8-rust/config.toml",
+    ),
+    (
+        "arg-perf-hud",
+        "Show how much of each frame's instruction budget the guest uses",
+        "Anzeigen, wie viel des Befehlsbudgets pro Frame das Programm nutzt",
+    ),
+    (
+        "arg-filter",
+        "Post-processing for the window, instead of filters under [video] in the config file",
+        "Nachbearbeitung für das Fenster, statt filters unter [video] in der Konfigurationsdatei",
+    ),
+    (
+        "arg-phosphor-frames",
+        "Frames the phosphor filter takes to fade a pixel out",
+        "Frames, in denen der Phosphor-Filter ein Pixel ausblendet",
+    ),
+    (
+        "arg-debug-tui",
+        "Debug the first ROM in a terminal UI instead of opening a window",
+        "Die erste ROM in einer Terminal-Oberfläche debuggen statt ein Fenster zu öffnen",
+    ),
+    (
+        "arg-frontend",
+        "Where to show the display; the terminal runs the first ROM only",
+        "Wo die Anzeige erscheint; im Terminal läuft nur die erste ROM",
+    ),
+    ("arg-mute", "Don't play any sound", "Keinen Ton ausgeben"),
+    (
+        "arg-fast-forward-max",
+        "Top fast-forward speed, as a multiple of normal, reached while Tab is held (default 16, or max under [fast_forward] in the config file)",
+        "Höchste Vorspulgeschwindigkeit als Vielfaches der normalen, erreicht bei gehaltener Tab-Taste (Standard 16, oder max unter [fast_forward] in der Konfigurationsdatei)",
+    ),
+    (
+        "arg-fast-forward-ramp",
+        "Seconds fast-forward takes to ramp up from 2x to its top speed (default 3, or ramp under [fast_forward] in the config file)",
+        "Sekunden, bis das Vorspulen von 2x auf Höchstgeschwindigkeit beschleunigt (Standard 3, oder ramp unter [fast_forward] in der Konfigurationsdatei)",
+    ),
+    (
+        "arg-fast-forward-mute",
+        "Silence sound while fast-forwarding, as mute under [fast_forward] in the config file does",
+        "Ton beim Vorspulen stummschalten, wie mute unter [fast_forward] in der Konfigurationsdatei",
+    ),
+    ("arg-volume", "Beep volume, in percent", "Lautstärke des Pieptons in Prozent"),
+    (
+        "arg-tone",
+        "Frequency of the beep for ROMs that don't set an XO-CHIP audio pattern",
+        "Frequenz des Pieptons für ROMs ohne eigenes XO-CHIP-Klangmuster",
+    ),
+    (
+        "arg-timed-sound",
+        "Start and stop the beep at the instruction that sets the sound timer, not the next frame",
+        "Piepton beim Befehl starten und stoppen, der den Sound-Timer setzt, nicht erst im nächsten Frame",
+    ),
+    (
+        "arg-record-replay",
+        "Record the first instance's input to FILE; F8 re-records from the F5 state",
+        "Eingaben der ersten Instanz in FILE aufzeichnen; F8 nimmt ab dem F5-Zustand neu auf",
+    ),
+    (
+        "arg-replay",
+        "Play back input recorded with --record-replay, with its seed",
+        "Mit --record-replay aufgezeichnete Eingaben samt Seed abspielen",
+    ),
+    (
+        "arg-headless",
+        "Run without a window or input for --frames frames, then exit",
+        "Ohne Fenster und Eingabe --frames Frames lang laufen, dann beenden",
+    ),
+    ("arg-frames", "Frames to run in headless mode", "Im Headless-Modus auszuführende Frames"),
+    (
+        "arg-dump-dir",
+        "Write each headless frame's framebuffer here as a PGM image",
+        "Den Framebuffer jedes Headless-Frames hier als PGM-Bild speichern",
+    ),
+    (
+        "arg-input-file",
+        "Write the pressed keypad keys to this file, e.g. for an OBS text source",
+        "Gedrückte Tasten in diese Datei schreiben, z. B. für eine OBS-Textquelle",
+    ),
+    (
+        "arg-auto-keys",
+        "Bind arrows, WASD and Space to the keys the ROM reads (single ROM only)",
+        "Pfeiltasten, WASD und Leertaste auf die vom ROM gelesenen Tasten legen (nur ein ROM)",
+    ),
+    (
+        "arg-keypad-overlay",
+        "Show a keypad over the display, dimming keys the ROM doesn't read",
+        "Ein Tastenfeld über der Anzeige zeigen, vom ROM nicht gelesene Tasten abgeblendet",
+    ),
+    (
+        "arg-hires-snap",
+        "Snap window sizes to multiples of 128x64 instead of 64x32",
+        "Fenstergröße auf Vielfache von 128x64 statt 64x32 einrasten",
+    ),
+    (
+        "arg-ipf",
+        "Instructions to run per 60 Hz frame (default 10, or what --calibrate stored for the ROM)",
+        "Auszuführende Befehle pro 60-Hz-Frame (Standard 10, oder was --calibrate für das ROM gespeichert hat)",
+    ),
+    (
+        "arg-speed",
+        "Instructions to run per second, rounded to a whole number per frame",
+        "Auszuführende Befehle pro Sekunde, auf ganze Befehle pro Frame gerundet",
+    ),
+    (
+        "arg-frame-stats",
+        "Record frame times and print a histogram on F9 and on exit",
+        "Frame-Zeiten aufzeichnen und ein Histogramm bei F9 und beim Beenden ausgeben",
+    ),
+    (
+        "arg-calibrate",
+        "Measure how much work the ROM does per frame, and on exit suggest an --ipf and store it in the config file for the ROM",
+        "Messen, wie viel Arbeit das ROM pro Frame erledigt, und beim Beenden ein --ipf vorschlagen und für das ROM in der Konfigurationsdatei speichern",
+    ),
+    (
+        "arg-ipf-jitter",
+        "Vary instructions per frame by up to this many in either direction",
+        "Befehle pro Frame um bis zu so viele in beide Richtungen variieren",
+    ),
+    (
+        "arg-jitter-seed",
+        "Seed for the instructions-per-frame jitter",
+        "Startwert für die Variation der Befehle pro Frame",
+    ),
+    (
+        "arg-background-speed",
+        "Emulation speed in percent while the window is unfocused",
+        "Emulationsgeschwindigkeit in Prozent, solange das Fenster keinen Fokus hat",
+    ),
+    (
+        "arg-stick-deadzone",
+        "How far, in percent, a controller stick must be pushed to press a direction",
+        "Wie weit ein Controller-Stick in Prozent ausgelenkt werden muss, um eine Richtung zu drücken",
+    ),
+    (
+        "arg-stick-hysteresis",
+        "How far, in percent, below the deadzone a stick must return to release",
+        "Wie weit ein Stick in Prozent unter die Totzone zurückkehren muss, um loszulassen",
+    ),
+    (
+        "arg-rewind-seconds",
+        "Seconds of history to keep for rewinding with Backspace (0 disables)",
+        "Sekunden an Verlauf zum Zurückspulen mit Rücktaste (0 schaltet es ab)",
+    ),
+    (
+        "arg-confirm-overwrite",
+        "Make F5 ask for a second press before overwriting states saved this many minutes ago",
+        "F5 vor dem Überschreiben von vor so vielen Minuten gespeicherten Zuständen ein zweites Mal verlangen",
+    ),
+    (
+        "arg-input-polls",
+        "Times per frame to read input, spreading the frame's instructions between reads",
+        "Wie oft pro Frame Eingaben gelesen werden; die Befehle des Frames werden dazwischen verteilt",
+    ),
+    (
+        "arg-rewind-interval",
+        "Frames between rewind snapshots",
+        "Frames zwischen Schnappschüssen zum Zurückspulen",
+    ),
+    (
+        "arg-quirks",
+        "Quirks of the platform the ROM was written for",
+        "Eigenheiten der Plattform, für die das ROM geschrieben wurde",
+    ),
+    (
+        "arg-clip-x",
+        "Clip sprites at the left/right edges instead of wrapping them",
+        "Sprites am linken/rechten Rand abschneiden statt umbrechen",
+    ),
+    (
+        "arg-clip-y",
+        "Clip sprites at the top/bottom edges instead of wrapping them",
+        "Sprites am oberen/unteren Rand abschneiden statt umbrechen",
+    ),
+    (
+        "arg-shift-vy",
+        "Make 8xy6/8xyE shift Vy into Vx",
+        "8xy6/8xyE schieben Vy nach Vx",
+    ),
+    (
+        "arg-load-store-increment",
+        "Make Fx55/Fx65 advance I past the registers",
+        "Fx55/Fx65 erhöhen I um die Anzahl der Register",
+    ),
+    (
+        "arg-jump-vx",
+        "Make Bxnn jump to xnn + Vx",
+        "Bxnn springt nach xnn + Vx",
+    ),
+    (
+        "arg-vf-reset",
+        "Make 8xy1/8xy2/8xy3 clear VF",
+        "8xy1/8xy2/8xy3 setzen VF auf 0",
+    ),
+    (
+        "arg-display-wait",
+        "Make Dxyn wait for the vertical blank, ending the frame",
+        "Dxyn wartet auf die vertikale Austastlücke und beendet den Frame",
+    ),
+    (
+        "arg-stack-limit",
+        "Maximum subroutine nesting depth",
+        "Maximale Verschachtelungstiefe von Unterprogrammen",
+    ),
+    (
+        "arg-strict",
+        "Warn about what the ROM does that real hardware couldn't, such as drawing more in a frame than a COSMAC VIP",
+        "Warnen, wenn das ROM etwas tut, was echte Hardware nicht könnte, etwa mehr pro Frame zeichnen als ein COSMAC VIP",
+    ),
+    (
+        "arg-draw-budget",
+        "Sprite rows a frame may draw before --strict warns",
+        "Sprite-Zeilen pro Frame, ab denen --strict warnt",
+    ),
+    (
+        "arg-deterministic",
+        "Use a seeded RNG, a fixed RTC and unpaced frames for reproducible runs",
+        "Geseedeten Zufall, feste Uhr und ungebremste Frames für reproduzierbare Läufe verwenden",
+    ),
+    (
+        "arg-trace",
+        "Write an instruction trace to this file",
+        "Eine Befehlsspur in diese Datei schreiben",
+    ),
+    (
+        "err-write-trace",
+        "Cannot write trace {}: {}",
+        "Spur {} kann nicht geschrieben werden: {}",
+    ),
+    (
+        "arg-max-cycles",
+        "Stop after this many instructions in total (exit status 3)",
+        "Nach so vielen Befehlen insgesamt abbrechen (Exit-Status 3)",
+    ),
+    (
+        "arg-max-rom-size",
+        "Refuse ROMs larger than this many bytes (exit status 3)",
+        "ROMs über so vielen Bytes ablehnen (Exit-Status 3)",
+    ),
+    (
+        "sandbox-cycles",
+        "Run stopped at its limit of {} instructions",
+        "Lauf an seiner Grenze von {} Befehlen abgebrochen",
+    ),
+    (
+        "sandbox-rom-size",
+        "ROM is {} bytes, over the limit of {}",
+        "ROM hat {} Bytes, mehr als die Grenze von {}",
+    ),
+    (
+        "arg-seed",
+        "Seed for the RND instruction in deterministic mode",
+        "Startwert für den RND-Befehl im deterministischen Modus",
+    ),
+    (
+        "arg-ext-rtc",
+        "Enable the non-standard FxE0 real-time clock opcode, which reads the time in UTC",
+        "Den nicht standardisierten FxE0-Echtzeituhr-Opcode aktivieren, der die Uhrzeit in UTC liest",
+    ),
+    (
+        "arg-ext-banking",
+        "Enable the non-standard FxF8 bank switching opcode for ROMs over 3.5KB",
+        "Den nicht standardisierten FxF8-Bankumschaltungs-Opcode für ROMs über 3,5 KB aktivieren",
+    ),
+    (
+        "arg-ext-light-pen",
+        "Enable the non-standard FxF9 light pen (mouse) opcode",
+        "Den nicht standardisierten FxF9-Lichtgriffel-Opcode (Maus) aktivieren",
+    ),
+    (
+        "arg-ext-frame-counter",
+        "Expose a read-only 16-bit frame counter at 0x1FE for benchmark ROMs",
+        "Einen schreibgeschützten 16-Bit-Framezähler bei 0x1FE für Benchmark-ROMs bereitstellen",
+    ),
+    (
+        "arg-lang",
+        "Language for messages (en, de), defaults to the system locale",
+        "Sprache der Meldungen (en, de), standardmäßig die Systemsprache",
+    ),
+    (
+        "err-read-rom",
+        "Cannot read ROM file {}: {}",
+        "ROM-Datei {} kann nicht gelesen werden: {}",
+    ),
+    (
+        "err-rom-too-large",
+        "ROM file {} is {} bytes, but at most {} fit in memory",
+        "ROM-Datei {} ist {} Bytes groß, aber höchstens {} passen in den Speicher",
+    ),
+    (
+        "err-screenshot",
+        "Cannot save screenshot: {}",
+        "Bildschirmfoto kann nicht gespeichert werden: {}",
+    ),
+    (
+        "screenshot-saved",
+        "saved screenshot {}",
+        "Bildschirmfoto {} gespeichert",
+    ),
+    ("state-saved", "saved state", "Zustand gespeichert"),
+    (
+        "state-confirm-overwrite",
+        "the saved state is {} min old; press F5 again to overwrite it",
+        "der gespeicherte Zustand ist {} min alt; zum Überschreiben erneut F5 drücken",
+    ),
+    (
+        "state-save-undone",
+        "undid the last save; the state it replaced is back",
+        "letztes Speichern rückgängig gemacht; der ersetzte Zustand ist zurück",
+    ),
+    (
+        "state-nothing-to-undo",
+        "no earlier saved state to go back to",
+        "kein früher gespeicherter Zustand vorhanden",
+    ),
+    (
+        "emulation-paused",
+        "paused (Ctrl+P continues)",
+        "angehalten (Strg+P setzt fort)",
+    ),
+    ("emulation-resumed", "continued", "fortgesetzt"),
+    ("reset-done", "reset", "zurückgesetzt"),
+    (
+        "reset-replay",
+        "can't reset while a replay is recording or playing",
+        "Zurücksetzen ist während einer Replay-Aufnahme oder -Wiedergabe nicht möglich",
+    ),
+    ("state-loaded", "loaded state", "Zustand geladen"),
+    (
+        "replay-branched",
+        "re-recording from frame {} (re-record {})",
+        "Neuaufnahme ab Frame {} (Neuaufnahme {})",
+    ),
+    (
+        "replay-finished",
+        "replay finished; the keyboard has control",
+        "Replay beendet; die Tastatur übernimmt",
+    ),
+    (
+        "replay-saved",
+        "saved {} frames with {} re-records to {}",
+        "{} Frames mit {} Neuaufnahmen nach {} gespeichert",
+    ),
+    (
+        "state-none",
+        "no saved state yet (press F5)",
+        "noch kein Zustand gespeichert (F5 drücken)",
+    ),
+    (
+        "err-load-state",
+        "Cannot load state: {}",
+        "Zustand kann nicht geladen werden: {}",
+    ),
+    (
+        "err-input-file",
+        "Cannot write input display file: {}",
+        "Eingabeanzeige-Datei kann nicht geschrieben werden: {}",
+    ),
+    (
+        "err-stack-limit",
+        "must be a number from 1 to {}",
+        "muss eine Zahl von 1 bis {} sein",
+    ),
+    (
+        "err-write-rom",
+        "Cannot write ROM file {}: {}",
+        "ROM-Datei {} kann nicht geschrieben werden: {}",
+    ),
+    (
+        "err-read-replay",
+        "Cannot read replay file {}: {}",
+        "Replay-Datei {} kann nicht gelesen werden: {}",
+    ),
+    (
+        "err-write-replay",
+        "Cannot write replay file {}: {}",
+        "Replay-Datei {} kann nicht geschrieben werden: {}",
+    ),
+    (
+        "err-read-config",
+        "Cannot read config file {}: {}",
+        "Konfigurationsdatei {} kann nicht gelesen werden: {}",
+    ),
+    ("err-config", "Invalid config file {}: {}", "Ungültige Konfigurationsdatei {}: {}"),
+    (
+        "err-write-config",
+        "Cannot write config file {}: {}",
+        "Konfigurationsdatei {} kann nicht geschrieben werden: {}",
+    ),
+    (
+        "err-config-list",
+        "{} must be a list",
+        "{} muss eine Liste sein",
+    ),
+    (
+        "err-config-filter",
+        "unknown filter {} in video.filters (scanlines, grid, phosphor, curvature)",
+        "unbekannter Filter {} in video.filters (scanlines, grid, phosphor, curvature)",
+    ),
+    (
+        "err-config-table",
+        "{} must be a table",
+        "{} muss eine Tabelle sein",
+    ),
+    (
+        "err-config-number",
+        "{} must be a whole number from {} to {}",
+        "{} muss eine ganze Zahl von {} bis {} sein",
+    ),
+    (
+        "err-config-bool",
+        "{} must be true or false",
+        "{} muss true oder false sein",
+    ),
+    (
+        "err-config-unknown",
+        "unknown setting {}",
+        "unbekannte Einstellung {}",
+    ),
+    (
+        "err-keymap-chip8-key",
+        "unknown CHIP-8 key {} in keymap (0-F)",
+        "unbekannte CHIP-8-Taste {} in keymap (0-F)",
+    ),
+    (
+        "err-keymap-twice",
+        "CHIP-8 key {} is mapped twice",
+        "CHIP-8-Taste {} ist doppelt belegt",
+    ),
+    (
+        "err-keymap-host-key",
+        "unknown key {} for CHIP-8 key {}",
+        "unbekannte Taste {} für CHIP-8-Taste {}",
+    ),
+    (
+        "err-keymap-duplicate",
+        "{} is bound to both CHIP-8 keys {} and {}",
+        "{} ist an die CHIP-8-Tasten {} und {} gebunden",
+    ),
+    (
+        "err-controller-button",
+        "unknown controller button {}",
+        "unbekannte Controller-Taste {}",
+    ),
+    (
+        "err-controller-key",
+        "controller button {} needs a CHIP-8 key 0-F, not {}",
+        "Controller-Taste {} braucht eine CHIP-8-Taste 0-F, nicht {}",
+    ),
+    (
+        "err-replay-line",
+        "Invalid replay line {}: {}",
+        "Ungültige Replay-Zeile {}: {}",
+    ),
+    (
+        "err-read-script",
+        "Cannot read script file {}: {}",
+        "Skriptdatei {} kann nicht gelesen werden: {}",
+    ),
+    (
+        "err-script-line",
+        "invalid script line {}: {}",
+        "ungültige Skriptzeile {}: {}",
+    ),
+    ("test-ok", "ok     frame {}: {}", "ok     Frame {}: {}"),
+    (
+        "test-failed",
+        "FAILED frame {}: {} (got {})",
+        "FEHLER Frame {}: {} (erhalten {})",
+    ),
+    (
+        "test-summary-failed",
+        "{} assertion(s) failed",
+        "{} Prüfung(en) fehlgeschlagen",
+    ),
+    ("err-guest", "Guest error: {}", "Fehler im Programm: {}"),
+    (
+        "warn-draw-budget",
+        "Warning: instance {} drew {} sprite rows in one frame, at {}; real hardware draws less",
+        "Warnung: Instanz {} hat {} Sprite-Zeilen in einem Frame gezeichnet, bei {}; echte Hardware zeichnet weniger",
+    ),
+    (
+        "err-dump-frame",
+        "Cannot write frame to {}: {}",
+        "Frame kann nicht nach {} geschrieben werden: {}",
+    ),
+    (
+        "err-write-gif",
+        "Cannot write GIF {}: {}",
+        "GIF {} kann nicht geschrieben werden: {}",
+    ),
+    ("gif-saved", "wrote {} frames to {}", "{} Bilder nach {} geschrieben"),
+    (
+        "err-read-dir",
+        "Cannot read directory {}: {}",
+        "Verzeichnis {} kann nicht gelesen werden: {}",
+    ),
+    (
+        "err-write-capture",
+        "Cannot write capture {}: {}",
+        "Aufnahme {} kann nicht geschrieben werden: {}",
+    ),
+    (
+        "capture-summary",
+        "captured {} of {} ROMs into {}",
+        "{} von {} ROMs nach {} aufgenommen",
+    ),
+    (
+        "err-audio",
+        "No sound, could not open audio: {}",
+        "Kein Ton, Audio konnte nicht geöffnet werden: {}",
+    ),
+    (
+        "auto-key",
+        "auto keys: {} -> {}",
+        "automatische Tasten: {} -> {}",
+    ),
+    ("info-yes", "yes", "ja"),
+    ("info-no", "no", "nein"),
+    ("info-size", "size:", "Größe:"),
+    ("info-bytes", "{} bytes", "{} Bytes"),
+    ("info-keys", "keys read:", "gelesene Tasten:"),
+    ("info-waits", "waits for key:", "wartet auf Taste:"),
+    ("info-sound", "sound:", "Ton:"),
+    ("info-random", "random:", "Zufall:"),
+    ("info-bcd", "BCD (scores):", "BCD (Punkte):"),
+    ("info-draw", "sprite heights:", "Sprite-Höhen:"),
+    ("info-shift-vy", "8xy6 uses Vy:", "8xy6 nutzt Vy:"),
+    ("info-increment", "Fx55 moves I:", "Fx55 ändert I:"),
+    ("info-unclear", "unclear", "unklar"),
+    (
+        "cmd-check",
+        "Check a ROM for invalid or suspicious code and exit non-zero on errors",
+        "Ein ROM auf ungültigen oder verdächtigen Code prüfen und bei Fehlern mit Fehlerstatus beenden",
+    ),
+    ("arg-check-rom-file", "ROM file to check", "Zu prüfende ROM-Datei"),
+    (
+        "cmd-disasm",
+        "Print a listing of a ROM, with unreachable bytes shown as data",
+        "Ein Listing eines ROMs ausgeben, unerreichbare Bytes als Daten",
+    ),
+    (
+        "arg-disasm-rom-file",
+        "ROM file to disassemble",
+        "Zu disassemblierende ROM-Datei",
+    ),
+    (
+        "arg-extensions",
+        "Accept the non-standard FxE0, FxF8, FxF9 and FFFF (BRK) opcodes and banked ROMs",
+        "Die nicht standardisierten Opcodes FxE0, FxF8, FxF9 und FFFF (BRK) sowie ROMs mit Bänken erlauben",
+    ),
+    ("check-error", "error", "Fehler"),
+    ("check-warning", "warning", "Warnung"),
+    ("check-empty", "ROM is empty", "ROM ist leer"),
+    (
+        "check-too-large",
+        "ROM is {} bytes, only {} fit in memory",
+        "ROM hat {} Bytes, in den Speicher passen nur {}",
+    ),
+    (
+        "check-invalid-opcode",
+        "invalid instruction {}",
+        "ungültiger Befehl {}",
+    ),
+    (
+        "check-extension-opcode",
+        "non-standard instruction {} (allow with --extensions)",
+        "nicht standardisierter Befehl {} (mit --extensions erlauben)",
+    ),
+    (
+        "check-leaves-rom",
+        "execution continues at {}, outside the ROM",
+        "Ausführung geht bei {} außerhalb des ROMs weiter",
+    ),
+    (
+        "check-index-oob",
+        "accesses memory past 0xFFFF with I = {}",
+        "greift mit I = {} über 0xFFFF hinaus auf Speicher zu",
+    ),
+    (
+        "check-writes-interpreter",
+        "writes to the interpreter area with I = {}",
+        "schreibt mit I = {} in den Interpreter-Bereich",
+    ),
+    (
+        "check-summary",
+        "{} error(s), {} warning(s)",
+        "{} Fehler, {} Warnung(en)",
+    ),
+    (
+        "calibrate-suggest",
+        "calibration: suggested --ipf {} (current {})",
+        "Kalibrierung: empfohlen --ipf {} (aktuell {})",
+    ),
+    (
+        "filters-unsupported",
+        "This renderer can't draw into textures, so the video filters are off",
+        "Dieser Renderer kann nicht in Texturen zeichnen, daher sind die Videofilter aus",
+    ),
+    (
+        "calibrate-saved",
+        "calibration: stored for this ROM in {}",
+        "Kalibrierung: für dieses ROM in {} gespeichert",
+    ),
+    (
+        "calibrate-applied",
+        "running at --ipf {}, as calibrated for this ROM",
+        "läuft mit --ipf {}, wie für dieses ROM kalibriert",
+    ),
+    (
+        "calibrate-none",
+        "calibration: the ROM rarely waits, so it has no pace to match",
+        "Kalibrierung: das ROM wartet selten und hat kein eigenes Tempo",
+    ),
+    (
+        "frame-stats-empty",
+        "frame times: no frames presented yet",
+        "Frame-Zeiten: noch keine Frames angezeigt",
+    ),
+    (
+        "frame-stats-summary",
+        "frame times over {} frames: p50 {}ms, p95 {}ms, p99 {}ms, max {}ms",
+        "Frame-Zeiten über {} Frames: p50 {}ms, p95 {}ms, p99 {}ms, max {}ms",
+    ),
+    (
+        "err-color",
+        "not a hex colour: {} (expected RRGGBB)",
+        "keine Hex-Farbe: {} (erwartet RRGGBB)",
+    ),
+    (
+        "err-unknown-lang",
+        "unknown language: {}",
+        "unbekannte Sprache: {}",
+    ),
+    ("err-usage-eq", "usage: eq <n>", "Verwendung: eq <n>"),
+    ("err-usage-calls", "usage: calls <file>", "Verwendung: calls <Datei>"),
+    ("err-usage-break", "usage: break <addr>", "Verwendung: break <Adresse>"),
+    (
+        "err-usage-step",
+        "usage: step [n|frame|draw]",
+        "Verwendung: step [n|frame|draw]",
+    ),
+    (
+        "err-usage-mem",
+        "usage: mem <addr> [len]",
+        "Verwendung: mem <Adresse> [Länge]",
+    ),
+    ("err-usage-key", "usage: key <0-F>", "Verwendung: key <0-F>"),
+    (
+        "err-usage-set",
+        "usage: set <mem[addr]|v0-vf|i|pc> <value>",
+        "Verwendung: set <mem[Adresse]|v0-vf|i|pc> <Wert>",
+    ),
+    (
+        "err-usage-watch",
+        "usage: watch <addr>[-<addr>] [r|w|rw]",
+        "Verwendung: watch <Adresse>[-<Adresse>] [r|w|rw]",
+    ),
+    (
+        "err-usage-breakop",
+        "usage: breakop <pattern>, e.g. Dxxx",
+        "Verwendung: breakop <Muster>, z. B. Dxxx",
+    ),
+    (
+        "err-invalid-addr",
+        "invalid address: {}",
+        "ungültige Adresse: {}",
+    ),
+    (
+        "err-invalid-pattern",
+        "invalid opcode pattern: {}",
+        "ungültiges Opcode-Muster: {}",
+    ),
+    (
+        "err-invalid-byte",
+        "invalid byte: {}",
+        "ungültiges Byte: {}",
+    ),
+    (
+        "err-unknown-command",
+        "unknown command: {}",
+        "unbekannter Befehl: {}",
+    ),
+    ("err-empty-command", "empty command", "leerer Befehl"),
+    (
+        "ramsearch-started",
+        "ram search: {} candidates",
+        "RAM-Suche: {} Kandidaten",
+    ),
+    (
+        "ramsearch-filtered",
+        "ram search: {} candidates {}",
+        "RAM-Suche: {} Kandidaten {}",
+    ),
+    (
+        "ramsearch-none",
+        "ram search: no search in progress",
+        "RAM-Suche: keine Suche aktiv",
+    ),
+    ("ramsearch-more", "  ... {} more", "  ... {} weitere"),
+    ("breakpoint-set", "breakpoint at {}", "Haltepunkt bei {}"),
+    ("watchpoint-set", "watchpoint on {}", "Überwachungspunkt auf {}"),
+    (
+        "watchpoint-read",
+        "paused: read of {} at {}",
+        "angehalten: Lesen von {} bei {}",
+    ),
+    (
+        "watchpoint-write",
+        "paused: write to {} at {}",
+        "angehalten: Schreiben nach {} bei {}",
+    ),
+    (
+        "breakpoint-opcode-set",
+        "opcode breakpoint set",
+        "Opcode-Haltepunkt gesetzt",
+    ),
+    (
+        "breakpoints-cleared",
+        "breakpoints and watchpoints cleared",
+        "Halte- und Überwachungspunkte entfernt",
+    ),
+    ("debugger-paused", "paused", "angehalten"),
+    (
+        "guest-break",
+        "paused: BRK at {}",
+        "angehalten: BRK bei {}",
+    ),
+    (
+        "terminal-hint",
+        " keys 1-4 Q-R A-F Z-V, Esc quits ",
+        " Tasten 1-4 Q-R A-F Z-V, Esc beendet ",
+    ),
+    (
+        "err-terminal",
+        "Cannot use the terminal: {}",
+        "Terminal kann nicht verwendet werden: {}",
+    ),
+    (
+        "tui-exited",
+        "Program exited (EXIT at {})",
+        "Programm beendet (EXIT bei {})",
+    ),
+    ("tui-running", "running", "läuft"),
+    ("tui-paused-at", "paused at {}", "angehalten bei {}"),
+    ("tui-stepped", "stepped from {} to {}", "von {} bis {} ausgeführt"),
+    (
+        "tui-help",
+        "commands: step [n|frame|draw], continue, pause, break <addr>, clear, mem <addr> [len], set <target> <value>, undo, key <k>, quit",
+        "Befehle: step [n|frame|draw], continue, pause, break <Adresse>, clear, mem <Adresse> [Länge], set <Ziel> <Wert>, undo, key <k>, quit",
+    ),
+    ("tui-undone", "undone: {}", "rückgängig: {}"),
+    ("tui-nothing-to-undo", "nothing to undo", "nichts rückgängig zu machen"),
+    ("tui-display", "display", "Anzeige"),
+    ("tui-registers", "registers", "Register"),
+    ("tui-disassembly", "disassembly", "Disassemblierung"),
+    ("tui-memory", "memory", "Speicher"),
+    ("tui-keys", "keys:", "Tasten:"),
+    ("tui-stack", "stack:", "Stapel:"),
+    (
+        "err-tui",
+        "Cannot run the terminal UI: {}",
+        "Terminal-Oberfläche kann nicht gestartet werden: {}",
+    ),
+    ("debugger-resumed", "resumed", "fortgesetzt"),
+    (
+        "callgraph-saved",
+        "wrote call graph to {}",
+        "Aufrufgraph nach {} geschrieben",
+    ),
+    (
+        "err-callgraph",
+        "Cannot write call graph: {}",
+        "Aufrufgraph kann nicht geschrieben werden: {}",
+    ),
+    ("filter-increased", "increased", "gestiegen"),
+    ("filter-decreased", "decreased", "gesunken"),
+    ("filter-unchanged", "unchanged", "unverändert"),
+    ("filter-changed", "changed", "verändert"),
+    ("filter-equal-to", "equal to {}", "gleich {}"),
+];
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Sets the process-wide language. Only the first call has any effect.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
+}
+
+/// Looks up the message `id` in the current language. Unknown ids are
+/// returned unchanged.
+pub fn tr(id: &'static str) -> &'static str {
+    let Some(&(_, en, de)) = MESSAGES.iter().find(|(key, _, _)| *key == id) else {
+        return id;
+    };
+
+    match lang() {
+        Lang::En => en,
+        Lang::De => de,
+    }
+}
+
+/// Like `tr`, then substitutes each `{}` in the message with the next
+/// argument.
+pub fn tr_args(id: &'static str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = tr(id).split("{}");
+    let mut args = args.iter();
+    let mut out = parts.next().unwrap_or_default().to_string();
+
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// The `.rs` files under `dir`, recursively.
+    fn sources(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                sources(&path, out);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                out.push(path);
+            }
+        }
+    }
+
+    /// The literal ids passed to `tr` and `tr_args` in `src`.
+    fn ids_used(src: &str) -> Vec<&str> {
+        let mut ids = vec![];
+        for call in ["tr(\"", "tr_args(\""] {
+            for (at, _) in src.match_indices(call) {
+                let word_start = !src[..at].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+                let rest = &src[at + call.len()..];
+                if let (true, Some(end)) = (word_start, rest.find('"')) {
+                    ids.push(&rest[..end]);
+                }
+            }
+        }
+        ids
+    }
+
+    // An unknown id shows up as itself, so a typo would only be noticed by
+    // reading the output
+    #[test]
+    fn every_id_used_in_the_workspace_exists() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let mut files = vec![];
+        sources(&root.join("src"), &mut files);
+        sources(&root.join("crates"), &mut files);
+        assert!(!files.is_empty());
+
+        let mut missing = vec![];
+        for file in &files {
+            let src = fs::read_to_string(file).unwrap();
+            for id in ids_used(&src) {
+                if !MESSAGES.iter().any(|(key, _, _)| *key == id) {
+                    missing.push(format!("{}: {}", file.display(), id));
+                }
+            }
+        }
+        assert!(
+            missing.is_empty(),
+            "unknown message ids:\n{}",
+            missing.join("\n")
+        );
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for (n, (id, _, _)) in MESSAGES.iter().enumerate() {
+            assert!(
+                MESSAGES[..n].iter().all(|(key, _, _)| key != id),
+                "{} is defined twice",
+                id
+            );
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! What the frontends and tools share on top of the emulator: the frontend
+//! loop and keypad layouts, the debugger, rewind, replays, call graphs,
+//! handing frames to a render thread and the user-facing messages.
+
+pub mod callgraph;
+pub mod debugger;
+pub mod frontend;
+pub mod handoff;
+pub mod i18n;
+pub mod replay;
+pub mod rewind;
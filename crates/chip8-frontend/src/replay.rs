@@ -0,0 +1,110 @@
+use crate::i18n::tr_args;
+use std::fmt;
+
+/*
+    chip8-replay 2
+    seed 42
+    rerecords 3
+    0000
+    0010
+    ...
+
+    After the header comes one line per frame: the keypad held during that
+    frame as four hex digits, bit n set for key n. Replays only play back
+    faithfully on a deterministic run with the same seed. Version 2 seeds
+    each instance's own generator rather than one shared by all, so
+    version 1 replays would draw different CXNN numbers and aren't read.
+*/
+
+const MAGIC: &str = "chip8-replay 2";
+
+/// One instance's keypad, recorded a frame at a time. Loading a save state
+/// while recording branches the replay: everything after the state's frame
+/// is dropped and recording carries on from there, TAS-style, counting a
+/// re-record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub rerecords: u32,
+    frames: Vec<u16>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay {
+            seed,
+            ..Replay::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Appends a frame with the keys held in `keypad`.
+    pub fn record(&mut self, keypad: &[bool]) {
+        let mask = keypad
+            .iter()
+            .enumerate()
+            .filter(|&(_, &held)| held)
+            .fold(0, |mask, (key, _)| mask | 1 << key);
+        self.frames.push(mask);
+    }
+
+    /// Drops every frame from `frame` on, for re-recording from a state
+    /// saved at that point.
+    pub fn branch(&mut self, frame: usize) {
+        self.frames.truncate(frame);
+        self.rerecords += 1;
+    }
+
+    /// The keys held during `frame`, or `None` past the end.
+    pub fn keys(&self, frame: usize) -> Option<[bool; 16]> {
+        let mask = *self.frames.get(frame)?;
+        Some(std::array::from_fn(|key| mask & 1 << key != 0))
+    }
+
+    pub fn parse(src: &str) -> Result<Replay, String> {
+        let lines: Vec<&str> = src.lines().map(str::trim).collect();
+        let invalid = |n: usize| {
+            let line = lines.get(n).copied().unwrap_or_default();
+            tr_args("err-replay-line", &[&(n + 1), &line])
+        };
+        let field = |n: usize, name: &str| lines.get(n)?.strip_prefix(name)?.parse().ok();
+
+        if lines.first() != Some(&MAGIC) {
+            return Err(invalid(0));
+        }
+        let seed = field(1, "seed ").ok_or_else(|| invalid(1))?;
+        let rerecords = field(2, "rerecords ")
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| invalid(2))?;
+
+        let frames = (3..lines.len())
+            .filter(|&n| !lines[n].is_empty())
+            .map(|n| u16::from_str_radix(lines[n], 16).map_err(|_| invalid(n)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Replay {
+            seed,
+            rerecords,
+            frames,
+        })
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", MAGIC)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "rerecords {}", self.rerecords)?;
+        for mask in &self.frames {
+            writeln!(f, "{:04X}", mask)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use chip8_core::chip8::Chip8;
+use std::collections::VecDeque;
+
+/// Recent states of one instance for rewinding. A snapshot is taken every
+/// `interval` frames and up to `capacity` steps back are kept. Snapshots
+/// are clones of the instance, which share every memory page that hasn't
+/// been written since, so each costs little more than the display.
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    interval: u32,
+    capacity: usize,
+    countdown: u32,
+    snapshots: VecDeque<Chip8>,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u32) -> Rewind {
+        Rewind {
+            interval: interval.max(1),
+            capacity,
+            countdown: 0,
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Call once per emulated frame.
+    pub fn record(&mut self, cpu: &Chip8) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+        self.countdown = self.interval - 1;
+
+        self.snapshots.push_back(cpu.clone());
+        while self.snapshots.len() > self.capacity + 1 {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Steps back one snapshot and returns it, for `Chip8::restore`, or
+    /// `None` once the oldest kept snapshot is reached.
+    pub fn step_back(&mut self) -> Option<&Chip8> {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        self.snapshots.pop_back();
+        self.countdown = self.interval - 1;
+        self.snapshots.back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_back_through_kept_snapshots() {
+        let mut cpu = Chip8::new(0);
+        // ADD V0, 1 then JP 0x200: V0 counts the loops
+        assert!(cpu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).is_ok());
+
+        let mut rewind = Rewind::new(2, 1);
+        for _ in 0..4 {
+            assert!(cpu.step().is_ok() && cpu.step().is_ok());
+            rewind.record(&cpu);
+        }
+
+        for v0 in [3, 2] {
+            let older = rewind.step_back().map(|older| older.get_registers()[0]);
+            assert_eq!(older, Some(v0));
+        }
+        assert!(rewind.step_back().is_none());
+    }
+}
@@ -0,0 +1,348 @@
+use chip8_core::chip8::{Chip8, Quirks, MEMORY_SIZE, MEMORY_START};
+use chip8_core::isa::{decode_opcode, Opcode};
+use std::collections::HashSet;
+
+/// How far back from a key test to look for the `6xkk` that loaded its key.
+const LOOKBACK: usize = 8;
+
+/// Keypad usage found by scanning a ROM's instructions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyUsage {
+    /// Keys tested by Ex9E/ExA1 whose register was loaded with a constant.
+    pub keys: [bool; 16],
+    /// Whether the ROM waits for any key with Fx0A.
+    pub waits_for_key: bool,
+}
+
+fn opcodes(rom: &[u8]) -> Vec<u16> {
+    rom.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+/// Finds which keys a ROM reads. This is a heuristic: code is assumed to be
+/// word-aligned, and a key test only counts when a `6xkk` for the same
+/// register shortly precedes it.
+pub fn keys_read(rom: &[u8]) -> KeyUsage {
+    let ops = opcodes(rom);
+    let mut usage = KeyUsage::default();
+
+    for (n, &op) in ops.iter().enumerate() {
+        let x = (op & 0x0F00) >> 8;
+
+        match op & 0xF0FF {
+            0xE09E | 0xE0A1 => {
+                let load = ops[n.saturating_sub(LOOKBACK)..n]
+                    .iter()
+                    .rev()
+                    .find(|&&prev| (prev & 0xFF00) == (0x6000 | (x << 8)));
+
+                if let Some(&load) = load {
+                    usage.keys[(load & 0xF) as usize] = true;
+                }
+            }
+            0xF00A => usage.waits_for_key = true,
+            _ => {}
+        }
+    }
+
+    usage
+}
+
+/// A rough "manual" for a ROM, from scanning its instructions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomInfo {
+    pub size: usize,
+    pub keys: KeyUsage,
+    /// Sets the sound timer (Fx18).
+    pub sound: bool,
+    /// Uses RND (Cxkk).
+    pub random: bool,
+    /// Uses BCD conversion (Fx33), usually for a score display.
+    pub bcd: bool,
+    /// Sprite heights drawn with Dxyn, indexed by n.
+    pub draw_heights: [bool; 16],
+}
+
+pub fn analyze(rom: &[u8]) -> RomInfo {
+    let mut info = RomInfo {
+        size: rom.len(),
+        keys: keys_read(rom),
+        ..RomInfo::default()
+    };
+
+    for op in opcodes(rom) {
+        match op & 0xF000 {
+            0xC000 => info.random = true,
+            0xD000 => info.draw_heights[(op & 0xF) as usize] = true,
+            _ => {}
+        }
+
+        match op & 0xF0FF {
+            0xF018 => info.sound = true,
+            0xF033 => info.bcd = true,
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Frames, at `GUESS_IPF` instructions each, to run a ROM for when guessing
+/// its quirks: five seconds of guest time.
+const GUESS_FRAMES: u32 = 300;
+const GUESS_IPF: u32 = 15;
+
+/// Likely settings of the quirks that ROMs disagree on, or `None` where
+/// running the ROM gave no evidence either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuirkGuess {
+    pub shift_vy: Option<bool>,
+    pub load_store_increment: Option<bool>,
+}
+
+/// Fixed so that every configuration sees the same "random" numbers.
+const GUESS_SEED: u64 = 0x5A;
+
+/// Runs `rom` briefly with `quirks` and scores how badly it went, lower
+/// being better: crashing is worst, then I pointing past the original 4K of
+/// memory, then a display left blank or almost all lit.
+fn trial(rom: &[u8], quirks: Quirks) -> u32 {
+    let mut cpu = Chip8::new(GUESS_SEED);
+    cpu.set_quirks(quirks);
+    if cpu.load_rom_bytes(rom).is_err() {
+        return 0;
+    }
+
+    let mut score = 0;
+    'frames: for _ in 0..GUESS_FRAMES {
+        for _ in 0..GUESS_IPF {
+            if cpu.step().is_err() {
+                score += 4;
+                break 'frames;
+            }
+            if cpu.get_index() > 0xFFF {
+                score += 2;
+                break 'frames;
+            }
+        }
+        cpu.tick_timers();
+    }
+
+    let video = cpu.get_video();
+    let lit = video.iter().filter(|&&p| p != 0).count();
+    if lit == 0 || lit * 10 > video.len() * 9 {
+        score += 1;
+    }
+
+    score
+}
+
+/// Suggests the shift and load/store quirks by running `rom` under each
+/// combination. A setting is only suggested when its best run scored
+/// better than the best run without it.
+pub fn guess_quirks(rom: &[u8]) -> QuirkGuess {
+    let mut scores = [[0; 2]; 2];
+    for (shift_vy, row) in scores.iter_mut().enumerate() {
+        for (load_store_increment, score) in row.iter_mut().enumerate() {
+            let quirks = Quirks {
+                shift_vy: shift_vy == 1,
+                load_store_increment: load_store_increment == 1,
+                ..Quirks::default()
+            };
+            *score = trial(rom, quirks);
+        }
+    }
+
+    let pick = |off: u32, on: u32| (off != on).then_some(on < off);
+    QuirkGuess {
+        shift_vy: pick(
+            scores[0][0].min(scores[0][1]),
+            scores[1][0].min(scores[1][1]),
+        ),
+        load_store_increment: pick(
+            scores[0][0].min(scores[1][0]),
+            scores[0][1].min(scores[1][1]),
+        ),
+    }
+}
+
+/// Something `check` found in a ROM. Addresses are guest addresses of the
+/// offending instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    Empty,
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+    InvalidOpcode {
+        addr: u16,
+        op: u16,
+    },
+    /// A non-standard opcode, when extensions aren't allowed.
+    ExtensionOpcode {
+        addr: u16,
+        op: u16,
+    },
+    /// Control reaches `target`, which is outside the ROM.
+    LeavesRom {
+        addr: u16,
+        target: u16,
+    },
+    /// Uses I (set by a known `Annn`) to access memory past the end.
+    IndexOutOfBounds {
+        addr: u16,
+        index: u16,
+    },
+    /// Stores through I into the interpreter area below 0x200.
+    WritesInterpreterArea {
+        addr: u16,
+        index: u16,
+    },
+}
+
+impl Problem {
+    /// Errors are faults the emulator would hit; the rest are warnings.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Problem::WritesInterpreterArea { .. })
+    }
+
+    fn addr(&self) -> u16 {
+        match *self {
+            Problem::Empty | Problem::TooLarge { .. } => 0,
+            Problem::InvalidOpcode { addr, .. }
+            | Problem::ExtensionOpcode { addr, .. }
+            | Problem::LeavesRom { addr, .. }
+            | Problem::IndexOutOfBounds { addr, .. }
+            | Problem::WritesInterpreterArea { addr, .. } => addr,
+        }
+    }
+}
+
+/// Statically validates a ROM by following every path from 0x200.
+///
+/// Both outcomes of a skip are followed, `JP V0, addr` ends a path and I is
+/// only tracked from the last `Annn` on the first path to reach an address,
+/// so this can miss problems but rarely reports one that can't happen.
+pub fn check(rom: &[u8], extensions: bool) -> Vec<Problem> {
+    if rom.is_empty() {
+        return vec![Problem::Empty];
+    }
+
+    walk(rom, extensions).0
+}
+
+/// Addresses of the instructions reachable from 0x200, by the same rules as
+/// `check` with extensions allowed. Includes invalid opcodes that are
+/// reached, but not the address word of `F000 nnnn`.
+pub fn reachable(rom: &[u8]) -> HashSet<u16> {
+    let end = MEMORY_START + rom.len();
+    let mut seen = walk(rom, true).1;
+    seen.retain(|&addr| (addr as usize) >= MEMORY_START && addr as usize + 2 <= end);
+    seen
+}
+
+/// Follows every path from 0x200, returning the problems found and every
+/// address visited.
+fn walk(rom: &[u8], extensions: bool) -> (Vec<Problem>, HashSet<u16>) {
+    let mut problems = vec![];
+    let max = MEMORY_SIZE - MEMORY_START;
+    if rom.len() > max && !extensions {
+        problems.push(Problem::TooLarge {
+            size: rom.len(),
+            max,
+        });
+    }
+
+    let end = MEMORY_START + rom.len().min(max);
+    let word = |addr: u16| {
+        let offset = (addr as usize).checked_sub(MEMORY_START)?;
+        let pair = rom.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([pair[0], pair[1]]))
+    };
+    let start = MEMORY_START as u16;
+    let mut seen = HashSet::new();
+    let mut pending = vec![(start, start, None)];
+
+    while let Some((from, addr, index)) = pending.pop() {
+        if !seen.insert(addr) {
+            continue;
+        }
+
+        let offset = addr as usize;
+        if offset < MEMORY_START || offset + 2 > end {
+            problems.push(Problem::LeavesRom {
+                addr: from,
+                target: addr,
+            });
+            continue;
+        }
+
+        let Some(op) = word(addr) else {
+            continue;
+        };
+        let Some(opcode) = decode_opcode(op) else {
+            problems.push(Problem::InvalidOpcode { addr, op });
+            continue;
+        };
+
+        // Bytes accessed through I, and whether they are written
+        let access = match opcode {
+            Opcode::Drw(_, _, 0) => Some((32, false)),
+            Opcode::Drw(_, _, n) => Some((n as usize, false)),
+            Opcode::LdB(_) => Some((3, true)),
+            Opcode::LdIVx(x) => Some((x as usize + 1, true)),
+            Opcode::LdVxI(x) => Some((x as usize + 1, false)),
+            Opcode::Rtc(_) | Opcode::Pen(_) => Some((3, true)),
+            Opcode::SaveRange(x, y) => Some((x.abs_diff(y) as usize + 1, true)),
+            Opcode::LoadRange(x, y) => Some((x.abs_diff(y) as usize + 1, false)),
+            Opcode::Audio => Some((16, false)),
+            _ => None,
+        };
+        if let (Some(index), Some((len, write))) = (index, access) {
+            if index as usize + len > MEMORY_SIZE {
+                problems.push(Problem::IndexOutOfBounds { addr, index });
+            } else if write && (index as usize) < MEMORY_START {
+                problems.push(Problem::WritesInterpreterArea { addr, index });
+            }
+        }
+
+        let index = match opcode {
+            Opcode::LdI(target) => Some(target),
+            Opcode::LdILong => word(addr.wrapping_add(2)),
+            Opcode::AddI(_) | Opcode::LdF(_) | Opcode::LdHf(_) => None,
+            _ => index,
+        };
+
+        // F000 nnnn is four bytes long, both to run and to skip over
+        let long = |addr: u16| word(addr) == Some(0xF000);
+        let next = addr.wrapping_add(if long(addr) { 4 } else { 2 });
+        let skip = next.wrapping_add(if long(next) { 4 } else { 2 });
+        match opcode {
+            Opcode::Rtc(_) | Opcode::Bank(_) | Opcode::Pen(_) | Opcode::Brk if !extensions => {
+                problems.push(Problem::ExtensionOpcode { addr, op });
+            }
+            Opcode::Jp(target) => pending.push((addr, target, index)),
+            // The subroutine may change I, so it's unknown after returning
+            Opcode::Call(target) => {
+                pending.push((addr, next, None));
+                pending.push((addr, target, index));
+            }
+            Opcode::Ret | Opcode::JpV0(_) | Opcode::Exit => {}
+            Opcode::SeByte(..)
+            | Opcode::SneByte(..)
+            | Opcode::SeReg(..)
+            | Opcode::SneReg(..)
+            | Opcode::Skp(_)
+            | Opcode::Sknp(_) => {
+                pending.push((addr, skip, index));
+                pending.push((addr, next, index));
+            }
+            _ => pending.push((addr, next, index)),
+        }
+    }
+
+    problems.sort_by_key(Problem::addr);
+    (problems, seen)
+}
@@ -0,0 +1,98 @@
+//! Headless captures for ROM galleries: a ROM run for a while with no
+//! input, then its display and a few facts about it as JSON.
+
+use crate::analysis::{self, RomInfo};
+use chip8_core::chip8::{Chip8, Fault};
+use std::fmt::Write;
+
+/// How a captured run went, and what scanning the ROM found.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// Frames run: all of them unless the guest exited or faulted first.
+    pub frames: u64,
+    pub exited: bool,
+    /// The fault that stopped the run early.
+    pub fault: Option<Fault>,
+    pub hires: bool,
+    /// Whether nothing is lit, as when a ROM needs a key press to start.
+    pub blank: bool,
+    pub info: RomInfo,
+}
+
+/// Runs `cpu`, which has just loaded `rom`, for up to `frames` frames of
+/// `ipf` instructions with no keys pressed.
+pub fn run(cpu: &mut Chip8, rom: &[u8], frames: u64, ipf: u32) -> Capture {
+    let mut run = 0;
+    let mut fault = None;
+    while run < frames && !cpu.exited() {
+        if let Err(e) = cpu.run_batch(ipf, Chip8::exited) {
+            fault = Some(e.fault);
+            break;
+        }
+        cpu.tick_timers();
+        run += 1;
+    }
+
+    Capture {
+        frames: run,
+        exited: cpu.exited(),
+        fault,
+        hires: cpu.is_hires(),
+        blank: cpu.get_video().iter().all(|&pixel| pixel == 0),
+        info: analysis::analyze(rom),
+    }
+}
+
+/// `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Capture {
+    /// The capture as a JSON object, naming the ROM and its screenshot so
+    /// a gallery page can be built from these files alone.
+    pub fn to_json(&self, rom: &str, screenshot: &str) -> String {
+        let keys: Vec<String> = (0..16)
+            .filter(|&n| self.info.keys.keys[n])
+            .map(|n| format!("\"{:X}\"", n))
+            .collect();
+        let fault = self
+            .fault
+            .map_or("null".to_string(), |f| json_string(&f.to_string()));
+
+        let fields = [
+            ("rom", json_string(rom)),
+            ("screenshot", json_string(screenshot)),
+            ("size", self.info.size.to_string()),
+            ("frames", self.frames.to_string()),
+            ("exited", self.exited.to_string()),
+            ("fault", fault),
+            ("hires", self.hires.to_string()),
+            ("blank", self.blank.to_string()),
+            ("keys", format!("[{}]", keys.join(", "))),
+            ("waits_for_key", self.info.keys.waits_for_key.to_string()),
+            ("sound", self.info.sound.to_string()),
+            ("random", self.info.random.to_string()),
+        ];
+
+        let body: Vec<String> = fields
+            .iter()
+            .map(|(name, value)| format!("  \"{}\": {}", name, value))
+            .collect();
+        format!("{{\n{}\n}}\n", body.join(",\n"))
+    }
+}
@@ -0,0 +1,101 @@
+use clap::ValueEnum;
+
+use chip8_core::isa::Opcode::{self, *};
+
+/// Small diagnostic programs for checking input, timers and display
+/// without a separate test ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiagRom {
+    /// Shows the hex digit of the last key pressed
+    Keypad,
+    /// Counts seconds using the delay timer and beeps on each tick
+    Timer,
+    /// Fills the screen with a checkerboard
+    Pattern,
+}
+
+fn assemble(ops: &[Opcode], data: &[u8]) -> Vec<u8> {
+    let mut rom: Vec<u8> = ops
+        .iter()
+        .flat_map(|op| op.encode().to_be_bytes())
+        .collect();
+    rom.extend_from_slice(data);
+    rom
+}
+
+fn keypad() -> Vec<u8> {
+    assemble(
+        &[
+            Cls,              // 200: CLS
+            LdVxK(0x0),       // 202: LD V0, K
+            LdF(0x0),         // 204: LD F, V0
+            Cls,              // 206: CLS
+            LdByte(0xA, 28),  // 208: LD VA, 28
+            LdByte(0xB, 13),  // 20A: LD VB, 13
+            Drw(0xA, 0xB, 5), // 20C: DRW VA, VB, 5
+            Jp(0x202),        // 20E: JP 202
+        ],
+        &[],
+    )
+}
+
+fn timer() -> Vec<u8> {
+    assemble(
+        &[
+            LdByte(0x5, 0),   // 200: LD V5, 0
+            Cls,              // 202: CLS
+            LdI(0x300),       // 204: LD I, 300
+            LdB(0x5),         // 206: LD B, V5
+            LdVxI(0x2),       // 208: LD V2, [I]
+            LdByte(0xA, 24),  // 20A: LD VA, 24
+            LdByte(0xB, 13),  // 20C: LD VB, 13
+            LdF(0x0),         // 20E: LD F, V0
+            Drw(0xA, 0xB, 5), // 210: DRW VA, VB, 5
+            AddByte(0xA, 5),  // 212: ADD VA, 5
+            LdF(0x1),         // 214: LD F, V1
+            Drw(0xA, 0xB, 5), // 216: DRW VA, VB, 5
+            AddByte(0xA, 5),  // 218: ADD VA, 5
+            LdF(0x2),         // 21A: LD F, V2
+            Drw(0xA, 0xB, 5), // 21C: DRW VA, VB, 5
+            LdByte(0x0, 60),  // 21E: LD V0, 60
+            LdDtVx(0x0),      // 220: LD DT, V0
+            LdByte(0x0, 4),   // 222: LD V0, 4
+            LdStVx(0x0),      // 224: LD ST, V0
+            LdVxDt(0x0),      // 226: LD V0, DT
+            SeByte(0x0, 0),   // 228: SE V0, 0
+            Jp(0x226),        // 22A: JP 226
+            AddByte(0x5, 1),  // 22C: ADD V5, 1
+            Jp(0x202),        // 22E: JP 202
+        ],
+        &[],
+    )
+}
+
+fn pattern() -> Vec<u8> {
+    assemble(
+        &[
+            Cls,              // 200: CLS
+            LdI(0x218),       // 202: LD I, 218
+            LdByte(0x1, 0),   // 204: LD V1, 0
+            LdByte(0x0, 0),   // 206: LD V0, 0
+            Drw(0x0, 0x1, 8), // 208: DRW V0, V1, 8
+            AddByte(0x0, 8),  // 20A: ADD V0, 8
+            SeByte(0x0, 64),  // 20C: SE V0, 64
+            Jp(0x208),        // 20E: JP 208
+            AddByte(0x1, 8),  // 210: ADD V1, 8
+            SeByte(0x1, 32),  // 212: SE V1, 32
+            Jp(0x206),        // 214: JP 206
+            Jp(0x216),        // 216: JP 216
+        ],
+        // 218: checkerboard tile
+        &[0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55],
+    )
+}
+
+pub fn build(rom: DiagRom) -> Vec<u8> {
+    match rom {
+        DiagRom::Keypad => keypad(),
+        DiagRom::Timer => timer(),
+        DiagRom::Pattern => pattern(),
+    }
+}
@@ -0,0 +1,78 @@
+use crate::analysis;
+use chip8_core::chip8::MEMORY_START;
+use chip8_core::isa::{decode_opcode, Opcode};
+use std::collections::HashSet;
+
+/// Data bytes per listing line.
+const DATA_PER_LINE: usize = 8;
+
+/// Lists `rom` as loaded at 0x200. Words reachable from 0x200 are shown as
+/// instructions and everything else as data, with labels where code jumps
+/// or calls (`L`) and where `LD I` points (`D`).
+pub fn listing(rom: &[u8]) -> String {
+    let code = analysis::reachable(rom);
+    let byte = |addr: usize| rom[addr - MEMORY_START];
+    let word = |addr: usize| {
+        let offset = addr - MEMORY_START;
+        rom.get(offset..offset + 2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+    };
+
+    let mut jumps = HashSet::new();
+    let mut pointers = HashSet::new();
+    for &addr in &code {
+        match word(addr as usize).and_then(decode_opcode) {
+            Some(Opcode::Jp(target) | Opcode::Call(target) | Opcode::JpV0(target)) => {
+                jumps.insert(target as usize);
+            }
+            Some(Opcode::LdI(target)) => {
+                pointers.insert(target as usize);
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = vec![];
+    let end = MEMORY_START + rom.len();
+    let mut addr = MEMORY_START;
+    while addr < end {
+        if jumps.contains(&addr) {
+            lines.push(format!("L{:03X}:", addr));
+        } else if pointers.contains(&addr) {
+            lines.push(format!("D{:03X}:", addr));
+        }
+
+        if code.contains(&(addr as u16)) {
+            let op = word(addr).unwrap_or(0);
+            let opcode = decode_opcode(op);
+            // F000's address is the following word, so fill it in here
+            let long = word(addr + 2).filter(|_| opcode == Some(Opcode::LdILong));
+            let line = match (opcode, long) {
+                (_, Some(target)) => {
+                    format!("{:04X} {:04X}  LD I, {:#06X}", op, target, target)
+                }
+                (Some(opcode), None) => format!("{:04X}       {}", op, opcode.mnemonic()),
+                (None, None) => format!("{:04X}       ??", op),
+            };
+            lines.push(format!("  {:#05X}  {}", addr, line));
+            addr += if long.is_some() { 4 } else { 2 };
+            continue;
+        }
+
+        // Data runs until code, a label or the end of the line
+        let start = addr;
+        addr += 1;
+        while addr < end
+            && addr - start < DATA_PER_LINE
+            && !code.contains(&(addr as u16))
+            && !jumps.contains(&addr)
+            && !pointers.contains(&addr)
+        {
+            addr += 1;
+        }
+        let bytes: Vec<String> = (start..addr).map(|a| format!("{:02X}", byte(a))).collect();
+        lines.push(format!("  {:#05X}  {}", start, bytes.join(" ")));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,86 @@
+use chip8_core::chip8::{Chip8, Palette, HIRES_HEIGHT, HIRES_WIDTH};
+use gif::{Encoder, EncodingError, Frame, Repeat};
+use std::io::Write;
+
+/// Writes the display of one instance to a looping animated GIF. Pixels keep
+/// their plane bits as colour indices, so the palette becomes the GIF's
+/// four-colour global table. The whole 128x64 framebuffer is recorded, so
+/// lores and hires frames share one size.
+pub struct GifRecorder<W: Write> {
+    encoder: Encoder<W>,
+    scale: usize,
+    /// The last frame and how long it has been shown for, held back so that
+    /// unchanged frames only lengthen its delay.
+    pending: Option<(Vec<u8>, u16)>,
+    frames: usize,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(out: W, palette: Palette, scale: u16) -> Result<GifRecorder<W>, EncodingError> {
+        let colors: Vec<u8> = palette
+            .colors
+            .iter()
+            .flat_map(|c| [c[0], c[1], c[2]])
+            .collect();
+        let width = HIRES_WIDTH as u16 * scale;
+        let height = HIRES_HEIGHT as u16 * scale;
+
+        let mut encoder = Encoder::new(out, width, height, &colors)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        Ok(GifRecorder {
+            encoder,
+            scale: scale as usize,
+            pending: None,
+            frames: 0,
+        })
+    }
+
+    /// Adds the current display, shown for `delay` hundredths of a second.
+    pub fn frame(&mut self, cpu: &Chip8, delay: u16) -> Result<(), EncodingError> {
+        let width = HIRES_WIDTH * self.scale;
+        let mut pixels = Vec::with_capacity(width * HIRES_HEIGHT * self.scale);
+        for row in cpu.get_video().chunks_exact(HIRES_WIDTH) {
+            let start = pixels.len();
+            for &pixel in row {
+                pixels.extend(std::iter::repeat_n(pixel & 3, self.scale));
+            }
+            for _ in 1..self.scale {
+                pixels.extend_from_within(start..start + width);
+            }
+        }
+
+        match &mut self.pending {
+            Some((last, shown)) if *last == pixels => {
+                *shown = shown.saturating_add(delay);
+                Ok(())
+            }
+            _ => {
+                let previous = self.pending.replace((pixels, delay));
+                self.write(previous)
+            }
+        }
+    }
+
+    fn write(&mut self, frame: Option<(Vec<u8>, u16)>) -> Result<(), EncodingError> {
+        let Some((pixels, delay)) = frame else {
+            return Ok(());
+        };
+
+        let width = (HIRES_WIDTH * self.scale) as u16;
+        let height = (HIRES_HEIGHT * self.scale) as u16;
+        let mut frame = Frame::from_indexed_pixels(width, height, pixels, None);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Writes the frame still held back and returns how many frames the GIF
+    /// has.
+    pub fn finish(mut self) -> Result<usize, EncodingError> {
+        let last = self.pending.take();
+        self.write(last)?;
+        Ok(self.frames)
+    }
+}
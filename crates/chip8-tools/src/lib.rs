@@ -0,0 +1,13 @@
+//! Tools that work on ROMs and machines without a display: static
+//! analysis, disassembly, diagnostic ROMs, GIF recording, gallery
+//! captures, test scripts and instruction traces, with limits for scripted
+//! runs of untrusted ROMs.
+
+pub mod analysis;
+pub mod capture;
+pub mod diagroms;
+pub mod disasm;
+pub mod gifrec;
+pub mod sandbox;
+pub mod testscript;
+pub mod trace;
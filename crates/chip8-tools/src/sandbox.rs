@@ -0,0 +1,100 @@
+//! Hard limits for scripted runs of ROMs nobody has vetted. Scripted runs
+//! already keep the guest off the host: the extensions that read host state
+//! (RTC, light pen) stay disabled, banking is off so a ROM has to fit in
+//! 64K of memory from 0x200 up, and nothing touches the filesystem but
+//! the run's own output. What's left to bound is the ROM size and how long
+//! the guest runs.
+
+use chip8_core::chip8::{self, Chip8};
+use chip8_frontend::i18n::tr_args;
+use std::fmt;
+
+/// The limits of one run. `None` leaves a limit off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Instructions the whole run may execute.
+    pub max_cycles: Option<u64>,
+    /// Bytes of ROM the run may load.
+    pub max_rom_size: Option<usize>,
+}
+
+/// A limit a run ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    Cycles { limit: u64 },
+    RomSize { size: usize, limit: usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::Cycles { limit } => write!(f, "{}", tr_args("sandbox-cycles", &[&limit])),
+            Violation::RomSize { size, limit } => {
+                write!(f, "{}", tr_args("sandbox-rom-size", &[&size, &limit]))
+            }
+        }
+    }
+}
+
+/// Why a sandboxed run stopped early.
+#[derive(Debug, Clone)]
+pub enum RunError {
+    Guest(chip8::Error),
+    Limit(Violation),
+}
+
+impl From<chip8::Error> for RunError {
+    fn from(e: chip8::Error) -> RunError {
+        RunError::Guest(e)
+    }
+}
+
+impl From<Violation> for RunError {
+    fn from(v: Violation) -> RunError {
+        RunError::Limit(v)
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Guest(e) => write!(f, "{}", tr_args("err-guest", &[e])),
+            RunError::Limit(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Counts a run's instructions against its limits.
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    limits: Limits,
+    cycles: u64,
+}
+
+impl Sandbox {
+    pub fn new(limits: Limits) -> Sandbox {
+        Sandbox { limits, cycles: 0 }
+    }
+
+    /// Checks a ROM against the size limit before it's loaded.
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), Violation> {
+        match self.limits.max_rom_size {
+            Some(limit) if rom.len() > limit => Err(Violation::RomSize {
+                size: rom.len(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs one instruction, unless the run has used up its cycles.
+    pub fn step(&mut self, cpu: &mut Chip8) -> Result<(), RunError> {
+        if let Some(limit) = self.limits.max_cycles {
+            if self.cycles >= limit {
+                return Err(Violation::Cycles { limit }.into());
+            }
+        }
+        self.cycles += 1;
+        Ok(cpu.step()?)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::sandbox::{RunError, Sandbox};
+use crate::trace::Tracer;
+use chip8_core::chip8::Chip8;
+use chip8_frontend::frontend::INSTRUCTIONS_PER_FRAME;
+use chip8_frontend::i18n::tr_args;
+use std::fmt;
+
+/*
+    # comments start with #
+    press 5 at frame 10
+    release 5 at frame 12
+    assert mem[0x400] == 5 at frame 120
+    assert v3 == 0x10 at frame 120
+    assert i == 0x2A0 at frame 120
+    assert pc == 0x21E at frame 120
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Mem(usize),
+    Reg(usize),
+    I,
+    Pc,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Mem(addr) => write!(f, "mem[{:#05X}]", addr),
+            Target::Reg(n) => write!(f, "v{:X}", n),
+            Target::I => write!(f, "i"),
+            Target::Pc => write!(f, "pc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Press(usize),
+    Release(usize),
+    Assert(Target, u16),
+}
+
+fn parse_num(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_key(s: &str) -> Option<usize> {
+    u8::from_str_radix(s, 16)
+        .ok()
+        .filter(|&k| k < 16)
+        .map(|k| k as usize)
+}
+
+pub fn parse_target(s: &str) -> Option<Target> {
+    if let Some(addr) = s.strip_prefix("mem[").and_then(|s| s.strip_suffix(']')) {
+        let addr = parse_num(addr)?;
+        return (addr < 0x1000).then_some(Target::Mem(addr as usize));
+    }
+
+    match s {
+        "i" => Some(Target::I),
+        "pc" => Some(Target::Pc),
+        _ => s.strip_prefix('v').and_then(parse_key).map(Target::Reg),
+    }
+}
+
+fn parse_step(step: &str) -> Option<Step> {
+    let words: Vec<&str> = step.split_whitespace().collect();
+
+    match words[..] {
+        ["press", key] => parse_key(key).map(Step::Press),
+        ["release", key] => parse_key(key).map(Step::Release),
+        ["assert", target, "==", value] => {
+            let value = u16::try_from(parse_num(value)?).ok()?;
+            Some(Step::Assert(parse_target(target)?, value))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a script into `(frame, step)` pairs, sorted by frame.
+pub fn parse(src: &str) -> Result<Vec<(u64, Step)>, String> {
+    let mut steps = vec![];
+
+    for (n, line) in src.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || tr_args("err-script-line", &[&(n + 1), &line]);
+        let (step, frame) = line.split_once(" at frame ").ok_or_else(invalid)?;
+        let frame = parse_num(frame.trim()).ok_or_else(invalid)?;
+        let step = parse_step(step).ok_or_else(invalid)?;
+
+        steps.push((frame, step));
+    }
+
+    steps.sort_by_key(|&(frame, _)| frame);
+    Ok(steps)
+}
+
+pub fn read(cpu: &Chip8, target: Target) -> u16 {
+    match target {
+        Target::Mem(addr) => cpu.read_memory(addr).unwrap_or(0) as u16,
+        Target::Reg(n) => cpu.get_registers()[n] as u16,
+        Target::I => cpu.get_index(),
+        Target::Pc => cpu.get_pc(),
+    }
+}
+
+/// Sets `target`, keeping only the low byte for memory and registers.
+pub fn write(cpu: &mut Chip8, target: Target, value: u16) {
+    match target {
+        Target::Mem(addr) => {
+            cpu.poke_memory(addr, value as u8);
+        }
+        Target::Reg(n) => cpu.set_register(n, value as u8),
+        Target::I => cpu.set_index(value),
+        Target::Pc => cpu.set_pc(value),
+    }
+}
+
+/// Runs the script against `cpu` inside `sandbox`, printing one line per
+/// assertion and tracing each instruction to `trace`. Steps at frame N
+/// apply after N frames have run. Returns the number of failures, or what
+/// stopped the run.
+pub fn run(
+    cpu: &mut Chip8,
+    steps: &[(u64, Step)],
+    sandbox: &mut Sandbox,
+    mut trace: Option<&mut Tracer>,
+) -> Result<usize, RunError> {
+    let mut frame = 0;
+    let mut failed = 0;
+
+    for &(at, step) in steps {
+        while frame < at {
+            for _ in 0..INSTRUCTIONS_PER_FRAME {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(cpu);
+                }
+                sandbox.step(cpu)?;
+                if cpu.waiting_for_vblank() {
+                    break;
+                }
+            }
+            cpu.tick_timers();
+            frame += 1;
+        }
+
+        match step {
+            Step::Press(key) => {
+                cpu.set_keypad(key, true);
+            }
+            Step::Release(key) => {
+                cpu.set_keypad(key, false);
+            }
+            Step::Assert(target, expected) => {
+                let actual = read(cpu, target);
+                let check = format!("{} == {:#04X}", target, expected);
+
+                if actual == expected {
+                    println!("{}", tr_args("test-ok", &[&frame, &check]));
+                } else {
+                    let actual = format!("{:#04X}", actual);
+                    println!("{}", tr_args("test-failed", &[&frame, &check, &actual]));
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(failed)
+}
@@ -0,0 +1,137 @@
+use chip8_core::chip8::Chip8;
+use std::fmt;
+use std::io::{self, Write};
+
+/*
+    # chip8-trace 1
+    # boot pc=200 i=000 sp=0 dt=00 st=00 mem=5A1C0E37
+    00000000 200 00E0 00000000000000000000000000000000 000 0 00 00
+    00000001 202 A22A 00000000000000000000000000000000 000 0 00 00
+    ...
+
+    After the header, one line per instruction with the machine as it was
+    before running it: cycle, PC, the word at PC, V0-VF, I, stack depth and
+    the delay and sound timers, all hex but the cycle. The boot line
+    records the state the machine booted in, `mem` being an FNV-1a hash of
+    all 64K once the ROM is loaded. Since the boot sequence is fixed (see
+    `Chip8::new`) and cycle 0 is the first fetch, traces of the same ROM
+    and inputs from any tool match line for line.
+*/
+
+const MAGIC: &str = "# chip8-trace 1";
+
+fn checksum(mem: &[u8]) -> u32 {
+    mem.iter().fold(0x811C_9DC5, |hash, &b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Writes a trace of one instance. Write errors don't interrupt the run;
+/// the first one is kept for `finish`.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    cycle: u64,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    /// Starts a trace of `cpu`, which should have just booted and loaded
+    /// its ROM.
+    pub fn new(out: Box<dyn Write>, cpu: &Chip8) -> Tracer {
+        let mut tracer = Tracer {
+            out,
+            cycle: 0,
+            error: None,
+        };
+
+        let (dt, st) = cpu.get_timers();
+        tracer.write(format_args!("{}", MAGIC));
+        tracer.write(format_args!(
+            "# boot pc={:03X} i={:03X} sp={:X} dt={:02X} st={:02X} mem={:08X}",
+            cpu.get_pc(),
+            cpu.get_index(),
+            cpu.get_stack().len(),
+            dt,
+            st,
+            checksum(&cpu.get_memory()),
+        ));
+        tracer
+    }
+
+    fn write(&mut self, line: fmt::Arguments) {
+        if self.error.is_none() {
+            self.error = writeln!(self.out, "{}", line).err();
+        }
+    }
+
+    /// Records the instruction `cpu` is about to run.
+    pub fn record(&mut self, cpu: &Chip8) {
+        let pc = cpu.get_pc();
+        let byte = |addr: u16| cpu.read_memory(addr as usize).unwrap_or(0);
+        let op = u16::from_be_bytes([byte(pc), byte(pc.wrapping_add(1))]);
+        let registers: String = cpu
+            .get_registers()
+            .iter()
+            .map(|v| format!("{:02X}", v))
+            .collect();
+        let (dt, st) = cpu.get_timers();
+        let cycle = self.cycle;
+
+        self.write(format_args!(
+            "{:08} {:03X} {:04X} {} {:03X} {:X} {:02X} {:02X}",
+            cycle,
+            pc,
+            op,
+            registers,
+            cpu.get_index(),
+            cpu.get_stack().len(),
+            dt,
+            st,
+        ));
+        self.cycle += 1;
+    }
+
+    /// Flushes the trace, returning the first error writing it.
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Keeps what the tracer writes readable after it's boxed.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Pins the boot state and the 64K memory hash, so any change to either
+    // shows up here before it breaks traces compared across tools
+    #[test]
+    fn header_records_the_boot_state() {
+        let out = Shared::default();
+        let cpu = Chip8::new(0);
+        Tracer::new(Box::new(out.clone()), &cpu).finish().unwrap();
+
+        let trace = String::from_utf8(out.0.take()).unwrap();
+        assert_eq!(
+            trace,
+            "# chip8-trace 1\n# boot pc=200 i=000 sp=0 dt=00 st=00 mem=0AA814C7\n"
+        );
+    }
+}
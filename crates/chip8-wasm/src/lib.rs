@@ -0,0 +1,143 @@
+//! WebAssembly bindings for embedding the emulator in a web page. The page
+//! owns the loop: it calls `cycle` as often as it likes, `tick_timers` at
+//! 60 Hz, and draws the framebuffer `render` leaves in wasm memory. See
+//! `www/` for a canvas-based harness.
+
+use chip8_core::chip8::{
+    Chip8, CycleStatus, Palette, Quirks, HIRES_HEIGHT, HIRES_WIDTH, RGBA_SIZE,
+};
+use wasm_bindgen::prelude::*;
+
+/// The `CycleStatus` flags under their JavaScript names. `cycle` returns a
+/// mask of these, tested with e.g. `status & CycleStatus.DrewToScreen`.
+#[wasm_bindgen(js_name = CycleStatus)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleFlag {
+    DrewToScreen = 1,
+    WaitingForKey = 2,
+    WaitingForVblank = 4,
+    SoundStarted = 8,
+    SoundStopped = 16,
+    Exited = 32,
+}
+
+// wasm_bindgen only takes literal discriminants, so check them against the
+// core's flags at compile time
+const _: () = assert!(
+    CycleFlag::DrewToScreen as u32 == CycleStatus::DREW_TO_SCREEN.bits()
+        && CycleFlag::WaitingForKey as u32 == CycleStatus::WAITING_FOR_KEY.bits()
+        && CycleFlag::WaitingForVblank as u32 == CycleStatus::WAITING_FOR_VBLANK.bits()
+        && CycleFlag::SoundStarted as u32 == CycleStatus::SOUND_STARTED.bits()
+        && CycleFlag::SoundStopped as u32 == CycleStatus::SOUND_STOPPED.bits()
+        && CycleFlag::Exited as u32 == CycleStatus::EXITED.bits()
+);
+
+/// One emulated machine. The framebuffer is `width() * height()` bytes of
+/// plane bits, one per pixel, with lores frames drawn doubled.
+#[wasm_bindgen]
+pub struct Chip8Wasm {
+    cpu: Chip8,
+    /// Kept across `load_rom`, which starts a fresh machine. Each ROM
+    /// draws the seed's sequence from the start.
+    seed: u64,
+    quirks: Quirks,
+    palette: Palette,
+    /// The framebuffer as `render` last rendered it.
+    rgba: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Creates a machine whose CXNN draws come from `seed`, e.g. a random
+    /// value from the page, or a fixed one for reproducible runs.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> Chip8Wasm {
+        Chip8Wasm {
+            cpu: Chip8::new(seed as u64),
+            seed: seed as u64,
+            quirks: Quirks::default(),
+            palette: Palette::default(),
+            rgba: vec![0; RGBA_SIZE],
+        }
+    }
+
+    /// Resets the machine and loads a ROM at 0x200.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        let mut cpu = Chip8::new(self.seed);
+        cpu.set_quirks(self.quirks);
+        cpu.load_rom_bytes(rom)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        self.cpu = cpu;
+        Ok(())
+    }
+
+    /// Executes one instruction, returning a mask of `CycleStatus` flags.
+    /// Guest errors come back as exceptions with the disassembly and
+    /// registers in the message.
+    pub fn cycle(&mut self) -> Result<u32, JsError> {
+        let status = self.cpu.cycle().map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(status.bits())
+    }
+
+    /// Has draws wait for the vertical blank, as on the COSMAC VIP.
+    pub fn set_display_wait(&mut self, on: bool) {
+        self.quirks.display_wait = on;
+        self.cpu.set_quirks(self.quirks);
+    }
+
+    /// Counts the delay and sound timers down; call this at 60 Hz. Returns
+    /// true when the sound timer ran out, to stop the beep.
+    pub fn tick_timers(&mut self) -> bool {
+        let was_on = self.cpu.is_sound_on();
+        self.cpu.tick_timers();
+        was_on && !self.cpu.is_sound_on()
+    }
+
+    /// Whether the sound timer is running, for the page to beep.
+    pub fn sound_on(&self) -> bool {
+        self.cpu.is_sound_on()
+    }
+
+    /// Whether the last cycle was SCHIP's EXIT.
+    pub fn exited(&self) -> bool {
+        self.cpu.exited()
+    }
+
+    /// Presses or releases a key, 0x0 to 0xF.
+    pub fn set_key(&mut self, key: u8, down: bool) {
+        if key < 16 {
+            self.cpu.set_keypad(key as usize, down);
+        }
+    }
+
+    /// Where the framebuffer starts in wasm memory. It stays put between
+    /// cycles, but view it afresh after `load_rom` or if memory grows.
+    pub fn get_framebuffer_ptr(&self) -> *const u8 {
+        self.cpu.get_video().as_ptr()
+    }
+
+    /// Renders the framebuffer as `width() * height() * 4` bytes of RGBA,
+    /// ready for an `ImageData`, and returns where they are in wasm memory.
+    pub fn render(&mut self) -> *const u8 {
+        self.cpu.render_rgba(&mut self.rgba, self.palette);
+        self.rgba.as_ptr()
+    }
+
+    /// Sets the colours `render` uses, as 0xRRGGBB. XO-CHIP's extra planes
+    /// get shades in between.
+    pub fn set_colors(&mut self, background: u32, foreground: u32) {
+        let rgba = |color: u32| {
+            let [_, r, g, b] = color.to_be_bytes();
+            [r, g, b, 255]
+        };
+        self.palette = Palette::new(rgba(background), rgba(foreground));
+    }
+
+    pub fn width() -> usize {
+        HIRES_WIDTH
+    }
+
+    pub fn height() -> usize {
+        HIRES_HEIGHT
+    }
+}
@@ -0,0 +1,73 @@
+// Build the bindings first, from the repository root:
+//
+//     wasm-pack build --target web crates/chip8-wasm
+//
+// then serve crates/chip8-wasm over HTTP and open www/index.html.
+
+import init, { Chip8Wasm, CycleStatus } from "../pkg/chip8_wasm.js";
+
+// The first SDL instance's layout, indexed by CHIP-8 key
+const KEYMAP = ["X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V"];
+const INSTRUCTIONS_PER_FRAME = 10;
+
+const wasm = await init();
+const chip8 = new Chip8Wasm(Math.floor(Math.random() * 2 ** 32));
+const width = Chip8Wasm.width();
+const height = Chip8Wasm.height();
+
+const ctx = document.getElementById("screen").getContext("2d");
+const image = ctx.createImageData(width, height);
+
+let running = false;
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const file = event.target.files[0];
+  if (!file) {
+    return;
+  }
+  chip8.load_rom(new Uint8Array(await file.arrayBuffer()));
+  running = true;
+  draw();
+});
+
+for (const [type, down] of [["keydown", true], ["keyup", false]]) {
+  document.addEventListener(type, (event) => {
+    const key = KEYMAP.indexOf(event.key.toUpperCase());
+    if (key >= 0) {
+      chip8.set_key(key, down);
+    }
+  });
+}
+
+function draw() {
+  const rgba = new Uint8ClampedArray(wasm.memory.buffer, chip8.render(), width * height * 4);
+  image.data.set(rgba);
+  ctx.putImageData(image, 0, 0);
+}
+
+function frame() {
+  if (running) {
+    let drew = false;
+    try {
+      for (let n = 0; n < INSTRUCTIONS_PER_FRAME; n++) {
+        const status = chip8.cycle();
+        drew ||= (status & CycleStatus.DrewToScreen) !== 0;
+        // A draw waiting for the vertical blank ends the frame early
+        if (status & (CycleStatus.WaitingForVblank | CycleStatus.Exited)) {
+          break;
+        }
+      }
+      chip8.tick_timers();
+    } catch (e) {
+      console.error(e);
+      running = false;
+    }
+    // The framebuffer only changes when an instruction says it did
+    if (drew) {
+      draw();
+    }
+  }
+  requestAnimationFrame(frame);
+}
+
+requestAnimationFrame(frame);
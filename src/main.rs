@@ -1,29 +1,1444 @@
-mod chip8;
-mod sdlgui;
+use chip8_core::chip8::{
+    Chip8, Extensions, GuestEvent, LoadError, Palette, Quirks, DEFAULT_STACK_LIMIT, HIRES_HEIGHT,
+    HIRES_WIDTH, MAX_STACK_LIMIT, MEMORY_SIZE, MEMORY_START, VIP_DRAW_BUDGET,
+};
+use chip8_frontend::frontend::{Machine, INSTRUCTIONS_PER_FRAME, KEYMAPS};
+use chip8_frontend::i18n::{self, tr, tr_args, Lang};
+use chip8_frontend::replay::Replay;
+use chip8_frontend_sdl::config::{self, Config};
+use chip8_frontend_sdl::console;
+use chip8_frontend_sdl::inputdisplay::InputDisplay;
+use chip8_frontend_sdl::sdlgui::{auto_bindings, Display, FastForward, Jitter, SDLGui, Stick};
+use chip8_frontend_sdl::status::{self, Status};
+use chip8_frontend_sdl::{Filter, Sound, DEFAULT_PHOSPHOR_FRAMES};
+use chip8_frontend_tui::{terminal, tui};
+use chip8_tools::analysis::{self, Problem};
+use chip8_tools::capture;
+use chip8_tools::diagroms::{self, DiagRom};
+use chip8_tools::disasm;
+use chip8_tools::gifrec::GifRecorder;
+use chip8_tools::sandbox::{Limits, RunError, Sandbox};
+use chip8_tools::testscript::{self, Step};
+use chip8_tools::trace::Tracer;
 
-use crate::chip8::Chip8;
-use crate::sdlgui::SDLGui;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::env;
+use std::fs;
+use std::io;
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use clap::Parser;
-
-/// Chip-8 Emulator in Rust
+// Help and descriptions come from the message table, in
+// `localized_command`, rather than doc comments
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// ROM file to load
-    #[arg()]
-    rom_file: String,
+#[command(author, version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true)]
+    rom_file: Option<String>,
 
-    /// Graphics scale
     #[arg(default_value_t = 20)]
     scale: u32,
+
+    #[command(flatten)]
+    opts: Options,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Run {
+        #[arg(required = true, num_args = 1..=2)]
+        rom_files: Vec<String>,
+
+        #[arg(long, default_value_t = 20)]
+        scale: u32,
+
+        #[command(flatten)]
+        opts: Options,
+    },
+
+    GenRom {
+        #[arg(value_enum)]
+        kind: DiagRom,
+
+        output: PathBuf,
+    },
+
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    Manpage,
+
+    Info {
+        rom_file: String,
+    },
+
+    Check {
+        rom_file: String,
+
+        #[arg(long)]
+        extensions: bool,
+    },
+
+    Disasm {
+        rom_file: String,
+    },
+
+    Test {
+        rom_file: String,
+
+        script: PathBuf,
+
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        #[arg(long, value_name = "FILE")]
+        trace: Option<PathBuf>,
+
+        #[command(flatten)]
+        limits: LimitOptions,
+    },
+
+    RecordGif {
+        rom_file: String,
+
+        output: PathBuf,
+
+        #[command(flatten)]
+        gif: GifOptions,
+    },
+
+    BatchCapture {
+        dir: PathBuf,
+
+        #[command(flatten)]
+        capture: CaptureOptions,
+    },
+}
+
+#[derive(Args, Debug)]
+struct CaptureOptions {
+    #[arg(long, value_name = "DIR")]
+    out: PathBuf,
+
+    #[arg(long, default_value_t = 900)]
+    frames: u64,
+
+    #[arg(long, default_value_t = INSTRUCTIONS_PER_FRAME)]
+    ipf: u32,
+
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..=16))]
+    scale: u16,
+
+    #[command(flatten)]
+    colors: PaletteOptions,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[arg(long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+}
+
+#[derive(Args, Debug)]
+struct GifOptions {
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 600)]
+    frames: u64,
+
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..=16))]
+    scale: u16,
+
+    #[command(flatten)]
+    colors: PaletteOptions,
+
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..=50))]
+    fps: u64,
+
+    #[arg(long, default_value_t = INSTRUCTIONS_PER_FRAME)]
+    ipf: u32,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    #[command(flatten)]
+    limits: LimitOptions,
+}
+
+/// A colour theme, with any of its colours replaced.
+#[derive(Args, Debug)]
+struct PaletteOptions {
+    #[arg(long, value_enum, default_value_t = PalettePreset::Mono)]
+    palette: PalettePreset,
+
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    bg: Option<[u8; 4]>,
+
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    fg: Option<[u8; 4]>,
+
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    fg2: Option<[u8; 4]>,
+
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    blend: Option<[u8; 4]>,
+}
+
+impl PaletteOptions {
+    fn palette(&self) -> Palette {
+        let mut palette = self.palette.palette();
+        if self.bg.is_some() || self.fg.is_some() {
+            let [bg, fg, ..] = palette.colors;
+            palette = Palette::new(self.bg.unwrap_or(bg), self.fg.unwrap_or(fg));
+        }
+        if let Some(fg2) = self.fg2 {
+            palette.colors[2] = fg2;
+        }
+        if let Some(blend) = self.blend {
+            palette.colors[3] = blend;
+        }
+        palette
+    }
+}
+
+/// Limits for scripted runs of untrusted ROMs. Running into one exits with
+/// `EXIT_LIMIT`.
+#[derive(Args, Debug)]
+struct LimitOptions {
+    #[arg(long, value_name = "N")]
+    max_cycles: Option<u64>,
+
+    // Defaults to the most that fits in memory without banking, so an
+    // oversized ROM exits with `EXIT_LIMIT` like any other limit
+    #[arg(long, value_name = "BYTES", default_value_t = MEMORY_SIZE - MEMORY_START)]
+    max_rom_size: usize,
+}
+
+impl LimitOptions {
+    fn sandbox(&self) -> Sandbox {
+        Sandbox::new(Limits {
+            max_cycles: self.max_cycles,
+            max_rom_size: Some(self.max_rom_size),
+        })
+    }
+}
+
+/// The exit status of a scripted run stopped by a limit, so whatever runs
+/// it can tell a runaway or oversized ROM from a failing one.
+const EXIT_LIMIT: i32 = 3;
+
+#[derive(Args, Debug)]
+struct Options {
+    #[arg(long)]
+    console: bool,
+
+    #[arg(long)]
+    announce: bool,
+
+    #[arg(long)]
+    perf_hud: bool,
+
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "FILTERS")]
+    filter: Vec<VideoFilter>,
+
+    #[arg(long, value_name = "FRAMES", default_value_t = DEFAULT_PHOSPHOR_FRAMES, value_parser = clap::value_parser!(u32).range(1..=60))]
+    phosphor_frames: u32,
+
+    #[command(flatten)]
+    colors: PaletteOptions,
+
+    #[arg(long)]
+    debug_tui: bool,
+
+    #[arg(long)]
+    mute: bool,
+
+    #[arg(long, value_name = "X", value_parser = clap::value_parser!(u32).range(2..=16))]
+    fast_forward_max: Option<u32>,
+
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(0..=60))]
+    fast_forward_ramp: Option<u64>,
+
+    #[arg(long)]
+    fast_forward_mute: bool,
+
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=100))]
+    volume: u32,
+
+    #[arg(long, value_name = "HZ", default_value_t = 500, value_parser = clap::value_parser!(u32).range(20..=10000))]
+    tone: u32,
+
+    #[arg(long)]
+    timed_sound: bool,
+
+    #[arg(long, value_enum, default_value_t = Frontend::Sdl)]
+    frontend: Frontend,
+
+    #[arg(long, requires = "frames")]
+    headless: bool,
+
+    #[arg(long, value_name = "N", requires = "headless")]
+    frames: Option<u64>,
+
+    #[arg(long, value_name = "DIR", requires = "headless")]
+    dump_dir: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    input_file: Option<PathBuf>,
+
+    #[arg(long)]
+    auto_keys: bool,
+
+    #[arg(long)]
+    keypad_overlay: bool,
+
+    #[arg(long, value_name = "FILE", alias = "keymap")]
+    config: Option<PathBuf>,
+
+    #[arg(long)]
+    hires_snap: bool,
+
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    ipf: Option<u32>,
+
+    #[arg(long, value_name = "IPS", conflicts_with = "ipf", value_parser = clap::value_parser!(u32).range(1..))]
+    speed: Option<u32>,
+
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..=60))]
+    fps: u32,
+
+    #[arg(long)]
+    frame_stats: bool,
+
+    #[arg(long)]
+    calibrate: bool,
+
+    #[arg(long, default_value_t = 0)]
+    ipf_jitter: u32,
+
+    #[arg(long, default_value_t = 0)]
+    jitter_seed: u64,
+
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=100))]
+    background_speed: u32,
+
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..=100))]
+    stick_deadzone: u32,
+
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=100))]
+    stick_hysteresis: u32,
+
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=600))]
+    rewind_seconds: u32,
+
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=60))]
+    rewind_interval: u32,
+
+    #[arg(long, value_name = "MINUTES")]
+    confirm_overwrite: Option<u64>,
+
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=16))]
+    input_polls: u32,
+
+    #[arg(long, value_enum)]
+    quirks: Option<QuirksProfile>,
+
+    #[arg(long)]
+    clip_x: bool,
+
+    #[arg(long)]
+    clip_y: bool,
+
+    #[arg(long)]
+    shift_vy: bool,
+
+    #[arg(long)]
+    load_store_increment: bool,
+
+    #[arg(long)]
+    jump_vx: bool,
+
+    #[arg(long)]
+    vf_reset: bool,
+
+    #[arg(long)]
+    display_wait: bool,
+
+    #[arg(long, default_value_t = DEFAULT_STACK_LIMIT, value_parser = parse_stack_limit)]
+    stack_limit: usize,
+
+    #[arg(long)]
+    strict: bool,
+
+    #[arg(long, value_name = "ROWS", default_value_t = VIP_DRAW_BUDGET)]
+    draw_budget: u16,
+
+    #[arg(long)]
+    deterministic: bool,
+
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["replay", "input_polls"])]
+    record_replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "input_polls")]
+    replay: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[arg(long)]
+    ext_rtc: bool,
+
+    #[arg(long)]
+    ext_banking: bool,
+
+    #[arg(long)]
+    ext_light_pen: bool,
+
+    #[arg(long)]
+    ext_frame_counter: bool,
+
+    #[arg(long, value_parser = parse_lang)]
+    lang: Option<Lang>,
+}
+
+/// Quirk presets for `--quirks`; the individual quirk flags add to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QuirksProfile {
+    /// The original COSMAC VIP interpreter
+    #[value(name = "chip-8")]
+    Chip8,
+    /// SUPER-CHIP 1.1
+    Schip,
+    /// XO-CHIP as implemented by Octo
+    XoChip,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Frontend {
+    /// A window, with sound and controllers
+    Sdl,
+    /// Half-block characters in the terminal, e.g. over SSH
+    Terminal,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PalettePreset {
+    /// White on black
+    Mono,
+    /// Amber phosphor
+    Amber,
+    /// Green phosphor
+    Green,
+    /// Game Boy greens
+    Gameboy,
+    /// Octo's yellow and orange
+    Octo,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum VideoFilter {
+    /// Darkened lines between pixel rows, like a CRT
+    Scanlines,
+    /// Gaps between pixels, like an LCD
+    Grid,
+    /// Pixels fade out over a few frames, see --phosphor-frames
+    Phosphor,
+    /// The picture bulges like a CRT's glass
+    Curvature,
+}
+
+impl VideoFilter {
+    /// The filter, with phosphor fading out over `frames`.
+    fn filter(self, frames: u32) -> Filter {
+        match self {
+            VideoFilter::Scanlines => Filter::Scanlines,
+            VideoFilter::Grid => Filter::Grid,
+            VideoFilter::Phosphor => Filter::Phosphor { frames },
+            VideoFilter::Curvature => Filter::Curvature,
+        }
+    }
+}
+
+impl PalettePreset {
+    fn palette(self) -> Palette {
+        match self {
+            PalettePreset::Mono => Palette::default(),
+            PalettePreset::Amber => Palette::amber(),
+            PalettePreset::Green => Palette::green(),
+            PalettePreset::Gameboy => Palette::gameboy(),
+            PalettePreset::Octo => Palette::octo(),
+        }
+    }
+}
+
+impl Options {
+    /// `--ipf`, or `--speed` converted to whole instructions per frame, if
+    /// either is given.
+    fn instructions_per_frame(&self) -> Option<u32> {
+        match self.speed {
+            Some(ips) => Some(((ips + 30) / 60).max(1)),
+            None => self.ipf,
+        }
+    }
+
+    fn quirks(&self) -> Quirks {
+        let base = match self.quirks {
+            Some(QuirksProfile::Chip8) => Quirks::chip8(),
+            Some(QuirksProfile::Schip) => Quirks::schip(),
+            Some(QuirksProfile::XoChip) => Quirks::xo_chip(),
+            None => Quirks::default(),
+        };
+
+        Quirks {
+            wrap_x: base.wrap_x && !self.clip_x,
+            wrap_y: base.wrap_y && !self.clip_y,
+            shift_vy: base.shift_vy || self.shift_vy,
+            load_store_increment: base.load_store_increment || self.load_store_increment,
+            jump_vx: base.jump_vx || self.jump_vx,
+            vf_reset: base.vf_reset || self.vf_reset,
+            display_wait: base.display_wait || self.display_wait,
+        }
+    }
+}
+
+fn parse_lang(tag: &str) -> Result<Lang, String> {
+    Lang::from_tag(tag).ok_or_else(|| tr_args("err-unknown-lang", &[&tag]))
+}
+
+/// Parses a colour as `RRGGBB` hex, with or without a leading `#`.
+fn parse_color(s: &str) -> Result<[u8; 4], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(tr_args("err-color", &[&s]));
+    }
+    let [_, r, g, b] = u32::from_str_radix(hex, 16).unwrap_or(0).to_be_bytes();
+    Ok([r, g, b, 255])
+}
+
+fn parse_stack_limit(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(n) if (1..=MAX_STACK_LIMIT).contains(&n) => Ok(n),
+        _ => Err(tr_args("err-stack-limit", &[&MAX_STACK_LIMIT])),
+    }
+}
+
+/// Finds `--lang` before clap runs, so that help and errors from the parser
+/// are already translated.
+fn lang_from_args() -> Option<Lang> {
+    let args: Vec<String> = env::args().collect();
+
+    args.iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.strip_prefix("--lang") {
+            Some("") => args.get(i + 1).and_then(|tag| Lang::from_tag(tag)),
+            Some(rest) => rest.strip_prefix('=').and_then(Lang::from_tag),
+            None => None,
+        })
+}
+
+fn localize_options(cmd: clap::Command) -> clap::Command {
+    let cmd = cmd
+        .mut_arg("console", |a| a.help(tr("arg-console")))
+        .mut_arg("announce", |a| a.help(tr("arg-announce")))
+        .mut_arg("perf_hud", |a| a.help(tr("arg-perf-hud")))
+        .mut_arg("filter", |a| a.help(tr("arg-filter")))
+        .mut_arg("phosphor_frames", |a| a.help(tr("arg-phosphor-frames")))
+        .mut_arg("debug_tui", |a| a.help(tr("arg-debug-tui")))
+        .mut_arg("mute", |a| a.help(tr("arg-mute")))
+        .mut_arg("fast_forward_max", |a| a.help(tr("arg-fast-forward-max")))
+        .mut_arg("fast_forward_ramp", |a| a.help(tr("arg-fast-forward-ramp")))
+        .mut_arg("fast_forward_mute", |a| a.help(tr("arg-fast-forward-mute")))
+        .mut_arg("volume", |a| a.help(tr("arg-volume")))
+        .mut_arg("tone", |a| a.help(tr("arg-tone")))
+        .mut_arg("timed_sound", |a| a.help(tr("arg-timed-sound")))
+        .mut_arg("frontend", |a| a.help(tr("arg-frontend")))
+        .mut_arg("headless", |a| a.help(tr("arg-headless")))
+        .mut_arg("frames", |a| a.help(tr("arg-frames")))
+        .mut_arg("dump_dir", |a| a.help(tr("arg-dump-dir")))
+        .mut_arg("input_file", |a| a.help(tr("arg-input-file")))
+        .mut_arg("auto_keys", |a| a.help(tr("arg-auto-keys")))
+        .mut_arg("keypad_overlay", |a| a.help(tr("arg-keypad-overlay")))
+        .mut_arg("config", |a| a.help(tr("arg-config")))
+        .mut_arg("hires_snap", |a| a.help(tr("arg-hires-snap")))
+        .mut_arg("ipf", |a| a.help(tr("arg-ipf")))
+        .mut_arg("speed", |a| a.help(tr("arg-speed")))
+        .mut_arg("fps", |a| a.help(tr("arg-display-fps")))
+        .mut_arg("frame_stats", |a| a.help(tr("arg-frame-stats")))
+        .mut_arg("calibrate", |a| a.help(tr("arg-calibrate")))
+        .mut_arg("ipf_jitter", |a| a.help(tr("arg-ipf-jitter")))
+        .mut_arg("jitter_seed", |a| a.help(tr("arg-jitter-seed")))
+        .mut_arg("background_speed", |a| a.help(tr("arg-background-speed")))
+        .mut_arg("stick_deadzone", |a| a.help(tr("arg-stick-deadzone")))
+        .mut_arg("stick_hysteresis", |a| a.help(tr("arg-stick-hysteresis")))
+        .mut_arg("rewind_seconds", |a| a.help(tr("arg-rewind-seconds")))
+        .mut_arg("confirm_overwrite", |a| a.help(tr("arg-confirm-overwrite")))
+        .mut_arg("rewind_interval", |a| a.help(tr("arg-rewind-interval")))
+        .mut_arg("input_polls", |a| a.help(tr("arg-input-polls")))
+        .mut_arg("quirks", |a| a.help(tr("arg-quirks")))
+        .mut_arg("clip_x", |a| a.help(tr("arg-clip-x")))
+        .mut_arg("clip_y", |a| a.help(tr("arg-clip-y")))
+        .mut_arg("shift_vy", |a| a.help(tr("arg-shift-vy")))
+        .mut_arg("load_store_increment", |a| {
+            a.help(tr("arg-load-store-increment"))
+        })
+        .mut_arg("jump_vx", |a| a.help(tr("arg-jump-vx")))
+        .mut_arg("vf_reset", |a| a.help(tr("arg-vf-reset")))
+        .mut_arg("display_wait", |a| a.help(tr("arg-display-wait")))
+        .mut_arg("stack_limit", |a| a.help(tr("arg-stack-limit")))
+        .mut_arg("strict", |a| a.help(tr("arg-strict")))
+        .mut_arg("draw_budget", |a| a.help(tr("arg-draw-budget")))
+        .mut_arg("deterministic", |a| a.help(tr("arg-deterministic")))
+        .mut_arg("record_replay", |a| a.help(tr("arg-record-replay")))
+        .mut_arg("replay", |a| a.help(tr("arg-replay")))
+        .mut_arg("seed", |a| a.help(tr("arg-seed")))
+        .mut_arg("ext_rtc", |a| a.help(tr("arg-ext-rtc")))
+        .mut_arg("ext_banking", |a| a.help(tr("arg-ext-banking")))
+        .mut_arg("ext_light_pen", |a| a.help(tr("arg-ext-light-pen")))
+        .mut_arg("ext_frame_counter", |a| a.help(tr("arg-ext-frame-counter")))
+        .mut_arg("lang", |a| a.help(tr("arg-lang")));
+    localize_palette(cmd)
+}
+
+/// Translates the help of a flattened `PaletteOptions`.
+fn localize_palette(cmd: clap::Command) -> clap::Command {
+    cmd.mut_arg("palette", |a| a.help(tr("arg-palette")))
+        .mut_arg("bg", |a| a.help(tr("arg-bg")))
+        .mut_arg("fg", |a| a.help(tr("arg-fg")))
+        .mut_arg("fg2", |a| a.help(tr("arg-fg2")))
+        .mut_arg("blend", |a| a.help(tr("arg-blend")))
+}
+
+fn localized_command() -> clap::Command {
+    let cmd = Cli::command()
+        .about(tr("about"))
+        .mut_arg("rom_file", |a| a.help(tr("arg-rom-file")))
+        .mut_arg("scale", |a| a.help(tr("arg-scale")))
+        .mut_subcommand("run", |c| {
+            let c = c
+                .about(tr("cmd-run"))
+                .mut_arg("rom_files", |a| a.help(tr("arg-rom-files")))
+                .mut_arg("scale", |a| a.help(tr("arg-scale")));
+            localize_options(c)
+        })
+        .mut_subcommand("gen-rom", |c| {
+            c.about(tr("cmd-gen-rom"))
+                .mut_arg("kind", |a| a.help(tr("arg-kind")))
+                .mut_arg("output", |a| a.help(tr("arg-output")))
+        })
+        .mut_subcommand("completions", |c| {
+            c.about(tr("cmd-completions"))
+                .mut_arg("shell", |a| a.help(tr("arg-shell")))
+        })
+        .mut_subcommand("manpage", |c| c.about(tr("cmd-manpage")))
+        .mut_subcommand("info", |c| {
+            c.about(tr("cmd-info"))
+                .mut_arg("rom_file", |a| a.help(tr("arg-rom-file")))
+        })
+        .mut_subcommand("check", |c| {
+            c.about(tr("cmd-check"))
+                .mut_arg("rom_file", |a| a.help(tr("arg-check-rom-file")))
+                .mut_arg("extensions", |a| a.help(tr("arg-extensions")))
+        })
+        .mut_subcommand("disasm", |c| {
+            c.about(tr("cmd-disasm"))
+                .mut_arg("rom_file", |a| a.help(tr("arg-disasm-rom-file")))
+        })
+        .mut_subcommand("test", |c| {
+            c.about(tr("cmd-test"))
+                .mut_arg("rom_file", |a| a.help(tr("arg-rom-file")))
+                .mut_arg("script", |a| a.help(tr("arg-script")))
+                .mut_arg("seed", |a| a.help(tr("arg-seed")))
+                .mut_arg("trace", |a| a.help(tr("arg-trace")))
+                .mut_arg("max_cycles", |a| a.help(tr("arg-max-cycles")))
+                .mut_arg("max_rom_size", |a| a.help(tr("arg-max-rom-size")))
+        })
+        .mut_subcommand("record-gif", |c| {
+            let c = c
+                .about(tr("cmd-record-gif"))
+                .mut_arg("rom_file", |a| a.help(tr("arg-rom-file")))
+                .mut_arg("output", |a| a.help(tr("arg-output")))
+                .mut_arg("script", |a| a.help(tr("arg-gif-script")))
+                .mut_arg("frames", |a| a.help(tr("arg-gif-frames")))
+                .mut_arg("scale", |a| a.help(tr("arg-gif-scale")))
+                .mut_arg("fps", |a| a.help(tr("arg-fps")))
+                .mut_arg("ipf", |a| a.help(tr("arg-ipf")))
+                .mut_arg("seed", |a| a.help(tr("arg-seed")))
+                .mut_arg("trace", |a| a.help(tr("arg-trace")))
+                .mut_arg("max_cycles", |a| a.help(tr("arg-max-cycles")))
+                .mut_arg("max_rom_size", |a| a.help(tr("arg-max-rom-size")));
+            localize_palette(c)
+        })
+        .mut_subcommand("batch-capture", |c| {
+            let c = c
+                .about(tr("cmd-batch-capture"))
+                .mut_arg("dir", |a| a.help(tr("arg-capture-dir")))
+                .mut_arg("out", |a| a.help(tr("arg-capture-out")))
+                .mut_arg("frames", |a| a.help(tr("arg-capture-frames")))
+                .mut_arg("ipf", |a| a.help(tr("arg-ipf")))
+                .mut_arg("scale", |a| a.help(tr("arg-gif-scale")))
+                .mut_arg("seed", |a| a.help(tr("arg-seed")))
+                .mut_arg("jobs", |a| a.help(tr("arg-jobs")));
+            localize_palette(c)
+        });
+
+    localize_options(cmd)
+}
+
+/// The time of day for FxE0, in UTC: std has no notion of time zones.
+fn system_clock() -> (u8, u8, u8) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let h = (secs / 3600) % 24;
+    let m = (secs / 60) % 60;
+    let s = secs % 60;
+
+    (h as u8, m as u8, s as u8)
+}
+
+/// Reads a ROM file into an instance; the core itself doesn't touch files.
+/// Loads `rom_file` into `cpu`, returning what was in the file.
+fn load_rom(cpu: &mut Chip8, rom_file: &str) -> Result<Vec<u8>, LoadError> {
+    let data = fs::read(rom_file)?;
+    cpu.load_rom_bytes(&data)?;
+    Ok(data)
+}
+
+fn load_error(rom_file: &str, e: &LoadError) -> String {
+    match e {
+        LoadError::RomTooLarge { size, max } => {
+            tr_args("err-rom-too-large", &[&rom_file, size, max])
+        }
+        LoadError::Io(e) => tr_args("err-read-rom", &[&rom_file, e]),
+    }
+}
+
+/// Loads a ROM for a scripted run, exiting if it can't be loaded or is over
+/// the sandbox's size limit.
+fn load_sandboxed(cpu: &mut Chip8, rom_file: &str, sandbox: &Sandbox) {
+    let data = fs::read(rom_file).unwrap_or_else(|e| {
+        eprintln!("{}", load_error(rom_file, &LoadError::Io(e)));
+        process::exit(1);
+    });
+    if let Err(v) = sandbox.check_rom(&data) {
+        exit_run(&v.into());
+    }
+    if let Err(e) = cpu.load_rom_bytes(&data) {
+        eprintln!("{}", load_error(rom_file, &e));
+        process::exit(1);
+    }
+}
+
+/// Starts a trace of a freshly loaded `cpu` to `path`, if there is one.
+fn start_trace(path: Option<&Path>, cpu: &Chip8) -> Option<Tracer> {
+    let path = path?;
+    let file = fs::File::create(path).unwrap_or_else(|e| {
+        eprintln!("{}", tr_args("err-write-trace", &[&path.display(), &e]));
+        process::exit(1);
+    });
+    Some(Tracer::new(Box::new(io::BufWriter::new(file)), cpu))
+}
+
+/// Flushes a trace from `start_trace`, exiting if it couldn't be written.
+fn finish_trace(path: Option<&Path>, tracer: Option<Tracer>) {
+    if let (Some(path), Some(tracer)) = (path, tracer) {
+        if let Err(e) = tracer.finish() {
+            eprintln!("{}", tr_args("err-write-trace", &[&path.display(), &e]));
+            process::exit(1);
+        }
+    }
+}
+
+/// Reports what stopped a scripted run and exits, with `EXIT_LIMIT` for a
+/// limit.
+fn exit_run(e: &RunError) -> ! {
+    eprintln!("{}", e);
+    process::exit(match e {
+        RunError::Guest(_) => 1,
+        RunError::Limit(_) => EXIT_LIMIT,
+    });
+}
+
+fn run(rom_files: &[String], scale: u32, opts: &Options) {
+    let replay = opts.replay.as_ref().map(|path| {
+        let src = fs::read_to_string(path)
+            .map_err(|e| tr_args("err-read-replay", &[&path.display(), &e]));
+        src.and_then(|src| Replay::parse(&src)).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        })
+    });
+
+    // Replays need the same CXNN draws and clock as the recording, but stay
+    // paced so they can be played
+    let replaying = opts.record_replay.is_some() || replay.is_some();
+    let seeded = opts.deterministic || replaying;
+    let seed = replay.as_ref().map_or(opts.seed, |replay| replay.seed);
+
+    let (mut cpus, rom_hashes): (Vec<Chip8>, Vec<String>) = rom_files
+        .iter()
+        .map(|rom_file| {
+            // Every instance starts from the seed, so split-screen players
+            // racing the same game see the same CXNN draws
+            let mut cpu = Chip8::new(match seeded {
+                true => seed,
+                false => rand::random(),
+            });
+            cpu.set_extensions(Extensions {
+                rtc: opts.ext_rtc,
+                banking: opts.ext_banking,
+                light_pen: opts.ext_light_pen,
+                frame_counter: opts.ext_frame_counter,
+            });
+            // Seeded runs keep the core's fixed 00:00:00 on the RTC.
+            if !seeded {
+                cpu.set_clock(system_clock);
+            }
+            cpu.set_quirks(opts.quirks());
+            // parse_stack_limit has already checked the range
+            cpu.set_stack_limit(opts.stack_limit);
+            cpu.set_draw_budget(opts.strict.then_some(opts.draw_budget));
+            let data = load_rom(&mut cpu, rom_file).unwrap_or_else(|e| {
+                eprintln!("{}", load_error(rom_file, &e));
+                process::exit(1);
+            });
+            (cpu, config::rom_hash(&data))
+        })
+        .unzip();
+
+    // The default config file is optional; one given by name isn't
+    let config_path = opts.config.clone().or_else(config::default_path);
+    let config = match &config_path {
+        Some(path) if opts.config.is_some() || path.exists() => config::load(path, &KEYMAPS[0])
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            }),
+        _ => Config::new(&KEYMAPS[0]),
+    };
+
+    // A calibrated speed belongs to one game, so it's only stored and used
+    // when every instance runs the same ROM
+    let rom_hash = match &rom_hashes[..] {
+        [first, rest @ ..] if rest.iter().all(|hash| hash == first) => Some(first),
+        _ => None,
+    };
+    let calibrated = rom_hash.and_then(|hash| config.rom_ipf.get(hash).copied());
+    let ipf = match (opts.instructions_per_frame(), calibrated) {
+        (Some(ipf), _) => ipf,
+        (None, Some(ipf)) => {
+            println!("{}", tr_args("calibrate-applied", &[&ipf]));
+            ipf
+        }
+        (None, None) => INSTRUCTIONS_PER_FRAME,
+    };
+
+    if opts.debug_tui {
+        if let Err(e) = tui::run(cpus.remove(0), ipf) {
+            eprintln!("{}", tr_args("err-tui", &[&e]));
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let (true, Some(frames)) = (opts.headless, opts.frames) {
+        headless(cpus, frames, opts);
+        return;
+    }
+
+    if opts.frontend == Frontend::Terminal {
+        if let Err(e) = terminal::run(cpus.remove(0), ipf, opts.mute) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if opts.announce {
+        for (instance, rom) in rom_files.iter().enumerate() {
+            status::announce(Status::Started { instance, rom });
+        }
+    }
+
+    // Split-screen already uses most of the keyboard.
+    let bindings = match &cpus[..] {
+        [cpu] if opts.auto_keys => {
+            let usage = analysis::keys_read(&cpu.get_memory()[MEMORY_START..]);
+            auto_bindings(&usage.keys)
+        }
+        _ => vec![],
+    };
+
+    for (name, key) in &bindings {
+        println!("{}", tr_args("auto-key", &[name, &format!("{:X}", key)]));
+    }
+
+    // When the scan finds no keys at all, it has missed how the ROM reads
+    // them, so nothing is dimmed
+    let keypad_overlay = opts.keypad_overlay.then(|| {
+        cpus.iter()
+            .map(|cpu| analysis::keys_read(&cpu.get_memory()[MEMORY_START..]).keys)
+            .map(|keys| match keys.contains(&true) {
+                true => keys,
+                false => [true; 16],
+            })
+            .collect()
+    });
+
+    let jitter = (opts.ipf_jitter > 0).then(|| Jitter::new(opts.ipf_jitter, opts.jitter_seed));
+    let console = opts.console.then(console::spawn);
+    let display = Display::open(cpus.len(), scale, opts.hires_snap);
+    let creator = display.texture_creator();
+    let mut gui = SDLGui::new(
+        display,
+        &creator,
+        opts.announce,
+        jitter,
+        opts.perf_hud,
+        console,
+    );
+    if let Some(path) = &opts.input_file {
+        gui.set_input_display(InputDisplay::new(path.clone()));
+    }
+    if let Some(used) = keypad_overlay {
+        gui.set_keypad_overlay(used);
+    }
+    gui.set_keymap(0, &config.keys);
+    gui.bind_buttons(&config.buttons);
+    gui.bind_keys(0, &bindings);
+    gui.set_display_fps(opts.fps);
+    gui.set_palette(opts.colors.palette());
+    if opts.calibrate {
+        gui.start_calibration();
+    }
+    if opts.frame_stats {
+        gui.start_frame_stats();
+    }
+    // Rewinding or slowing down in the background would desync a replay
+    if !replaying {
+        gui.set_background_speed(opts.background_speed as f32 / 100.0);
+    }
+    if let Some(minutes) = opts.confirm_overwrite {
+        gui.set_confirm_overwrite(Duration::from_secs(minutes * 60));
+    }
+    gui.set_sound((!opts.mute).then_some(Sound {
+        volume: opts.volume as f32 / 100.0,
+        tone: opts.tone,
+        timed: opts.timed_sound,
+    }));
+    // The options override the config file
+    gui.set_fast_forward(FastForward {
+        max: opts.fast_forward_max.unwrap_or(config.fast_forward.max),
+        ramp: opts
+            .fast_forward_ramp
+            .map_or(config.fast_forward.ramp, Duration::from_secs),
+        mute: opts.fast_forward_mute || config.fast_forward.mute,
+    });
+    // --filter replaces the config file's list; --phosphor-frames applies
+    // to either
+    let frames = opts.phosphor_frames;
+    gui.set_filters(match opts.filter.is_empty() {
+        true => config
+            .filters
+            .iter()
+            .map(|&filter| match filter {
+                Filter::Phosphor { .. } => Filter::Phosphor { frames },
+                filter => filter,
+            })
+            .collect(),
+        false => opts.filter.iter().map(|f| f.filter(frames)).collect(),
+    });
+    gui.set_stick(Stick {
+        deadzone: opts.stick_deadzone,
+        hysteresis: opts.stick_hysteresis,
+    });
+    if opts.record_replay.is_some() {
+        gui.start_recording(Replay::new(seed));
+    }
+    if let Some(replay) = replay {
+        gui.start_playback(replay);
+    }
+
+    let mut machine = Machine::new(cpus, ipf);
+    machine.deterministic = opts.deterministic;
+    machine.input_polls = opts.input_polls.max(1);
+    // BRK only pauses with the console there to resume from
+    machine.debugger.set_guest_breakpoints(opts.console);
+    if opts.rewind_seconds > 0 && !replaying {
+        let capacity = opts.rewind_seconds * 60 / opts.rewind_interval;
+        machine.start_rewind(capacity as usize, opts.rewind_interval);
+    }
+    gui.run(&mut machine);
+
+    if let (Some(path), Some(replay)) = (&opts.record_replay, gui.recording()) {
+        match fs::write(path, replay.to_string()) {
+            Ok(()) => {
+                let (frames, rerecords) = (replay.len(), replay.rerecords);
+                println!(
+                    "{}",
+                    tr_args("replay-saved", &[&frames, &rerecords, &path.display()])
+                );
+            }
+            Err(e) => eprintln!("{}", tr_args("err-write-replay", &[&path.display(), &e])),
+        }
+    }
+
+    if let Some(stats) = gui.frame_stats() {
+        println!("{}", stats.report());
+    }
+
+    if let Some(calibration) = gui.calibration() {
+        match calibration.suggest() {
+            Some(suggested) => {
+                println!("{}", tr_args("calibrate-suggest", &[&suggested, &ipf]));
+                if let (Some(path), Some(hash)) = (&config_path, rom_hash) {
+                    match config::save_ipf(path, hash, suggested) {
+                        Ok(()) => println!("{}", tr_args("calibrate-saved", &[&path.display()])),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            }
+            None => println!("{}", tr("calibrate-none")),
+        }
+    }
+}
+
+/// Runs a test script deterministically, with a seeded RNG and the fixed
+/// RTC, and exits with status 1 if any assertion fails.
+fn test(rom_file: &str, script: &Path, seed: u64, trace: Option<&Path>, limits: &LimitOptions) {
+    let steps = fs::read_to_string(script)
+        .map_err(|e| tr_args("err-read-script", &[&script.display(), &e]))
+        .and_then(|src| testscript::parse(&src));
+
+    let steps = steps.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut sandbox = limits.sandbox();
+    let mut cpu = Chip8::new(seed);
+    load_sandboxed(&mut cpu, rom_file, &sandbox);
+    let mut tracer = start_trace(trace, &cpu);
+
+    let failed = testscript::run(&mut cpu, &steps, &mut sandbox, tracer.as_mut());
+    finish_trace(trace, tracer);
+    let failed = failed.unwrap_or_else(|e| exit_run(&e));
+    if failed > 0 {
+        eprintln!("{}", tr_args("test-summary-failed", &[&failed]));
+        process::exit(1);
+    }
+}
+
+/// Runs a ROM headless, fed the key steps of an optional test script, and
+/// records its display. GIF delays are whole hundredths of a second, so they
+/// are taken from each frame's guest time rounded down, which keeps the
+/// total length exact at any frame rate.
+fn record_gif(rom_file: &str, output: &Path, opts: &GifOptions) {
+    let steps = match &opts.script {
+        Some(script) => fs::read_to_string(script)
+            .map_err(|e| tr_args("err-read-script", &[&script.display(), &e]))
+            .and_then(|src| testscript::parse(&src)),
+        None => Ok(vec![]),
+    };
+    let steps = steps.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut sandbox = opts.limits.sandbox();
+    let mut cpu = Chip8::new(opts.seed);
+    load_sandboxed(&mut cpu, rom_file, &sandbox);
+    let mut tracer = start_trace(opts.trace.as_deref(), &cpu);
+
+    let fail = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}", tr_args("err-write-gif", &[&output.display(), e]));
+        process::exit(1);
+    };
+    let file = fs::File::create(output).unwrap_or_else(|e| fail(&e));
+    let mut gif = GifRecorder::new(io::BufWriter::new(file), opts.colors.palette(), opts.scale)
+        .unwrap_or_else(|e| fail(&e));
+
+    let mut steps = steps.iter().peekable();
+    let mut shown = 0;
+    for frame in 0..opts.frames {
+        while let Some((_, step)) = steps.next_if(|&&(at, _)| at <= frame) {
+            match *step {
+                Step::Press(key) => {
+                    cpu.set_keypad(key, true);
+                }
+                Step::Release(key) => {
+                    cpu.set_keypad(key, false);
+                }
+                Step::Assert(..) => {}
+            }
+        }
+
+        for _ in 0..opts.ipf {
+            if let Some(tracer) = &mut tracer {
+                tracer.record(&cpu);
+            }
+            if let Err(e) = sandbox.step(&mut cpu) {
+                finish_trace(opts.trace.as_deref(), tracer);
+                exit_run(&e);
+            }
+            if cpu.waiting_for_vblank() {
+                break;
+            }
+        }
+        cpu.tick_timers();
+
+        // Capture whenever the frame count crosses a GIF frame boundary
+        let frame = frame + 1;
+        if frame * opts.fps / 60 > (frame - 1) * opts.fps / 60 {
+            let time = frame * 100 / 60;
+            if let Err(e) = gif.frame(&cpu, (time - shown) as u16) {
+                fail(&e);
+            }
+            shown = time;
+        }
+    }
+
+    finish_trace(opts.trace.as_deref(), tracer);
+    match gif.finish() {
+        Ok(frames) => println!("{}", tr_args("gif-saved", &[&frames, &output.display()])),
+        Err(e) => fail(&e),
+    }
+}
+
+/// Runs every ROM in `dir` headless, several at once, and writes a GIF
+/// screenshot and info JSON of each to the output directory, named after
+/// the ROM file. Exits with status 1 if any ROM couldn't be captured; a
+/// guest fault is recorded in the JSON rather than counted as a failure.
+fn batch_capture(dir: &Path, opts: &CaptureOptions) {
+    let roms = fs::read_dir(dir).and_then(|entries| {
+        let mut roms = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                roms.push(entry.path());
+            }
+        }
+        Ok(roms)
+    });
+    let mut roms = roms.unwrap_or_else(|e| {
+        eprintln!("{}", tr_args("err-read-dir", &[&dir.display(), &e]));
+        process::exit(1);
+    });
+    roms.sort();
+
+    if let Err(e) = fs::create_dir_all(&opts.out) {
+        eprintln!(
+            "{}",
+            tr_args("err-write-capture", &[&opts.out.display(), &e])
+        );
+        process::exit(1);
+    }
+
+    let jobs = opts
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..jobs.min(roms.len()) {
+            s.spawn(|| {
+                while let Some(rom) = roms.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(e) = capture_rom(rom, opts) {
+                        eprintln!("{}", e);
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    let failed = failed.into_inner();
+    let captured = roms.len() - failed;
+    let out = opts.out.display();
+    println!(
+        "{}",
+        tr_args("capture-summary", &[&captured, &roms.len(), &out])
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Captures one ROM for `batch_capture`. Each ROM starts from the same
+/// seed, so captures don't depend on which thread ran them.
+fn capture_rom(rom_file: &Path, opts: &CaptureOptions) -> Result<(), String> {
+    let name = rom_file.file_name().unwrap_or_default().to_string_lossy();
+    let rom = fs::read(rom_file).map_err(|e| load_error(&name, &LoadError::Io(e)))?;
+
+    let mut cpu = Chip8::new(opts.seed);
+    cpu.load_rom_bytes(&rom)
+        .map_err(|e| load_error(&name, &e))?;
+    let capture = capture::run(&mut cpu, &rom, opts.frames, opts.ipf);
+
+    let screenshot = format!("{}.gif", name);
+    let path = opts.out.join(&screenshot);
+    let fail = |e: &dyn std::fmt::Display| tr_args("err-write-capture", &[&path.display(), e]);
+    let file = fs::File::create(&path).map_err(|e| fail(&e))?;
+    let mut gif = GifRecorder::new(io::BufWriter::new(file), opts.colors.palette(), opts.scale)
+        .map_err(|e| fail(&e))?;
+    gif.frame(&cpu, 0).map_err(|e| fail(&e))?;
+    gif.finish().map_err(|e| fail(&e))?;
+
+    let path = opts.out.join(format!("{}.json", name));
+    fs::write(&path, capture.to_json(&name, &screenshot))
+        .map_err(|e| tr_args("err-write-capture", &[&path.display(), &e]))
+}
+
+/// The framebuffer as a binary PGM, with the default palette's shades.
+fn pgm(cpu: &Chip8) -> Vec<u8> {
+    let shades = Palette::default().colors;
+    let mut out = format!("P5\n{} {}\n255\n", HIRES_WIDTH, HIRES_HEIGHT).into_bytes();
+    out.extend(cpu.get_video().iter().map(|&p| shades[p as usize & 3][0]));
+    out
+}
+
+/// Runs every instance for `frames` frames with no window or input,
+/// optionally saving each frame, for checking ROMs in CI. Stops early once
+/// every instance has run EXIT.
+fn headless(mut cpus: Vec<Chip8>, frames: u64, opts: &Options) {
+    if let Some(dir) = &opts.dump_dir {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("{}", tr_args("err-dump-frame", &[&dir.display(), &e]));
+            process::exit(1);
+        }
+    }
+
+    // Headless runs don't read the config file, so their output depends
+    // only on the options
+    let ipf = opts
+        .instructions_per_frame()
+        .unwrap_or(INSTRUCTIONS_PER_FRAME);
+    let mut over_budget = vec![false; cpus.len()];
+    for frame in 1..=frames {
+        for (instance, cpu) in cpus.iter_mut().enumerate() {
+            let warned = &mut over_budget[instance];
+            let batch = cpu.run_batch(ipf, |cpu| {
+                for event in cpu.events() {
+                    if let GuestEvent::DrawBudgetExceeded { pc, rows } = *event {
+                        if !mem::replace(warned, true) {
+                            let pc = format!("{:#05X}", pc);
+                            eprintln!("{}", tr_args("warn-draw-budget", &[&instance, &rows, &pc]));
+                        }
+                    }
+                }
+                cpu.exited()
+            });
+            if let Err(e) = batch {
+                eprintln!("{}", tr_args("err-guest", &[&e]));
+                process::exit(1);
+            }
+            cpu.tick_timers();
+
+            if let Some(dir) = &opts.dump_dir {
+                let path = dir.join(format!("frame-{:06}-{}.pgm", frame, instance));
+                if let Err(e) = fs::write(&path, pgm(cpu)) {
+                    eprintln!("{}", tr_args("err-dump-frame", &[&path.display(), &e]));
+                    process::exit(1);
+                }
+            }
+        }
+
+        if cpus.iter().all(Chip8::exited) {
+            break;
+        }
+    }
+}
+
+fn info(rom_file: &String) {
+    let rom = fs::read(rom_file).unwrap_or_else(|e| {
+        eprintln!("{}", tr_args("err-read-rom", &[rom_file, &e]));
+        process::exit(1);
+    });
+
+    let info = analysis::analyze(&rom);
+    let list = |set: &[bool; 16]| {
+        let items: Vec<String> = (0..16)
+            .filter(|&n| set[n])
+            .map(|n| format!("{:X}", n))
+            .collect();
+
+        match items.is_empty() {
+            true => "-".to_string(),
+            false => items.join(" "),
+        }
+    };
+    let yes_no = |b: bool| tr(if b { "info-yes" } else { "info-no" }).to_string();
+    let guess = analysis::guess_quirks(&rom);
+    let maybe = |guess: Option<bool>| guess.map_or(tr("info-unclear").to_string(), yes_no);
+
+    let size = tr_args("info-bytes", &[&info.size]);
+    let rows = [
+        ("info-size", size),
+        ("info-keys", list(&info.keys.keys)),
+        ("info-waits", yes_no(info.keys.waits_for_key)),
+        ("info-sound", yes_no(info.sound)),
+        ("info-random", yes_no(info.random)),
+        ("info-bcd", yes_no(info.bcd)),
+        ("info-draw", list(&info.draw_heights)),
+        ("info-shift-vy", maybe(guess.shift_vy)),
+        ("info-increment", maybe(guess.load_store_increment)),
+    ];
+
+    for (label, value) in rows {
+        println!("{:<18}{}", tr(label), value);
+    }
+}
+
+fn describe(problem: &Problem) -> String {
+    let hex = |value: u16| format!("{:#06X}", value);
+
+    match *problem {
+        Problem::Empty => tr("check-empty").to_string(),
+        Problem::TooLarge { size, max } => tr_args("check-too-large", &[&size, &max]),
+        Problem::InvalidOpcode { op, .. } => tr_args("check-invalid-opcode", &[&hex(op)]),
+        Problem::ExtensionOpcode { op, .. } => tr_args("check-extension-opcode", &[&hex(op)]),
+        Problem::LeavesRom { target, .. } => tr_args("check-leaves-rom", &[&hex(target)]),
+        Problem::IndexOutOfBounds { index, .. } => tr_args("check-index-oob", &[&hex(index)]),
+        Problem::WritesInterpreterArea { index, .. } => {
+            tr_args("check-writes-interpreter", &[&hex(index)])
+        }
+    }
+}
+
+fn check(rom_file: &String, extensions: bool) {
+    let rom = fs::read(rom_file).unwrap_or_else(|e| {
+        eprintln!("{}", tr_args("err-read-rom", &[rom_file, &e]));
+        process::exit(1);
+    });
+
+    let problems = analysis::check(&rom, extensions);
+    for problem in &problems {
+        let severity = tr(if problem.is_error() {
+            "check-error"
+        } else {
+            "check-warning"
+        });
+        let location = match *problem {
+            Problem::InvalidOpcode { addr, .. }
+            | Problem::ExtensionOpcode { addr, .. }
+            | Problem::LeavesRom { addr, .. }
+            | Problem::IndexOutOfBounds { addr, .. }
+            | Problem::WritesInterpreterArea { addr, .. } => format!("{}:{:#05X}", rom_file, addr),
+            Problem::Empty | Problem::TooLarge { .. } => rom_file.to_string(),
+        };
+
+        println!("{}: {}: {}", location, severity, describe(problem));
+    }
+
+    let errors = problems.iter().filter(|p| p.is_error()).count();
+    let warnings = problems.len() - errors;
+    println!("{}", tr_args("check-summary", &[&errors, &warnings]));
+
+    if errors > 0 {
+        process::exit(1);
+    }
 }
 
 pub fn main() {
-    let args = Args::parse();
-    let rng = rand::random::<u8>;
-    let mut cpu = Chip8::new(rng);
-    cpu.load_rom(&args.rom_file);
-    let mut gui = SDLGui::new(cpu, args.scale);
-    gui.run();
+    i18n::set_lang(lang_from_args().unwrap_or_else(Lang::from_env));
+
+    let matches = localized_command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    match cli.command {
+        Some(Command::Run {
+            rom_files,
+            scale,
+            opts,
+        }) => run(&rom_files, scale, &opts),
+        Some(Command::GenRom { kind, output }) => {
+            if let Err(e) = fs::write(&output, diagroms::build(kind)) {
+                eprintln!("{}", tr_args("err-write-rom", &[&output.display(), &e]));
+                process::exit(1);
+            }
+        }
+        Some(Command::Completions { shell }) => {
+            let mut cmd = localized_command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+        }
+        Some(Command::Info { rom_file }) => info(&rom_file),
+        Some(Command::Check {
+            rom_file,
+            extensions,
+        }) => check(&rom_file, extensions),
+        Some(Command::Disasm { rom_file }) => {
+            let rom = fs::read(&rom_file).unwrap_or_else(|e| {
+                eprintln!("{}", tr_args("err-read-rom", &[&rom_file, &e]));
+                process::exit(1);
+            });
+            println!("{}", disasm::listing(&rom));
+        }
+        Some(Command::Manpage) => {
+            let man = clap_mangen::Man::new(localized_command());
+            if let Err(e) = man.render(&mut io::stdout()) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        Some(Command::Test {
+            rom_file,
+            script,
+            seed,
+            trace,
+            limits,
+        }) => test(&rom_file, &script, seed, trace.as_deref(), &limits),
+        Some(Command::RecordGif {
+            rom_file,
+            output,
+            gif,
+        }) => record_gif(&rom_file, &output, &gif),
+        Some(Command::BatchCapture { dir, capture }) => batch_capture(&dir, &capture),
+        None => run(&[cli.rom_file.unwrap()], cli.scale, &cli.opts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Help text comes only from the message table, so an argument left out
+    // of `localized_command` would have none
+    #[test]
+    fn every_argument_has_help() {
+        fn check(cmd: &clap::Command) {
+            assert!(cmd.get_about().is_some(), "{} has no about", cmd.get_name());
+            for arg in cmd.get_arguments() {
+                assert!(
+                    arg.get_help().is_some(),
+                    "{} --{} has no help",
+                    cmd.get_name(),
+                    arg.get_id()
+                );
+            }
+            for sub in cmd.get_subcommands() {
+                check(sub);
+            }
+        }
+
+        check(&localized_command());
+    }
 }